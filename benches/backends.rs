@@ -0,0 +1,67 @@
+use ark_bn254::Fr;
+use ark_std::rand::rngs::StdRng;
+use ark_std::rand::SeedableRng;
+use criterion::{criterion_group, criterion_main, Criterion};
+use SKATE::lifecycle::TaskState;
+use SKATE::plonk::{self, Srs};
+use SKATE::position::Position;
+use SKATE::proof_system::{plonk_matrices, Groth16Backend, PlonkBackend, ProofSystem};
+use SKATE::rollup::RollupCircuit;
+use SKATE::Skate::{create_robot, create_task, SkateParameters, IDLE_TASK_ID};
+
+/* Groth16 against PLONK on the native rollup circuit (see rollup::RollupCircuit), which needs no
+   compiled artifact: keys (the trusted setup of Groth16, the preprocessing of PLONK from the reference
+   string), proof and verification of a round. The reference string is generated once, outside of the
+   measures, for the largest circuit. The larger sizes take minutes per sample and are only run with
+   the bench-complex-all feature. Results in benches/RESULTS.md */
+
+#[cfg(not(feature = "bench-complex-all"))]
+const SIZES: [(usize, usize); 2] = [(1, 1), (2, 2)];
+#[cfg(feature = "bench-complex-all")]
+const SIZES: [(usize, usize); 4] = [(1, 1), (2, 2), (3, 3), (3, 6)];
+
+// A round of n idle robots and m pending tasks on distinct positions, without prerequisites
+fn circuit(n: usize, m: usize) -> RollupCircuit {
+    let robots: Vec<_> = (0..n)
+        .map(|i| {
+            let position = Position::new(2 * i as u64, 0, 0).unwrap();
+            create_robot(Fr::from(i as u64), [Fr::from(IDLE_TASK_ID); 2], [Position::default(), position])
+                .unwrap()
+        })
+        .collect();
+    let tasks: Vec<_> = (0..m)
+        .map(|j| create_task(Fr::from(j as u64), Position::new(3 * j as u64 + 1, 1, 0).unwrap()).unwrap())
+        .collect();
+    let states = (0..m).map(|j| (Fr::from(j as u64), TaskState::Pending.value())).collect();
+    RollupCircuit::new(&robots, &tasks, &SkateParameters::default(), Fr::from(1), &states).unwrap()
+}
+
+fn bench_backend<S: ProofSystem>(c: &mut Criterion, system: &S, circuit: &RollupCircuit, rng: &mut StdRng) {
+    let name = format!("{} {}x{}", system.backend(), circuit.robots.len(), circuit.tasks.len());
+    let mut group = c.benchmark_group(name.as_str());
+    group.sample_size(10);
+    group.bench_function("keys", |b| b.iter(|| system.keys(circuit.clone(), &mut StdRng::seed_from_u64(0)).unwrap()));
+    let (pk, vk) = system.keys(circuit.clone(), rng).unwrap();
+    group.bench_function("prove", |b| b.iter(|| system.prove(&pk, circuit.clone(), rng).unwrap()));
+    let proof = system.prove(&pk, circuit.clone(), rng).unwrap();
+    let public_inputs = circuit.public_inputs();
+    assert!(system.verify(&vk, &public_inputs, &proof).unwrap());
+    group.bench_function("verify", |b| b.iter(|| system.verify(&vk, &public_inputs, &proof).unwrap()));
+    group.finish();
+    println!("{}: proof of {} strings", name, system.encode(&proof).len());
+}
+
+fn bench_backends(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let circuits: Vec<RollupCircuit> = SIZES.iter().map(|&(n, m)| circuit(n, m)).collect();
+    let gates = circuits.iter().map(|circuit| plonk::gates(&plonk_matrices(circuit.clone()).unwrap()));
+    let srs = Srs::setup(gates.max().unwrap(), &mut rng);
+    let plonk = PlonkBackend::new(srs);
+    for circuit in &circuits {
+        bench_backend(c, &Groth16Backend, circuit, &mut rng);
+        bench_backend(c, &plonk, circuit, &mut rng);
+    }
+}
+
+criterion_group!(benches, bench_backends);
+criterion_main!(benches);
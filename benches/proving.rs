@@ -0,0 +1,77 @@
+use ark_bn254::Fr;
+use ark_std::rand::rngs::StdRng;
+use ark_std::rand::SeedableRng;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::path::PathBuf;
+use SKATE::hashes::{robot_root, round_seed, task_root};
+use SKATE::inputs::{build_rollup_inputs, RollupInputs};
+use SKATE::lifecycle::{task_state_witness, TaskState};
+use SKATE::position::Position;
+use SKATE::prover::CircuitProver;
+use SKATE::registry::{Artifact, CircuitArtifacts, CircuitRegistry};
+use SKATE::Skate::{create_robot, create_task, skate_assignments, SkateParameters, IDLE_TASK_ID};
+
+/* Proof latency of the registered circuits (SKATE_CIRCUITS, the shipped src/client/circuits.toml by
+   default), without the road map. "reloaded" runs CircuitProver::load (reading and checking the zkey)
+   before every proof, "cached" proves with a CircuitProver loaded once. Neither is the former circom
+   client loop (read_zkey and CircomConfig::new per proof), which went with the circom circuits. The
+   circuits must be set up with the default parameters (see Skate::SkateParameters). Results in
+   benches/RESULTS.md */
+
+// A round of n idle robots and m pending tasks on distinct positions, without prerequisites
+fn round_inputs(n: usize, m: usize, parameters: &SkateParameters) -> RollupInputs {
+    let robots: Vec<_> = (0..n)
+        .map(|i| {
+            let position = Position::new(2 * i as u64, 0, 0).unwrap();
+            create_robot(Fr::from(i as u64), [Fr::from(IDLE_TASK_ID); 2], [Position::default(), position])
+                .unwrap()
+        })
+        .collect();
+    let tasks: Vec<_> = (0..m)
+        .map(|j| create_task(Fr::from(j as u64), Position::new(3 * j as u64 + 1, 1, 0).unwrap()).unwrap())
+        .collect();
+    let root = robot_root(&robots).unwrap().0;
+    let parameters = SkateParameters { seed: round_seed(root, Fr::from(1)), ..*parameters };
+    let states = (0..m).map(|j| (Fr::from(j as u64), TaskState::Pending.value())).collect();
+    let assignments = skate_assignments(&robots, &tasks, &parameters, None).unwrap();
+    let task_state = task_state_witness(&states, &robots, &tasks, &assignments).unwrap();
+    build_rollup_inputs(&robots, &tasks, root, task_root(&tasks).unwrap())
+        .with_round(&tasks, Fr::from(1), &parameters, &task_state)
+        .unwrap()
+}
+
+fn bench_circuit(c: &mut Criterion, circuit: &CircuitArtifacts) {
+    let inputs = round_inputs(circuit.robots, circuit.tasks, &SkateParameters::default());
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut group = c.benchmark_group(format!("proof latency {}", circuit.key()));
+    group.sample_size(10);
+
+    group.bench_function("reloaded", |b| {
+        b.iter(|| CircuitProver::load(circuit).unwrap().prove(&inputs, &mut rng).unwrap())
+    });
+
+    let prover = CircuitProver::load(circuit).unwrap();
+    group.bench_function("cached", |b| b.iter(|| prover.prove(&inputs, &mut rng).unwrap()));
+    group.finish();
+}
+
+fn bench_proving(c: &mut Criterion) {
+    let path = std::env::var("SKATE_CIRCUITS").unwrap_or("src/client/circuits.toml".to_string());
+    let registry = match CircuitRegistry::load(&PathBuf::from(&path)) {
+        Ok(registry) => registry,
+        Err(e) => {
+            eprintln!("No circuit to benchmark : {}", e);
+            return;
+        }
+    };
+    if let Err(e) = registry.check(&[Artifact::Zkey]) {
+        eprintln!("Invalid circuit registry : {}", e);
+        return;
+    }
+    for circuit in registry.circuits().iter().filter(|circuit| !circuit.road_map) {
+        bench_circuit(c, circuit);
+    }
+}
+
+criterion_group!(benches, bench_proving);
+criterion_main!(benches);
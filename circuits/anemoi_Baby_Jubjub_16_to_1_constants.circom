@@ -1,173 +0,0 @@
-//test
-pragma circom 2.0.0;
-
-function C_16_to_1() {
-    return
-    [   [37,
-        3751828524803055471428227881618625174556947755988347881191159153764975591158,
-        1949599869510645482615188660368033333056398404211414284257663219972027283546,
-        15418714136223760726336850467094720601517297152372747941595383567153544466510,
-        2051364020635386491684330813940258081446633848044222793769786224456430304135,
-        10090179034993010558102218011894365265553139077003095604632067209817734227933,
-        8028159766450428260247672764635944810437448373505931890575593008026684240892,
-        6038640551694734419700304778536210706968140418756878935180026019574185855557],
-        [13352247125433170118601974521234241686699252132838635793584252509352796067497,
-        21001839722121566863419881512791069124083822968210421491151340238400176843969,
-        7777973433348993759823475421272986966077614788945193384284752157197566919783,
-        7510868303125272354383482129054989965437700001696569061378387286787540912966,
-        3489667450678756499348856536618575873859828361246995371459655381608910764645,
-        17226535916205011485231748641928773227250499339475166429109201522929304761449,
-        15989113623472576107436323171751475042196693205554832684001569595629659681200,
-        264623000782376699607622489905098823779156185650493463902661083161601684394],
-        [8959866518978803666083663798535154543742217570455117599799616562379347639707,
-        21722442537234642741320951134727484119993387379465291657407115605240150584902,
-        14978255763535453150583514941371658296885530039770722142017236155522063862875,
-        2486271948109263578326941575775016622852446417676777862628719610012816205134,
-        1931134671373155129200103831772843017109890345347006596470154032714674918653,
-        18578887337466783668058778167536521099865544815029332185552017473067261853249,
-        11770221312376822437251573946075117719358093029360638308545532619343162720189,
-        18622769053026735680927105671445818092136744270193036991335195104598025230669],
-        [3222831896788299315979047232033900743869692917288857580060845801753443388885,
-        5574110054747610058729632355948568604793546392090976147435879266833412620404,
-        6923652180771635546931891136479594563054615964717465958928739801840041650090,
-        17165098443539374195961763629412427208317340468839803881902169270575466592477,
-        2398209970396913879124580466523009053162381236915444908898110942762022678813,
-        13140582292287772659594968261291341738704757820200685852445183072114091018706,
-        4317490574843205854234717776375333828405130172023929618910454428353393909192,
-        21439434405862530040682811849687469707296443744630870048946677267520684101138],
-        [11437915391085696126542499325791687418764799800375359697173212755436799377493,
-        19347108854758320361854968987183753113398822331033233961719129079198795045322,
-        11678722465295853606385043319120728107430471738554216518209749383951610070884,
-        298634292953774973796833203638064688368255214341699882489316578230867010994,
-        15732584836729695651624564713724758818473010867867193029090920122268304074078,
-        10853722952278485820345918108400806221123389660094597252161020950481331218685,
-        7060492761285679344143370810580159070356991642618432492242112002087920746145,
-        6394478730219774520007454760776838290601262440431506251110113768039393062807],
-        [14725846076402186085242174266911981167870784841637418717042290211288365715997,
-        17733032409684964025894538244134113560864261458948810209753406163729963104066,
-        13922241985592995290253950498211017441065915694679478649621852463687790678098,
-        2715332607428691280985384408158125834364732338319072744128115935192202839051,
-        3199743087930844503011218184701440601055854275974995339562911870213727105787,
-        21349335770632126633804476477550939435359400562878780842650188211523537600462,
-        21327019343092847588748272303322227369299217736100837703175817452635655296159,
-        13404866120515722721975964926061221299843527622868476552220982880360537085388],
-        [3625896738440557179745980526949999799504652863693655156640745358188128872126,
-        16641102106808059030810525726117803887885616319153331237086309361060282564245,
-        401770237450672237389925642936019223184566782452617540147388711832717193317,
-        374528379742030564629213859641498310600673792401266330320357078275802016761,
-        5858321187638419357374933653348196986624905659519446389484580338005964799320,
-        8362456426639961639652226750137608888512581643066799262160018699138293166126,
-        17384486449037122975763387773482591163145794785878203154285370129151274236925,
-        5635967514748871973962347808720265667703960824328879051539332489993973327173],
-        [463291105983501380924034618222275689104775247665779333141206049632645736639,
-        9245970744804222215259369270991414441925747897718226734085751033703871913242,
-        5179269137741251963657651082986337071287701632468998552354829723675107591959,
-        19095727768515297673050373763484467089496400227788592149888060564100975126966,
-        16264987956859102199080131937334065173735632037822305074999943314146708369124,
-        10800727433834717920904700833652973438864333859869652817357198388778400055797,
-        2947480405542395757955813745799344148144538631068264372423639085416261020113,
-        21563073271114144018357607118890301447910222266180693637588348374503882855805],
-        [17443852951621246980363565040958781632244400021738903729528591709655537559937,
-        18243401795478654990110719981452738859015913555820749188627866268359980949315,
-        10684192457422730891811923419937385412089832793504426819231331969125627448716,
-        17470332954208716118252250751112311282399887258695813542803649629307353902995,
-        1295901914741613217058485060919169647174282721985915585351519305696426753068,
-        11794571321338127805416635335443208428204009973447757830246784684604520045520,
-        12888253630376589775942304006878507001809713809404460726149281705993418714708,
-        18806559116738933056388066181654022850009634282007187092844172114467688120804],
-        [10761214205488034344706216213805155745482379858424137060372633423069634639664,
-        18200337361605220875540054729693479452916227111908726624753615870884702413869,
-        13591751711030312278826555518140666328583891165068799640705167493071392966003,
-        3924632121730109823825356160917049198029194277703755080337793114638247487729,
-        4286775244231158514001957472365095970291718332883473938881002847146654742382,
-        13500335851098562957871390650929713672395033544669304695355139523343274987067,
-        18845183240389924554681651426826219920195518385103080147657262535182191001958,
-        102962827482765569016241172313580928679319029471348384113876454721781270285]
-    ];
-}
-
-function D_16_to_1() {
-    return
-    [
-        [8755297148735710088898562298102910035419345760166413737479281674630323398284,
-        16133435893292874812888083849160666046321318009323051176910097996974633748758,
-        11515618990709373787379985173053774331925623019658433031330089380733125663964,
-        3140635413281121928031621189427733725520500843764228833901471822105691965090,
-        3188616203145313506326753431360868318002200621960878082717504210765986481932,
-        10008016665561596437216064082093920284478873054000825789979440866155472072383,
-        18101068709693882374459129166710847442975975903368357555659971886556568787510,
-        13950112603514624007459917801878399391306803947665300882367798659811473056260],
-        [5240474505904316858775051800099222288270827863409873986701694203345984265770,
-        16516377322346822856154252461095180562000423191949949242508439100972699801595,
-        476922786283158715862786914720798531099069374797036586995338337321530100227,
-        253962683757345429599173577407348744141498063908908753020839747678361707189,
-        9648092736763395387512199880058531765115989505984509459743737573857140238085,
-        277303778509034015620109692890398812328463286877721070094735198629907405925,
-        9194952798451466872922294554588448240887450705822082804724108493522409027844,
-        13197268156176978160888156239266633162818414085379774210426797929337562180740],
-        [9012679925958717565787111885188464538194947839997341443807348023221726055342,
-        3513323292129390671339287145562649862242777741759770715956300048086055264273,
-        15841791142978385266153197242607799254788139164593550995618112102495217260681,
-        3393952355250103813073003831915704794437399018860103205161461837752827216719,
-        16254145983966561176893817983001128301247206029055506335644525991812094609455,
-        9794241226279573357977510026286476077824663301402872477427840915617054715087,
-        13140646513864480362267916136700420310930005068598874080158361284085102284195,
-        17831757363090829079491604483338406735508792308477269045051417531047367448760],
-        [21855834035835287540286238525800162342051591799629360593177152465113152235615,
-        5945179541709432313351711573896685950772105367183734375093638912196647730870,
-        4479133420442366764858910899571412036138029301922539637939986764754716983374,
-        14764724710908013533065163347408791895083097282405374049845282514256999539540,
-        13413167143218119029175632079606970852480501133006189473482853917800964305093,
-        1047882041328361451871037581896973231844680518956470969731377530605405816022,
-        2379861636558662881608397428856312935157846423644410215933654109036855408676,
-        17340368576154422541604648123435734865849295995297346928073270709911548254707],
-        [11227229470941648605622822052481187204980748641142847464327016901091886692935,
-        874490282529106871250179638055108647411431264552976943414386206857408624500,
-        12278758517614824301331600260393058857096299434602309294956698015737472368497,
-        942815372970653787919770099814942651716426386750289148168131490783586922386,
-        7903853950359865056448747759731958805825180722384922347713160579602624169070,
-        1805577513967314089641524607186950990845726717693401467182917077843832980330,
-        8167418635649375848536587641241651453692122253081932187001013351642569209958,
-        5339967713159906497948828212705616725736529049941002227972408879301444180705],
-        [8277823808153992786803029269162651355418392229624501612473854822154276610437,
-        14911320361190879980016686915823914584756893340104182663424627943175208757859,
-        8284941689807820207681039715044518592063401937947166554646561560684476554709,
-        19010420211180699539835259325153449287592926458363291481782895499531554825058,
-        11021918725296143352562339251527086078288047078128354130161116979334679275394,
-        6063853984216809125580615251898254606413396167697180185949844804096862941105,
-        16196608869352398315622021409544890153966006893783932526212479267401127338970,
-        6113018755351708922397870653551170136310452779597567657361038456833411782284],
-        [20904607884889140694334069064199005451741168419308859136555043894134683701950,
-        15657880601171476575713502187548665287918791967520790431542060879010363657805,
-        18491203356362273967844248394767855838870961193348426529654831733910047005312,
-        18508106526351540414259916566377882139969411679657571808759666381119989442535,
-        15518987367861219797706882509914902839997642228884891920867315185631752408694,
-        16803708054921420944455599059483259524255485415513319689942409216792262442153,
-        14092566518154175293417964669446314323953127710773384718106561682421581719503,
-        182610692442359765165081325951274880311429748270056897463917804971683463836],
-        [1902748146936068574869616392736208205391158973416079524055965306829204527070,
-        14311738005510898661766244714944477794557156116636816483240167459479765463026,
-        7429448151156837373469467072083100551023964213499903752862733467002441364561,
-        21390051809628791202038569707485777782915006285179993839327830588195166513347,
-        10086400031585886318769574031165697891158236777322846817383138883022499939105,
-        3402724956620160905065566380263550938657105802451269456140049627682373292431,
-        5704549241002706977214289624285269261550104126514576490943495546512380958915,
-        270462343311615488917833873386237524567559360256967694513394410731596953075],
-        [14452570815461138929654743535323908350592751448372202277464697056225242868484,
-        18878429879072656191963192145256996413709289475622337294803628783509021017215,
-        8503632293725641056969336128885343093888063075418330376760581801826107739434,
-        15333917818209534402586043414964816177880461016970213589264765742774691807492,
-        12574817684194997314339929619859271655207219562785490028454265150521173336782,
-        21854072538850170767169503347162255218607114017328407169749186199457448295887,
-        11214583288724225750546376605215626317277247005733771201690484256462685171626,
-        14971451883663004504540295401258428217277303477382493850488768426644357231807],
-        [10548134661912479705005015677785100436776982856523954428067830720054853946467,
-        21613568037783775488400147863112554980555854603176833550688470336449256480025,
-        14189394139917775854991589196703441983328373018449222017993509276187387254977,
-        4566419578315481519166769794384372066456019607444673946558001178521099390482,
-        18343893606269096022291023000920015951270906745149567201742840642386915324352,
-        4449796789355884108385473887006303347196024759600438510918428802035908740073,
-        19949715491322113940293344994778157208609303152898909442957557036066971457132,
-        20934301058830665650422497106790079357441604196729208305215768903889772875161]
-    ];
-}
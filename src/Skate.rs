@@ -1,19 +1,31 @@
+use crate::error::{Result, SkateError};
+use crate::graph::DistanceTable;
+use crate::position::Position;
 use ark_bn254::Fr;
 use ark_ff::PrimeField;
+use serde::Deserialize;
 use std::fmt::{Display, Formatter};
 use ark_std::iterable::Iterable;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Task {
     pub task_id: Fr,
-    pub x: Fr, // task position (meters) between 0 and 10 000
-    pub y: Fr, // task position (meters) between 0 and 10 000
-    pub z: Fr, // task position (meters) between 0 and 10 000
+    pub position: Position, // grid position, see position::Frame for the conversion to meters
+    pub priority: Fr, // task priority between 0 (default) and 255 (most urgent)
+    pub deadline: Fr, // last round in which the task can be assigned (0 for no deadline)
+    pub requirements: Fr, // capabilities (bitmask on 16 bits) a robot needs to perform the task
+    pub team_size: Fr, // robots needed at once (coalition), 0 or 1 for a single robot
+    pub prerequisites: [Option<Fr>; MAX_PREREQUISITES], // ids of the tasks that must be done first
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Robot {
     pub robot_id: Fr,
+    pub capabilities: Fr, // capabilities (bitmask on 16 bits) of the robot
+    pub battery: Fr, // energy left, in units of the distance metric
+    pub capacity: Fr, // energy of a full battery, 0 for a robot without battery limit
+    pub station: Position, // charging station of the robot, the battery is full again once it stands there
+    pub workload: Fr, // tasks assigned to the robot since it joined (on 32 bits), never reset, see AssignmentMode::Fair
     pub list_tasks: [Task; 2],
 }
 
@@ -29,81 +41,551 @@ impl Display for Robot {
     }
 }
 
-pub fn create_task(task_id: Fr, positions: [Fr; 3]) -> Task {
-    Task {
+// Task ids are stable across rounds and on 64 bits, the leaf of a task in the tree is only
+// its rank among the ids of the round (see hashes::task_root)
+pub const MAX_TASK_ID: u64 = u64::MAX;
+
+// Priorities are on 8 bits and deadlines on 32 bits (see the packing in hashes::hash_task)
+pub const MAX_PRIORITY: u32 = 255;
+pub const MAX_DEADLINE: u64 = (1 << 32) - 1;
+
+// Team sizes are on 4 bits (see hashes::hash_task)
+pub const MAX_TEAM_SIZE: u32 = 15;
+// Prerequisites are committed as id + 1 on 65 bits, 0 for an empty slot (see hashes::hash_task)
+pub const MAX_PREREQUISITES: usize = 2;
+
+// Capabilities are on 16 bits (see hashes::hash_task and hashes::hash_robot)
+pub const CAPABILITY_AERIAL: u32 = 1 << 0; // drones
+pub const CAPABILITY_GROUND: u32 = 1 << 1; // ground vehicles
+pub const CAPABILITY_MANIPULATION: u32 = 1 << 2; // manipulator arms
+pub const MAX_CAPABILITIES: u32 = (1 << 16) - 1;
+
+// Batteries are on 32 bits, in units of the distance metric (see hashes::hash_robot)
+pub const MAX_BATTERY: u64 = (1 << 32) - 1;
+// A robot whose battery is below capacity / LOW_BATTERY_DIVISOR takes no task and returns to charge
+pub const LOW_BATTERY_DIVISOR: u64 = 4;
+// Id of the return-to-charge waypoints, never used by a task of the task tree
+pub const CHARGE_TASK_ID: u64 = MAX_TASK_ID;
+// Id of the waypoints of a robot that was never assigned a task. Like CHARGE_TASK_ID it is out of the
+// task-state tree, so that the robot is never busy (see lifecycle::busy_robots)
+pub const IDLE_TASK_ID: u64 = MAX_TASK_ID - 1;
+
+// Workloads are on 32 bits (see hashes::hash_robot)
+pub const MAX_WORKLOAD: u64 = (1 << 32) - 1;
+// Costs of the fair mode: a distance (at most 30 bits) plus a penalty (16 bits) times a workload (32 bits)
+pub const FAIR_COST_BITS: u32 = 49;
+
+// Vertical moves cost this much more than horizontal ones with the weighted metric
+pub const VERTICAL_WEIGHT: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentMode {
+    Distance, // cost = distance (original SKATE)
+    Priority, // cost = (MAX_PRIORITY - priority) * 2^distance_bits + distance, urgent tasks are chosen first
+    Fair,     // cost = distance + fairness_penalty * workload, robots at the workload cap take no task
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceMetric {
+    SquaredEuclidean, // dx^2 + dy^2 + dz^2 (original SKATE)
+    Manhattan,        // |dx| + |dy| + |dz|
+    Chebyshev,        // max(|dx|, |dy|, |dz|)
+    Weighted,         // dx^2 + dy^2 + VERTICAL_WEIGHT * dz^2, for ground robots
+    Planar,           // dx^2 + dy^2, the altitude is ignored
+    Graph,            // shortest travel time on a road map (see graph::DistanceTable)
+}
+
+impl DistanceMetric {
+    // The energy of a trip is the sum of the energies of its legs only with these metrics (a squared
+    // distance is not: two legs of 1 cost 2, the direct move of 2 costs 4), so robots with a battery
+    // need one of them
+    pub fn is_additive(&self) -> bool {
+        matches!(self, DistanceMetric::Manhattan | DistanceMetric::Chebyshev | DistanceMetric::Graph)
+    }
+
+    // Since positions are between 0 and 10 000, every distance is strictly below 2^bits
+    pub fn bits(&self) -> u32 {
+        match self {
+            DistanceMetric::SquaredEuclidean => 29, // 3 * 10000^2 = 300 000 000
+            DistanceMetric::Manhattan => 15,        // 3 * 10000 = 30 000
+            DistanceMetric::Chebyshev => 14,        // 10 000
+            DistanceMetric::Weighted => 30,         // (2 + 4) * 10000^2 = 600 000 000
+            DistanceMetric::Planar => 28,           // 2 * 10000^2 = 200 000 000
+            DistanceMetric::Graph => 29,            // graph::MAX_TRAVEL_TIME
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SkateParameters {
+    pub mode: AssignmentMode,
+    pub metric: DistanceMetric,
+    pub seed: u32, // seed rotating the robot indexes used to break ties, see hashes::round_seed
+    pub fairness_penalty: u16, // cost added per task already assigned to the robot (fair mode)
+    pub workload_cap: u32, // robots with this many tasks in their lifetime take no more (fair mode), 0 for no cap
+}
+
+impl Default for SkateParameters {
+    fn default() -> Self {
+        SkateParameters {
+            mode: AssignmentMode::Distance,
+            metric: DistanceMetric::SquaredEuclidean,
+            seed: 0,
+            fairness_penalty: 0,
+            workload_cap: 0,
+        }
+    }
+}
+
+impl SkateParameters {
+    // Number of bits of the costs, used by the range checks of the circuit (before the tie-breakers)
+    pub fn cost_bits(&self) -> u32 {
+        match self.mode {
+            AssignmentMode::Distance => self.metric.bits(),
+            AssignmentMode::Priority => self.metric.bits() + 9,
+            AssignmentMode::Fair => FAIR_COST_BITS,
+        }
+    }
+
+    // Cost used when a robot has no available task, strictly above every reachable cost
+    pub(crate) fn no_task_cost(&self) -> Fr {
+        match self.mode {
+            AssignmentMode::Distance => Fr::from((1u64 << self.metric.bits()) - 1),
+            AssignmentMode::Priority => Fr::from(1u64 << (self.metric.bits() + 8)),
+            AssignmentMode::Fair => Fr::from((1u64 << FAIR_COST_BITS) - 1),
+        }
+    }
+
+    // Cost of an infeasible pair, above every feasible cost and below no_task_cost
+    pub(crate) fn infeasible_cost(&self) -> Fr {
+        self.no_task_cost() - Fr::from(1)
+    }
+
+    // In the fair mode, a robot that reached the workload cap takes no task this round. The workload is
+    // never reset, so the cap is a lifetime limit. In any mode, a robot at MAX_WORKLOAD takes no task, its
+    // workload could not grow
+    pub fn is_capped(&self, robot: &Robot) -> bool {
+        robot.workload >= Fr::from(MAX_WORKLOAD)
+            || (self.mode == AssignmentMode::Fair
+                && self.workload_cap != 0
+                && robot.workload >= Fr::from(self.workload_cap))
+    }
+}
+
+pub fn create_task(task_id: Fr, position: Position) -> Result<Task> {
+    create_prioritized_task(task_id, position, Fr::from(0), Fr::from(0))
+}
+
+pub fn create_prioritized_task(
+    task_id: Fr,
+    position: Position,
+    priority: Fr,
+    deadline: Fr,
+) -> Result<Task> {
+    let task = Task {
         task_id,
-        x: positions[0],
-        y: positions[1],
-        z: positions[2],
+        position,
+        priority,
+        deadline,
+        requirements: Fr::from(0),
+        team_size: Fr::from(0),
+        prerequisites: [None; MAX_PREREQUISITES],
+    };
+    check_task(&task)?;
+    Ok(task)
+}
+
+pub fn create_task_with_requirements(
+    task_id: Fr,
+    position: Position,
+    requirements: Fr,
+) -> Result<Task> {
+    let task = Task {
+        requirements,
+        ..create_task(task_id, position)?
+    };
+    check_task(&task)?;
+    Ok(task)
+}
+
+// A coalition task, assigned to exactly `team_size` robots or to none: when the round cannot give it its
+// whole team, the task is not assigned and stays pending for a later round (see form_coalitions)
+pub fn create_coalition_task(task_id: Fr, position: Position, team_size: Fr) -> Result<Task> {
+    let task = Task {
+        team_size,
+        ..create_task(task_id, position)?
+    };
+    check_task(&task)?;
+    Ok(task)
+}
+
+// A task whose prerequisites must be done before it is assigned (see lifecycle::TaskLedger::pending)
+pub fn create_task_with_prerequisites(
+    task_id: Fr,
+    position: Position,
+    prerequisites: [Option<Fr>; MAX_PREREQUISITES],
+) -> Result<Task> {
+    let task = Task {
+        prerequisites,
+        ..create_task(task_id, position)?
+    };
+    check_task(&task)?;
+    Ok(task)
+}
+
+// Values of the prerequisite slots in the hash and the circuits: id + 1, or 0 for an empty slot
+pub fn prerequisite_values(task: &Task) -> [Fr; MAX_PREREQUISITES] {
+    task.prerequisites
+        .map(|prerequisite| prerequisite.map_or(Fr::from(0), |id| id + Fr::from(1)))
+}
+
+// Number of robots a task needs
+pub fn team_size(task: &Task) -> usize {
+    (task.team_size.into_bigint().0[0] as usize).max(1)
+}
+
+// The id and the extras of a task must fit in their bits of the hash (see hashes::hash_task)
+pub fn check_task(task: &Task) -> Result<()> {
+    if task.task_id > Fr::from(MAX_TASK_ID) {
+        return Err(SkateError::InvalidTaskId(task.task_id));
+    }
+    if task.priority > Fr::from(MAX_PRIORITY) {
+        return Err(SkateError::InvalidPriority(task.priority));
+    }
+    if task.deadline > Fr::from(MAX_DEADLINE) {
+        return Err(SkateError::InvalidDeadline(task.deadline));
+    }
+    if task.requirements > Fr::from(MAX_CAPABILITIES) {
+        return Err(SkateError::InvalidCapabilities(task.requirements));
+    }
+    if task.team_size > Fr::from(MAX_TEAM_SIZE) {
+        return Err(SkateError::InvalidTeamSize(task.team_size));
+    }
+    for prerequisite in task.prerequisites.into_iter().flatten() {
+        if prerequisite > Fr::from(MAX_TASK_ID) {
+            return Err(SkateError::InvalidTaskId(prerequisite));
+        }
+    }
+    Ok(())
+}
+
+pub fn check_robot(robot: &Robot) -> Result<()> {
+    if robot.capabilities > Fr::from(MAX_CAPABILITIES) {
+        return Err(SkateError::InvalidCapabilities(robot.capabilities));
+    }
+    if robot.battery > Fr::from(MAX_BATTERY) {
+        return Err(SkateError::InvalidBattery(robot.battery));
+    }
+    if robot.capacity > Fr::from(MAX_BATTERY) {
+        return Err(SkateError::InvalidBattery(robot.capacity));
     }
+    if robot.workload > Fr::from(MAX_WORKLOAD) {
+        return Err(SkateError::InvalidWorkload(robot.workload));
+    }
+    Ok(())
+}
+
+pub fn is_expired(task: &Task, round: Fr) -> Result<bool> {
+    check_task(task)?;
+    Ok(task.deadline != Fr::from(0) && task.deadline < round)
+}
+
+// A robot can perform a task if it has every capability the task requires
+pub fn is_compatible(task: &Task, robot: &Robot) -> Result<bool> {
+    check_task(task)?;
+    check_robot(robot)?;
+    let requirements = task.requirements.into_bigint().0[0];
+    let capabilities = robot.capabilities.into_bigint().0[0];
+    Ok(requirements & capabilities == requirements)
+}
+
+// The robot once charged: a robot standing on its charging station starts the round with a full battery
+pub fn charged(robot: &Robot) -> Robot {
+    let mut robot = *robot;
+    if robot.capacity != Fr::from(0) && robot.list_tasks[1].position == robot.station {
+        robot.battery = robot.capacity;
+    }
+    robot
+}
+
+// A charged robot low on battery takes no task, it is sent back to its station instead
+pub fn needs_charge(robot: &Robot) -> bool {
+    let robot = charged(robot);
+    robot.capacity != Fr::from(0)
+        && robot.battery * Fr::from(LOW_BATTERY_DIVISOR) < robot.capacity
 }
 
-pub fn create_robot(robot_id: Fr, task_id: [Fr; 2], positions: [[Fr; 3]; 2]) -> Robot {
-    Robot {
+// A robot low on battery goes back to its station if its battery lasts until there, otherwise it stays
+// where it is (and is stranded until it is moved by hand)
+pub fn goes_to_charge(robot: &Robot, metric: DistanceMetric, road_map: Option<&DistanceTable>) -> Result<bool> {
+    if !needs_charge(robot) {
+        return Ok(false);
+    }
+    let robot = charged(robot);
+    Ok(travel(robot.list_tasks[1].position, robot.station, metric, road_map)? <= robot.battery)
+}
+
+// Robots with a battery need an additive metric (see DistanceMetric::is_additive)
+pub fn check_metric(robots: &Vec<Robot>, metric: DistanceMetric) -> Result<()> {
+    match robots.iter().find(|robot| robot.capacity != Fr::from(0)) {
+        Some(robot) if !metric.is_additive() => Err(SkateError::NonAdditiveMetric(robot.robot_id)),
+        _ => Ok(()),
+    }
+}
+
+// A robot can take a task if it is compatible with it and, with a limited battery, if it can reach the
+// task and then its charging station
+pub fn is_feasible(
+    task: &Task,
+    robot: &Robot,
+    metric: DistanceMetric,
+    road_map: Option<&DistanceTable>,
+) -> Result<bool> {
+    if !is_compatible(task, robot)? {
+        return Ok(false);
+    }
+    let robot = charged(robot);
+    if robot.capacity == Fr::from(0) {
+        return Ok(true);
+    }
+    if needs_charge(&robot) {
+        return Ok(false);
+    }
+    let there = distance(task, &robot, metric, road_map)?;
+    let back = travel(task.position, robot.station, metric, road_map)?;
+    Ok(there + back <= robot.battery)
+}
+
+pub fn create_robot(robot_id: Fr, task_id: [Fr; 2], positions: [Position; 2]) -> Result<Robot> {
+    create_robot_with_capabilities(robot_id, Fr::from(0), task_id, positions)
+}
+
+pub fn create_robot_with_capabilities(
+    robot_id: Fr,
+    capabilities: Fr,
+    task_id: [Fr; 2],
+    positions: [Position; 2],
+) -> Result<Robot> {
+    create_robot_with_battery(robot_id, capabilities, task_id, positions, Fr::from(0), Position::default())
+}
+
+// The robot starts with a full battery
+pub fn create_robot_with_battery(
+    robot_id: Fr,
+    capabilities: Fr,
+    task_id: [Fr; 2],
+    positions: [Position; 2],
+    capacity: Fr,
+    station: Position,
+) -> Result<Robot> {
+    let robot = Robot {
         robot_id,
+        capabilities,
+        battery: capacity,
+        capacity,
+        station,
+        workload: Fr::from(0),
         list_tasks: [
-            create_task(task_id[0], positions[0]),
-            create_task(task_id[1], positions[1]),
+            create_task(task_id[0], positions[0])?,
+            create_task(task_id[1], positions[1])?,
         ],
+    };
+    check_robot(&robot)?;
+    Ok(robot)
+}
+
+// Field element used as an index in a list of `length` elements
+pub fn to_index(value: Fr, length: usize) -> Result<usize> {
+    let limbs = value.into_bigint().0;
+    if limbs[1..].iter().any(|limb| *limb != 0) || limbs[0] >= length as u64 {
+        return Err(SkateError::InvalidIndex(value, length));
     }
+    Ok(limbs[0] as usize)
+}
+
+pub(crate) fn check_length(found: usize, expected: usize) -> Result<()> {
+    if found != expected {
+        return Err(SkateError::InvalidLength { expected, found });
+    }
+    Ok(())
 }
 
 pub fn create_list_tasks(
     task_id: &Vec<Fr>,
-    positions: &Vec<[Fr; 3]>,
+    positions: &Vec<Position>,
     log_length: usize,
-) -> Vec<Task> {
-    assert_eq!(task_id.len(), 2usize.pow(log_length as u32));
-    assert_eq!(positions.len(), 2usize.pow(log_length as u32));
-
-    let mut tasks = vec![
-        create_task(Fr::from(0), [Fr::from(0), Fr::from(0), Fr::from(0)]);
-        2usize.pow(log_length as u32)
-    ];
+) -> Result<Vec<Task>> {
+    check_length(task_id.len(), 2usize.pow(log_length as u32))?;
+    check_length(positions.len(), 2usize.pow(log_length as u32))?;
+
+    let mut tasks = vec![];
     for i in 0..2usize.pow(log_length as u32) {
-        tasks[i] = create_task(task_id[i], positions[i]);
+        tasks.push(create_task(task_id[i], positions[i])?);
     }
 
-    tasks
+    Ok(tasks)
 }
 
 pub fn create_list_robots(
     robots_id: &Vec<Fr>,
     tasks_id: &Vec<[Fr; 2]>,
-    positions: &Vec<[[Fr; 3]; 2]>,
+    positions: &Vec<[Position; 2]>,
     log_length: usize,
-) -> Vec<Robot> {
-    assert_eq!(robots_id.len(), 2usize.pow(log_length as u32));
-    assert_eq!(tasks_id.len(), 2usize.pow(log_length as u32));
-    assert_eq!(positions.len(), 2usize.pow(log_length as u32));
-
-    let mut robots = vec![
-        create_robot(Fr::from(0), [Fr::from(0); 2], [[Fr::from(0); 3]; 2]);
-        2usize.pow(log_length as u32)
-    ];
+) -> Result<Vec<Robot>> {
+    check_length(robots_id.len(), 2usize.pow(log_length as u32))?;
+    check_length(tasks_id.len(), 2usize.pow(log_length as u32))?;
+    check_length(positions.len(), 2usize.pow(log_length as u32))?;
+
+    let mut robots = vec![];
     for i in 0..2usize.pow(log_length as u32) {
-        robots[i] = create_robot(robots_id[i], tasks_id[i], positions[i]);
+        robots.push(create_robot(robots_id[i], tasks_id[i], positions[i])?);
     }
 
-    robots
+    Ok(robots)
 }
 
-fn distance(task: &Task, robot: &Robot) -> Fr {
-    (robot.list_tasks[1].x - task.x) * (robot.list_tasks[1].x - task.x)
-        + (robot.list_tasks[1].y - task.y) * (robot.list_tasks[1].y - task.y)
-        + (robot.list_tasks[1].z - task.z) * (robot.list_tasks[1].z - task.z)
+fn abs_diff(a: Fr, b: Fr) -> Fr {
+    if a < b {
+        b - a
+    } else {
+        a - b
+    }
 }
 
-fn cost_matrix(robots: &Vec<Robot>, tasks: &Vec<Task>) -> Vec<Vec<Fr>> {
+fn max(a: Fr, b: Fr) -> Fr {
+    if a < b {
+        b
+    } else {
+        a
+    }
+}
+
+// Travel cost from the current task of the robot to the task, without the priority term
+pub fn distance(
+    task: &Task,
+    robot: &Robot,
+    metric: DistanceMetric,
+    road_map: Option<&DistanceTable>,
+) -> Result<Fr> {
+    travel(robot.list_tasks[1].position, task.position, metric, road_map)
+}
+
+// Travel cost between two positions, also the energy the move takes
+pub fn travel(
+    from: Position,
+    to: Position,
+    metric: DistanceMetric,
+    road_map: Option<&DistanceTable>,
+) -> Result<Fr> {
+    if metric == DistanceMetric::Graph {
+        return road_map
+            .ok_or(SkateError::MissingRoadMap)?
+            .travel_time(from, to)
+            .ok_or(SkateError::NotOnRoadMap);
+    }
+    let dx = abs_diff(from.x(), to.x());
+    let dy = abs_diff(from.y(), to.y());
+    let dz = abs_diff(from.z(), to.z());
+    Ok(match metric {
+        DistanceMetric::SquaredEuclidean => dx * dx + dy * dy + dz * dz,
+        DistanceMetric::Manhattan => dx + dy + dz,
+        DistanceMetric::Chebyshev => max(max(dx, dy), dz),
+        DistanceMetric::Weighted => dx * dx + dy * dy + Fr::from(VERTICAL_WEIGHT) * dz * dz,
+        DistanceMetric::Planar => dx * dx + dy * dy,
+        DistanceMetric::Graph => unreachable!(),
+    })
+}
+
+// A busy robot is kept on its task, as a capped one it can take none
+fn cost_matrix(
+    robots: &Vec<Robot>,
+    tasks: &Vec<Task>,
+    parameters: &SkateParameters,
+    road_map: Option<&DistanceTable>,
+    busy: &[bool],
+) -> Result<Vec<Vec<Fr>>> {
     let mut cost =
         vec![vec![Fr::from(0); tasks.len()]; robots.len()];
     for i in 0..robots.len() {
         for j in 0..tasks.len() {
-            cost[i][j] = distance(&tasks[j], &robots[i]);
+            if !is_feasible(&tasks[j], &robots[i], parameters.metric, road_map)?
+                || parameters.is_capped(&robots[i])
+                || busy[i]
+            {
+                cost[i][j] = parameters.infeasible_cost();
+                continue;
+            }
+            cost[i][j] = distance(&tasks[j], &robots[i], parameters.metric, road_map)?;
+            match parameters.mode {
+                AssignmentMode::Distance => {}
+                AssignmentMode::Priority => {
+                    cost[i][j] += (Fr::from(MAX_PRIORITY) - tasks[j].priority)
+                        * Fr::from(1u64 << parameters.metric.bits());
+                }
+                AssignmentMode::Fair => {
+                    cost[i][j] += Fr::from(parameters.fairness_penalty) * robots[i].workload;
+                }
+            }
+        }
+    }
+
+    Ok(cost)
+}
+
+fn compatibility_matrix(
+    robots: &Vec<Robot>,
+    tasks: &Vec<Task>,
+    parameters: &SkateParameters,
+    road_map: Option<&DistanceTable>,
+    busy: &[bool],
+) -> Result<Vec<Vec<bool>>> {
+    let mut compatible = vec![vec![true; tasks.len()]; robots.len()];
+    for i in 0..robots.len() {
+        for j in 0..tasks.len() {
+            compatible[i][j] = is_feasible(&tasks[j], &robots[i], parameters.metric, road_map)?
+                && !parameters.is_capped(&robots[i])
+                && !busy[i];
         }
     }
 
-    cost
+    Ok(compatible)
+}
+
+/* Tie-breaking, shared with the native rollup (see rollup::skate):
+    - in each column, the robots are sorted by cost, then by (robot index + seed) mod 2^log_n
+    - a robot chooses the task with the lowest cost, then with the lowest task index
+   The tie-breaker is appended to the low bits of the cost so that every compared key is unique */
+
+// Number of bits of an index, the `log_n` and `log_m` of the circuits
+pub(crate) fn index_bits(length: usize) -> u32 {
+    length.next_power_of_two().ilog2()
+}
+
+fn rank_keys(cost: &Vec<Vec<Fr>>, seed: u32) -> Vec<Vec<Fr>> {
+    let bits = index_bits(cost.len());
+    let mut keys = cost.clone();
+    for i in 0..cost.len() {
+        let rotation = (i as u64 + seed as u64) % (1u64 << bits);
+        for j in 0..cost[0].len() {
+            keys[i][j] = cost[i][j] * Fr::from(1u64 << bits) + Fr::from(rotation);
+        }
+    }
+
+    keys
+}
+
+fn choice_keys(cost: &Vec<Vec<Fr>>) -> Vec<Vec<Fr>> {
+    let bits = index_bits(cost[0].len());
+    let mut keys = cost.clone();
+    for i in 0..cost.len() {
+        for j in 0..cost[0].len() {
+            keys[i][j] = cost[i][j] * Fr::from(1u64 << bits) + Fr::from(j as u64);
+        }
+    }
+
+    keys
 }
 
 fn min(array: Vec<Fr>) -> Fr {
@@ -117,15 +599,15 @@ fn min(array: Vec<Fr>) -> Fr {
 }
 
 fn index(array: &Vec<Fr>, min: Fr) -> Fr {
-    let mut idx = Fr::from(array.len() as i32);
     for i in 0..array.len() {
         if array[i] == min {
-            idx = Fr::from(i as i32);
+            return Fr::from(i as i32);
         }
     }
-    idx
+    Fr::from(array.len() as i32)
 }
 
+// The keys are unique in each column, so that the ranks do not depend on the sorting algorithm
 fn rank_matrix(cost: &Vec<Vec<Fr>>) -> Vec<Vec<Fr>> {
     let mut rank =
         vec![vec![Fr::from(0); cost[0].len()]; cost.len()];
@@ -156,30 +638,43 @@ fn rank_matrix(cost: &Vec<Vec<Fr>>) -> Vec<Vec<Fr>> {
     rank
 }
 
+// The keys are unique, the chosen task is the only candidate with the minimal key (m if none)
 fn task_choice(
     rank: &Vec<Fr>,
     available: &Vec<bool>,
     robot_id: Fr,
-    cost: &Vec<Fr>,
+    keys: &Vec<Fr>,
+    no_task_key: Fr,
 ) -> Fr {
     let mut array = vec![Fr::from(0); rank.len()];
     for i in 0..rank.len() {
         if rank[i] == robot_id && available[i] {
-            array[i] = cost[i];
+            array[i] = keys[i];
         } else {
-            array[i] = Fr::from(399999999i32);
+            array[i] = no_task_key;
         }
     }
 
     let min = min(array);
-    index(cost, min)
+    index(keys, min)
 }
 
-fn assign(rank: &Vec<Vec<Fr>>, cost: &Vec<Vec<Fr>>) -> Vec<Fr> {
+// A robot that gets no task is assigned the index m (number of tasks). A task stays available until
+// `teams[k]` robots took it, a row of the rank matrix gives it at most one robot
+fn assign(
+    rank: &Vec<Vec<Fr>>,
+    cost: &Vec<Vec<Fr>>,
+    compatible: &Vec<Vec<bool>>,
+    teams: &Vec<usize>,
+    parameters: &SkateParameters,
+) -> Result<Vec<Fr>> {
     let mut robot_available = vec![true; cost.len()];
+    let mut remaining = teams.clone();
     let mut task_available = vec![true; cost[0].len()];
     let mut task: Fr;
-    let mut assignments = vec![Fr::from(0); cost.len()];
+    let mut assignments = vec![Fr::from(cost[0].len() as u32); cost.len()];
+    let keys = choice_keys(cost);
+    let no_task_key = parameters.no_task_cost() * Fr::from(1u64 << index_bits(cost[0].len()));
 
     for i in 0..cost.len() {
         for j in 0..cost.len() {
@@ -187,47 +682,281 @@ fn assign(rank: &Vec<Vec<Fr>>, cost: &Vec<Vec<Fr>>) -> Vec<Fr> {
                 &rank[i],
                 &task_available,
                 Fr::from(j as i32),
-                &cost[i]
+                &keys[i],
+                no_task_key,
             );
             if task != Fr::from(cost[0].len() as u32) && robot_available[j] {
-                robot_available[j] = false;
-                task_available[task.into_bigint().to_string().parse::<usize>().unwrap()] = false;
-                assignments[j] = task;
+                let k = to_index(task, cost[0].len())?;
+                if compatible[j][k] {
+                    robot_available[j] = false;
+                    remaining[k] -= 1;
+                    task_available[k] = remaining[k] > 0;
+                    assignments[j] = task;
+                }
             }
         }
     }
 
+    Ok(assignments)
+}
+
+// A coalition that did not get all its robots is not formed, its robots get no task this round. Every task
+// thus ends with 0 or `team_size` robots, the rule the circuits enforce: exactly `team_size` cannot be
+// required of a task that no robot, or not enough of them, can take
+fn form_coalitions(assignments: Vec<Fr>, teams: &Vec<usize>) -> Vec<Fr> {
+    let no_task = Fr::from(teams.len() as u32);
+    let task = |assignment: &Fr| assignment.into_bigint().0[0] as usize;
+    let mut count = vec![0; teams.len()];
+    for assignment in assignments.iter().filter(|a| **a != no_task) {
+        count[task(assignment)] += 1;
+    }
     assignments
+        .iter()
+        .map(|assignment| {
+            if *assignment == no_task || count[task(assignment)] == teams[task(assignment)] {
+                *assignment
+            } else {
+                no_task
+            }
+        })
+        .collect()
+}
+
+pub fn skate(robots: &Vec<Robot>, tasks: &Vec<Task>) -> Result<Vec<Robot>> {
+    skate_with_parameters(robots, tasks, &SkateParameters::default())
+}
+
+pub fn skate_with_parameters(
+    robots: &Vec<Robot>,
+    tasks: &Vec<Task>,
+    parameters: &SkateParameters,
+) -> Result<Vec<Robot>> {
+    skate_with_road_map(robots, tasks, parameters, None)
+}
+
+// The road map is only used with DistanceMetric::Graph
+pub fn skate_with_road_map(
+    robots: &Vec<Robot>,
+    tasks: &Vec<Task>,
+    parameters: &SkateParameters,
+    road_map: Option<&DistanceTable>,
+) -> Result<Vec<Robot>> {
+    let assignments = skate_assignments(robots, tasks, parameters, road_map)?;
+    apply_assignments(robots, tasks, &assignments, parameters.metric, road_map)
 }
 
-pub fn skate(robots: &Vec<Robot>, tasks: &Vec<Task>) -> Vec<Robot> {
+// New robots once each robot moved to its assigned task, or to its station when low on battery (see
+// goes_to_charge). A robot with a limited battery spends the travel cost of its move, an assigned task adds
+// to its workload. The assignments must be feasible, as those of skate_assignments
+pub fn apply_assignments(
+    robots: &Vec<Robot>,
+    tasks: &Vec<Task>,
+    assignments: &Vec<Option<usize>>,
+    metric: DistanceMetric,
+    road_map: Option<&DistanceTable>,
+) -> Result<Vec<Robot>> {
+    apply_assignments_with_busy(robots, tasks, assignments, metric, road_map, &vec![false; robots.len()])
+}
+
+// A busy robot still holds its task (see lifecycle::busy_robots): it gets no task and does not go back
+// to charge before its task is over
+pub fn apply_assignments_with_busy(
+    robots: &Vec<Robot>,
+    tasks: &Vec<Task>,
+    assignments: &Vec<Option<usize>>,
+    metric: DistanceMetric,
+    road_map: Option<&DistanceTable>,
+    busy: &[bool],
+) -> Result<Vec<Robot>> {
+    check_length(assignments.len(), robots.len())?;
+    check_length(busy.len(), robots.len())?;
 
-    let cost = cost_matrix(&robots, &tasks);
-    let rank = rank_matrix(&cost);
-    let assignments = assign(&rank, &cost);
+    check_metric(robots, metric)?;
 
-    let mut new_robots = robots.clone();
+    let mut new_robots = vec![];
     for i in 0..robots.len() {
-        new_robots[i].list_tasks[0] = new_robots[i].list_tasks[1];
-        new_robots[i].list_tasks[1].task_id = assignments[i];
-        new_robots[i].list_tasks[1].x = tasks[assignments[i]
-            .into_bigint()
-            .to_string()
-            .parse::<usize>()
-            .unwrap()]
-        .x;
-        new_robots[i].list_tasks[1].y = tasks[assignments[i]
-            .into_bigint()
-            .to_string()
-            .parse::<usize>()
-            .unwrap()]
-        .y;
-        new_robots[i].list_tasks[1].z = tasks[assignments[i]
-            .into_bigint()
-            .to_string()
-            .parse::<usize>()
-            .unwrap()]
-        .z;
-    }
-    new_robots
+        let goes_to_charge = goes_to_charge(&robots[i], metric, road_map)?;
+        let mut robot = charged(&robots[i]);
+        let from = robot.list_tasks[1].position;
+        robot.list_tasks[0] = robot.list_tasks[1];
+        match assignments[i] {
+            Some(_) if busy[i] => return Err(SkateError::Busy(robot.robot_id)),
+            Some(k) => {
+                if k >= tasks.len() {
+                    return Err(SkateError::InvalidIndex(Fr::from(k as u64), tasks.len()));
+                }
+                robot.list_tasks[1].task_id = tasks[k].task_id;
+                robot.list_tasks[1].position = tasks[k].position;
+                robot.workload += Fr::from(1);
+                if robot.workload > Fr::from(MAX_WORKLOAD) {
+                    return Err(SkateError::InvalidWorkload(robot.workload));
+                }
+            }
+            None if goes_to_charge && !busy[i] => {
+                robot.list_tasks[1].task_id = Fr::from(CHARGE_TASK_ID);
+                robot.list_tasks[1].position = robot.station;
+            }
+            None => {} // No task for this robot, it stays on its current task
+        }
+        if robot.capacity != Fr::from(0) && robot.list_tasks[1].position != from {
+            let used = travel(from, robot.list_tasks[1].position, metric, road_map)?;
+            if used > robot.battery {
+                return Err(SkateError::InvalidBattery(robot.battery));
+            }
+            robot.battery -= used;
+        }
+        new_robots.push(robot);
+    }
+    Ok(new_robots)
+}
+
+// Index of the task assigned to each robot, None for an idle robot
+pub fn skate_assignments(
+    robots: &Vec<Robot>,
+    tasks: &Vec<Task>,
+    parameters: &SkateParameters,
+    road_map: Option<&DistanceTable>,
+) -> Result<Vec<Option<usize>>> {
+    skate_assignments_with_busy(robots, tasks, parameters, road_map, &vec![false; robots.len()])
+}
+
+// The busy robots keep their task, they get None
+pub fn skate_assignments_with_busy(
+    robots: &Vec<Robot>,
+    tasks: &Vec<Task>,
+    parameters: &SkateParameters,
+    road_map: Option<&DistanceTable>,
+    busy: &[bool],
+) -> Result<Vec<Option<usize>>> {
+    if robots.is_empty() || tasks.is_empty() {
+        return Err(SkateError::Empty);
+    }
+    check_length(busy.len(), robots.len())?;
+    check_metric(robots, parameters.metric)?;
+
+    let cost = cost_matrix(&robots, &tasks, parameters, road_map, busy)?;
+    let compatible = compatibility_matrix(&robots, &tasks, parameters, road_map, busy)?;
+    let rank = rank_matrix(&rank_keys(&cost, parameters.seed));
+    let teams = tasks.iter().map(team_size).collect();
+    let assignments = form_coalitions(assign(&rank, &cost, &compatible, &teams, parameters)?, &teams);
+
+    let mut indexes = vec![];
+    for assignment in assignments {
+        if assignment == Fr::from(tasks.len() as u32) {
+            indexes.push(None);
+        } else {
+            indexes.push(Some(to_index(assignment, tasks.len())?));
+        }
+    }
+    Ok(indexes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::rand::rngs::StdRng;
+    use ark_std::rand::{Rng, SeedableRng};
+
+    fn robot(id: u64, position: Position) -> Robot {
+        create_robot(Fr::from(id), [Fr::from(0), Fr::from(0)], [position; 2]).unwrap()
+    }
+
+    fn robots_per_task(assignments: &[Option<usize>], tasks: usize) -> Vec<usize> {
+        let mut count = vec![0; tasks];
+        for k in assignments.iter().flatten() {
+            count[*k] += 1;
+        }
+        count
+    }
+
+    #[test]
+    fn coalition_without_its_team_is_not_formed() {
+        let robots = vec![robot(0, Position::new(0, 0, 0).unwrap()), robot(1, Position::new(10, 0, 0).unwrap())];
+        let tasks = vec![create_coalition_task(Fr::from(7), Position::new(5, 0, 0).unwrap(), Fr::from(3)).unwrap()];
+        let assignments = skate_assignments(&robots, &tasks, &SkateParameters::default(), None).unwrap();
+        assert_eq!(assignments, vec![None, None]);
+
+        let robots = vec![robots[0], robots[1], robot(2, Position::new(20, 0, 0).unwrap())];
+        let assignments = skate_assignments(&robots, &tasks, &SkateParameters::default(), None).unwrap();
+        assert_eq!(assignments, vec![Some(0); 3]);
+    }
+
+    // A robot at (100, 0, 0) with a capacity of 800, its station at the origin: low below 200
+    fn battery_robot(battery: u64) -> Robot {
+        let position = Position::new(100, 0, 0).unwrap();
+        let robot = create_robot_with_battery(
+            Fr::from(0),
+            Fr::from(0),
+            [Fr::from(0), Fr::from(0)],
+            [position; 2],
+            Fr::from(800),
+            Position::default(),
+        )
+        .unwrap();
+        Robot { battery: Fr::from(battery), ..robot }
+    }
+
+    #[test]
+    fn low_robots_charge_only_when_they_can() {
+        let tasks = vec![create_task(Fr::from(1), Position::new(101, 0, 0).unwrap()).unwrap()];
+        let parameters = SkateParameters { metric: DistanceMetric::Manhattan, ..SkateParameters::default() };
+
+        // Low but able to reach its station: it goes back to charge
+        let robots = vec![battery_robot(150)];
+        let new_robots = skate_with_parameters(&robots, &tasks, &parameters).unwrap();
+        assert_eq!(new_robots[0].list_tasks[1].task_id, Fr::from(CHARGE_TASK_ID));
+        assert_eq!(new_robots[0].list_tasks[1].position, Position::default());
+        assert_eq!(new_robots[0].battery, Fr::from(50));
+
+        // Stranded: the round goes on, the robot stays where it is
+        let robots = vec![battery_robot(50)];
+        let new_robots = skate_with_parameters(&robots, &tasks, &parameters).unwrap();
+        assert_eq!(new_robots[0].list_tasks[1].task_id, Fr::from(0));
+        assert_eq!(new_robots[0].list_tasks[1].position, robots[0].list_tasks[1].position);
+        assert_eq!(new_robots[0].battery, Fr::from(50));
+    }
+
+    #[test]
+    fn batteries_need_an_additive_metric() {
+        let tasks = vec![create_task(Fr::from(1), Position::default()).unwrap()];
+        let robots = vec![battery_robot(800)];
+        for metric in [DistanceMetric::SquaredEuclidean, DistanceMetric::Weighted, DistanceMetric::Planar] {
+            let parameters = SkateParameters { metric, ..SkateParameters::default() };
+            assert_eq!(
+                skate_with_parameters(&robots, &tasks, &parameters).unwrap_err(),
+                SkateError::NonAdditiveMetric(Fr::from(0))
+            );
+        }
+        let parameters = SkateParameters { metric: DistanceMetric::Chebyshev, ..SkateParameters::default() };
+        assert!(skate_with_parameters(&robots, &tasks, &parameters).is_ok());
+    }
+
+    #[test]
+    fn saturated_workloads_take_no_task() {
+        let tasks = vec![create_task(Fr::from(1), Position::new(1, 0, 0).unwrap()).unwrap()];
+        let full = Robot { workload: Fr::from(MAX_WORKLOAD), ..robot(0, Position::default()) };
+        let robots = vec![full, robot(1, Position::new(50, 0, 0).unwrap())];
+        for mode in [AssignmentMode::Distance, AssignmentMode::Priority, AssignmentMode::Fair] {
+            let parameters = SkateParameters { mode, ..SkateParameters::default() };
+            let new_robots = skate_with_parameters(&robots, &tasks, &parameters).unwrap();
+            assert_eq!(new_robots[0].workload, Fr::from(MAX_WORKLOAD));
+            assert_eq!(new_robots[1].list_tasks[1].task_id, Fr::from(1));
+        }
+    }
+
+    #[test]
+    fn tasks_get_their_team_or_no_robot() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let grid = |rng: &mut StdRng| Position::new(rng.gen_range(0..100), rng.gen_range(0..100), 0).unwrap();
+        for _ in 0..300 {
+            let robots: Vec<Robot> = (0..rng.gen_range(1..7)).map(|i| robot(i, grid(&mut rng))).collect();
+            let tasks: Vec<Task> = (0..rng.gen_range(1..5))
+                .map(|k| create_coalition_task(Fr::from(k), grid(&mut rng), Fr::from(rng.gen_range(0..4u64))).unwrap())
+                .collect();
+            let assignments = skate_assignments(&robots, &tasks, &SkateParameters::default(), None).unwrap();
+            for (task, count) in tasks.iter().zip(robots_per_task(&assignments, tasks.len())) {
+                assert!(count == 0 || count == team_size(task), "{} robots for a team of {}", count, team_size(task));
+            }
+        }
+    }
 }
@@ -0,0 +1,688 @@
+use crate::anemoi_2_to_1::hash_2_to_1;
+use crate::error::{Result, SkateError};
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::pairing::{Pairing, PairingOutput};
+use ark_ec::scalar_mul::fixed_base::FixedBase;
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{Field, One, PrimeField, Zero};
+use ark_groth16::{PreparedVerifyingKey, Proof};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{CryptoRng, RngCore};
+use ark_std::UniformRand;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/* SnarkPack aggregation of Groth16 proofs with the same verifying key (Gailly, Maller and Nitulescu,
+   2021), for the rounds of a circuit checked at once. With a random r, the n proofs satisfy
+       prod e(A_i, B_i)^(r^i) = e(alpha, beta)^(sum r^i) e(sum r^i I_i, gamma) e(sum r^i C_i, delta)
+   where I_i are the prepared public inputs. The aggregate proves the left side Z_AB and sum r^i C_i (Z_C)
+   against commitments to the A, B and C of the proofs made before r: a TIPP (inner pairing product) and
+   a MIPP (multi-exponentiation) argument, run together in log n halving rounds, end with one A, B and C
+   and the commitment keys folded by the challenges, whose folding is proven with KZG openings.
+   The commitment keys come from a reference string of two secrets a and b (see AggregationSrs):
+   v = ([a^i]_2, [b^i]_2) commits to the G1 points, w = ([a^(n+i)]_1, [b^(n+i)]_1) to the G2 ones. The
+   TIPP runs on r^i A_i with the key v_i / r^i, which keeps the commitment. The verification costs a
+   constant number of pairings and log n operations in GT, besides combining the public inputs.
+   The number of proofs is padded to a power of two by repeating the last one.
+   Whoever knows a or b can open the commitments to other points and pass an aggregate of invalid proofs:
+   AggregationSrs::setup samples them on one machine, for the tests and a single operator, the servers of
+   the robots which do not trust the coordinator need a string from a multi-party ceremony */
+
+type Gt = PairingOutput<Bn254>;
+
+fn invalid(message: &str) -> SkateError {
+    SkateError::Proof(message.to_string())
+}
+
+fn powers(scalar: Fr, size: usize) -> Vec<Fr> {
+    let mut powers = Vec::with_capacity(size);
+    let mut power = Fr::one();
+    for _ in 0..size {
+        powers.push(power);
+        power *= scalar;
+    }
+    powers
+}
+
+fn fixed_base<G: CurveGroup<ScalarField = Fr>>(generator: G, scalars: &[Fr]) -> Vec<G::Affine> {
+    let window = FixedBase::get_mul_window_size(scalars.len());
+    let bits = Fr::MODULUS_BIT_SIZE as usize;
+    let table = FixedBase::get_window_table(bits, window, generator);
+    G::normalize_batch(&FixedBase::msm::<G>(bits, window, &table, scalars))
+}
+
+// Reference string of the aggregation of up to `max_proofs` proofs: [a^i]_1 and [b^i]_1 for i < 2n,
+// [a^i]_2 and [b^i]_2 for i < n
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregationSrs {
+    pub g_a: Vec<G1Affine>,
+    pub g_b: Vec<G1Affine>,
+    pub h_a: Vec<G2Affine>,
+    pub h_b: Vec<G2Affine>,
+}
+
+// The part of the reference string the verifier needs, whatever the number of proofs
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregationKey {
+    pub g: G1Affine,
+    pub h: G2Affine,
+    pub g_a: G1Affine,
+    pub g_b: G1Affine,
+    pub h_a: G2Affine,
+    pub h_b: G2Affine,
+}
+
+impl AggregationSrs {
+    // Local generation, a and b are dropped once the powers are computed. Not a ceremony: the string is only
+    // as trustworthy as the RNG and the machine that ran it (see the module comment)
+    pub fn setup<R: RngCore + CryptoRng>(max_proofs: usize, rng: &mut R) -> AggregationSrs {
+        let n = max_proofs.max(2).next_power_of_two();
+        let (a, b) = (Fr::rand(rng), Fr::rand(rng));
+        let (g, h) = (G1Affine::generator().into_group(), G2Affine::generator().into_group());
+        AggregationSrs {
+            g_a: fixed_base(g, &powers(a, 2 * n)),
+            g_b: fixed_base(g, &powers(b, 2 * n)),
+            h_a: fixed_base(h, &powers(a, n)),
+            h_b: fixed_base(h, &powers(b, n)),
+        }
+    }
+
+    pub fn max_proofs(&self) -> usize {
+        self.h_a.len()
+    }
+
+    pub fn key(&self) -> AggregationKey {
+        AggregationKey {
+            g: self.g_a[0],
+            h: self.h_a[0],
+            g_a: self.g_a[1],
+            g_b: self.g_b[1],
+            h_a: self.h_a[1],
+            h_b: self.h_b[1],
+        }
+    }
+
+    // Each vector holds the successive powers of the secret of its [a]_2 or [a]_1 (checked on random
+    // combinations), from the same generators
+    pub fn check<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Result<()> {
+        let n = self.h_a.len();
+        let sized = n >= 2
+            && n.is_power_of_two()
+            && self.h_b.len() == n
+            && self.g_a.len() == 2 * n
+            && self.g_b.len() == 2 * n;
+        if !sized || self.g_a[0].is_zero() || self.h_a[0].is_zero() {
+            return Err(SkateError::Artifact("the aggregation reference string is malformed".to_string()));
+        }
+        let (g, h) = (self.g_a[0], self.h_a[0]);
+        let inconsistent =
+            || SkateError::Artifact("the powers of the aggregation reference string are inconsistent".to_string());
+        if self.g_b[0] != g || self.h_b[0] != h {
+            return Err(inconsistent());
+        }
+        for (g1, g2) in [(&self.g_a, &self.h_a), (&self.g_b, &self.h_b)] {
+            let scalars: Vec<Fr> = (1..g1.len()).map(|_| Fr::rand(rng)).collect();
+            let lower = G1Projective::msm_unchecked(&g1[..g1.len() - 1], &scalars);
+            let upper = G1Projective::msm_unchecked(&g1[1..], &scalars);
+            if Bn254::pairing(upper, h) != Bn254::pairing(lower, g2[1]) {
+                return Err(inconsistent());
+            }
+            let scalars = &scalars[..g2.len() - 1];
+            let lower = G2Projective::msm_unchecked(&g2[..g2.len() - 1], scalars);
+            let upper = G2Projective::msm_unchecked(&g2[1..], scalars);
+            if Bn254::pairing(g, upper) != Bn254::pairing(g1[1], lower) {
+                return Err(inconsistent());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<AggregationSrs> {
+        let error = |e: &dyn std::fmt::Display| SkateError::Artifact(format!("{}: {}", path.display(), e));
+        let mut reader = BufReader::new(File::open(path).map_err(|e| error(&e))?);
+        Ok(AggregationSrs {
+            g_a: Vec::deserialize_compressed(&mut reader).map_err(|e| error(&e))?,
+            g_b: Vec::deserialize_compressed(&mut reader).map_err(|e| error(&e))?,
+            h_a: Vec::deserialize_compressed(&mut reader).map_err(|e| error(&e))?,
+            h_b: Vec::deserialize_compressed(&mut reader).map_err(|e| error(&e))?,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let error = |e: &dyn std::fmt::Display| SkateError::Artifact(format!("{}: {}", path.display(), e));
+        let mut writer = BufWriter::new(File::create(path).map_err(|e| error(&e))?);
+        self.g_a.serialize_compressed(&mut writer).map_err(|e| error(&e))?;
+        self.g_b.serialize_compressed(&mut writer).map_err(|e| error(&e))?;
+        self.h_a.serialize_compressed(&mut writer).map_err(|e| error(&e))?;
+        self.h_b.serialize_compressed(&mut writer).map_err(|e| error(&e))
+    }
+}
+
+// Cross terms of a halving round, left ones scaled by the challenge x and right ones by 1 / x
+#[derive(Debug, Clone, PartialEq)]
+pub struct GipaRound {
+    pub z_ab: [Gt; 2],
+    pub com_ab: [[Gt; 2]; 2],
+    pub com_c: [[Gt; 2]; 2],
+    pub z_c: [G1Affine; 2],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregateProof {
+    pub com_ab: [Gt; 2], // Commitments to the A and B of the proofs, with the keys of a and of b
+    pub com_c: [Gt; 2],
+    pub z_ab: Gt,
+    pub z_c: G1Affine,
+    pub rounds: Vec<GipaRound>,
+    pub a: G1Affine, // A, B and C once folded
+    pub b: G2Affine,
+    pub c: G1Affine,
+    pub v: [G2Affine; 2], // Commitment keys once folded
+    pub w: [G1Affine; 2],
+    pub v_openings: [G2Affine; 2],
+    pub w_openings: [G1Affine; 2],
+}
+
+impl AggregateProof {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        fn write<T: CanonicalSerialize>(bytes: &mut Vec<u8>, value: &T) {
+            value.serialize_compressed(bytes).unwrap();
+        }
+        let mut bytes = vec![];
+        write(&mut bytes, &self.com_ab);
+        write(&mut bytes, &self.com_c);
+        write(&mut bytes, &self.z_ab);
+        write(&mut bytes, &self.z_c);
+        write(&mut bytes, &(self.rounds.len() as u64));
+        for round in &self.rounds {
+            write(&mut bytes, &round.z_ab);
+            write(&mut bytes, &round.com_ab);
+            write(&mut bytes, &round.com_c);
+            write(&mut bytes, &round.z_c);
+        }
+        write(&mut bytes, &(self.a, self.b, self.c));
+        write(&mut bytes, &(self.v, self.w));
+        write(&mut bytes, &(self.v_openings, self.w_openings));
+        bytes
+    }
+
+    // The points are checked on their curve and in their subgroup. The arrays are read element by element,
+    // their deserialization in ark-serialize panics on a short input
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<AggregateProof> {
+        fn read<T: CanonicalDeserialize>(reader: &mut &[u8]) -> Result<T> {
+            T::deserialize_compressed(reader).map_err(|_| invalid("malformed aggregate proof"))
+        }
+        fn array<T: CanonicalDeserialize, const N: usize>(reader: &mut &[u8]) -> Result<[T; N]> {
+            let mut values = Vec::with_capacity(N);
+            for _ in 0..N {
+                values.push(read(reader)?);
+            }
+            values.try_into().map_err(|_| invalid("malformed aggregate proof"))
+        }
+        fn pairs<T: CanonicalDeserialize>(reader: &mut &[u8]) -> Result<[[T; 2]; 2]> {
+            Ok([array(reader)?, array(reader)?])
+        }
+        let reader = &mut bytes;
+        let (com_ab, com_c, z_ab, z_c) = (array(reader)?, array(reader)?, read(reader)?, read(reader)?);
+        let count: u64 = read(reader)?;
+        if count > 32 {
+            return Err(invalid("malformed aggregate proof"));
+        }
+        let mut rounds = vec![];
+        for _ in 0..count {
+            let (z_ab, com_ab, com_c, z_c) = (array(reader)?, pairs(reader)?, pairs(reader)?, array(reader)?);
+            rounds.push(GipaRound { z_ab, com_ab, com_c, z_c });
+        }
+        let (a, b, c) = (read(reader)?, read(reader)?, read(reader)?);
+        let (v, w) = (array(reader)?, array(reader)?);
+        let (v_openings, w_openings) = (array(reader)?, array(reader)?);
+        let proof = AggregateProof { com_ab, com_c, z_ab, z_c, rounds, a, b, c, v, w, v_openings, w_openings };
+        if !reader.is_empty() {
+            return Err(invalid("malformed aggregate proof"));
+        }
+        Ok(proof)
+    }
+}
+
+// Fiat-Shamir transcript with the Anemoi hash, as the one of the PLONK backend, over the serialized
+// values in chunks below the scalar modulus
+struct Transcript {
+    state: Fr,
+}
+
+impl Transcript {
+    // Bound to the verifying key and the public inputs of the proofs
+    fn new(vk: &PreparedVerifyingKey<Bn254>, inputs: &[Vec<Fr>]) -> Transcript {
+        let mut transcript = Transcript { state: Fr::from(inputs.len() as u64) };
+        transcript.absorb(&vk.vk);
+        inputs.iter().for_each(|inputs| transcript.absorb(inputs));
+        transcript
+    }
+
+    fn absorb<T: CanonicalSerialize + ?Sized>(&mut self, value: &T) {
+        let mut bytes = vec![];
+        value.serialize_uncompressed(&mut bytes).unwrap();
+        for chunk in bytes.chunks(16) {
+            self.state = hash_2_to_1(self.state, Fr::from_le_bytes_mod_order(chunk));
+        }
+    }
+
+    // Non-zero, so that it can be inverted
+    fn challenge(&mut self) -> Result<(Fr, Fr)> {
+        self.state = hash_2_to_1(self.state, Fr::zero());
+        Ok((self.state, self.state.inverse().ok_or_else(|| invalid("zero challenge"))?))
+    }
+}
+
+// The inputs and proofs padded to a power of two, at least 2
+fn padded<T: Clone>(values: &[T]) -> Result<Vec<T>> {
+    let last = values.last().ok_or_else(|| invalid("no proof to aggregate"))?;
+    let mut values = values.to_vec();
+    values.resize(values.len().max(2).next_power_of_two(), last.clone());
+    Ok(values)
+}
+
+fn pairing_product(g1: &[G1Affine], g2: &[G2Affine]) -> Gt {
+    Bn254::multi_pairing(g1, g2)
+}
+
+fn fold<G: AffineRepr<ScalarField = Fr>>(points: &[G], scalar: Fr) -> Vec<G> {
+    let (left, right) = points.split_at(points.len() / 2);
+    let folded: Vec<G::Group> = left.iter().zip(right).map(|(l, r)| *l + *r * scalar).collect();
+    G::Group::normalize_batch(&folded)
+}
+
+// Coefficients of prod_j (1 + c_j X^(n / 2^(j + 1))), j over the rounds: the one of index i is the
+// product of the c_j of the bits of i
+fn folding_coefficients(challenges: &[Fr]) -> Vec<Fr> {
+    let mut coefficients = vec![Fr::one()];
+    for challenge in challenges.iter().rev() {
+        let scaled: Vec<Fr> = coefficients.iter().map(|c| *c * challenge).collect();
+        coefficients.extend(scaled);
+    }
+    coefficients
+}
+
+fn evaluate_folding(challenges: &[Fr], point: Fr) -> Fr {
+    let n = 1u64 << challenges.len();
+    challenges
+        .iter()
+        .enumerate()
+        .map(|(j, c)| Fr::one() + *c * point.pow([n >> (j + 1)]))
+        .product()
+}
+
+// Quotient of p(X) - p(point) by X - point
+fn divide_linear(coefficients: &[Fr], point: Fr) -> Vec<Fr> {
+    let mut quotient = vec![Fr::zero(); coefficients.len().saturating_sub(1)];
+    let mut carry = Fr::zero();
+    for i in (1..coefficients.len()).rev() {
+        carry = coefficients[i] + carry * point;
+        quotient[i - 1] = carry;
+    }
+    quotient
+}
+
+pub fn aggregate(
+    srs: &AggregationSrs,
+    vk: &PreparedVerifyingKey<Bn254>,
+    proofs: &[(Vec<Fr>, Proof<Bn254>)],
+) -> Result<AggregateProof> {
+    let proofs = padded(proofs)?;
+    let n = proofs.len();
+    if n > srs.max_proofs() {
+        return Err(SkateError::InvalidLength { expected: srs.max_proofs(), found: n });
+    }
+    let inputs: Vec<Vec<Fr>> = proofs.iter().map(|(inputs, _)| inputs.clone()).collect();
+    let mut a: Vec<G1Affine> = proofs.iter().map(|(_, proof)| proof.a).collect();
+    let mut b: Vec<G2Affine> = proofs.iter().map(|(_, proof)| proof.b).collect();
+    let mut c: Vec<G1Affine> = proofs.iter().map(|(_, proof)| proof.c).collect();
+    let mut v = [srs.h_a[..n].to_vec(), srs.h_b[..n].to_vec()];
+    let mut w = [srs.g_a[n..2 * n].to_vec(), srs.g_b[n..2 * n].to_vec()];
+
+    let mut transcript = Transcript::new(vk, &inputs);
+    let com_ab = [0, 1].map(|k| pairing_product(&a, &v[k]) + pairing_product(&w[k], &b));
+    let com_c = [0, 1].map(|k| pairing_product(&c, &v[k]));
+    transcript.absorb(&com_ab);
+    transcript.absorb(&com_c);
+    let (r, r_inverse) = transcript.challenge()?;
+
+    // r^i A_i and r^i C_i against the keys v_i / r^i
+    let (r_powers, r_inverse_powers) = (powers(r, n), powers(r_inverse, n));
+    let scale_g1 = |points: &[G1Affine], scalars: &[Fr]| {
+        let scaled: Vec<G1Projective> = points.iter().zip(scalars).map(|(p, s)| *p * s).collect();
+        G1Projective::normalize_batch(&scaled)
+    };
+    a = scale_g1(&a, &r_powers);
+    c = scale_g1(&c, &r_powers);
+    v = v.map(|key| {
+        let scaled: Vec<G2Projective> = key.iter().zip(&r_inverse_powers).map(|(p, s)| *p * s).collect();
+        G2Projective::normalize_batch(&scaled)
+    });
+    let z_ab = pairing_product(&a, &b);
+    let z_c = c.iter().map(|point| point.into_group()).sum::<G1Projective>().into_affine();
+    transcript.absorb(&z_ab);
+    transcript.absorb(&z_c);
+
+    let mut s = vec![Fr::one(); n];
+    let mut rounds = vec![];
+    let mut challenges = vec![];
+    while a.len() > 1 {
+        let m = a.len() / 2;
+        let (a_l, a_r) = a.split_at(m);
+        let (b_l, b_r) = b.split_at(m);
+        let (c_l, c_r) = c.split_at(m);
+        let (s_l, s_r) = s.split_at(m);
+        let round = GipaRound {
+            z_ab: [pairing_product(a_r, b_l), pairing_product(a_l, b_r)],
+            com_ab: [
+                [0, 1].map(|k| pairing_product(a_r, &v[k][..m]) + pairing_product(&w[k][m..], b_l)),
+                [0, 1].map(|k| pairing_product(a_l, &v[k][m..]) + pairing_product(&w[k][..m], b_r)),
+            ],
+            com_c: [
+                [0, 1].map(|k| pairing_product(c_r, &v[k][..m])),
+                [0, 1].map(|k| pairing_product(c_l, &v[k][m..])),
+            ],
+            z_c: [
+                G1Projective::msm_unchecked(c_r, s_l).into_affine(),
+                G1Projective::msm_unchecked(c_l, s_r).into_affine(),
+            ],
+        };
+        transcript.absorb(&round.z_ab);
+        transcript.absorb(&round.com_ab);
+        transcript.absorb(&round.com_c);
+        transcript.absorb(&round.z_c);
+        let (x, x_inverse) = transcript.challenge()?;
+        a = fold(&a, x);
+        c = fold(&c, x);
+        b = fold(&b, x_inverse);
+        v = v.map(|key| fold(&key, x_inverse));
+        w = w.map(|key| fold(&key, x));
+        s = s_l.iter().zip(s_r).map(|(l, r)| *l + x_inverse * r).collect();
+        rounds.push(round);
+        challenges.push(x);
+    }
+    let (v, w) = ([v[0][0], v[1][0]], [w[0][0], w[1][0]]);
+    transcript.absorb(&(a[0], b[0], c[0]));
+    transcript.absorb(&(v, w));
+    let (z, _) = transcript.challenge()?;
+
+    // The folded keys are [p_v(a)]_2 and [p_w(a)]_1 (and at b), opened at z
+    let inverses: Vec<Fr> = challenges.iter().map(|x| x.inverse().unwrap()).collect();
+    let p_v: Vec<Fr> = folding_coefficients(&inverses)
+        .iter()
+        .zip(&r_inverse_powers)
+        .map(|(c, r)| *c * r)
+        .collect();
+    let p_w: Vec<Fr> = [vec![Fr::zero(); n], folding_coefficients(&challenges)].concat();
+    let (q_v, q_w) = (divide_linear(&p_v, z), divide_linear(&p_w, z));
+    Ok(AggregateProof {
+        com_ab,
+        com_c,
+        z_ab,
+        z_c,
+        rounds,
+        a: a[0],
+        b: b[0],
+        c: c[0],
+        v,
+        w,
+        v_openings: [&srs.h_a, &srs.h_b].map(|h| G2Projective::msm_unchecked(&h[..q_v.len()], &q_v).into_affine()),
+        w_openings: [&srs.g_a, &srs.g_b].map(|g| G1Projective::msm_unchecked(&g[..q_w.len()], &q_w).into_affine()),
+    })
+}
+
+// Checks the aggregate of the proofs of the given public inputs, in the order they were aggregated
+pub fn verify_aggregate(
+    key: &AggregationKey,
+    vk: &PreparedVerifyingKey<Bn254>,
+    inputs: &[Vec<Fr>],
+    proof: &AggregateProof,
+) -> Result<bool> {
+    let bases = &vk.vk.gamma_abc_g1;
+    if let Some(inputs) = inputs.iter().find(|inputs| inputs.len() + 1 != bases.len()) {
+        return Err(SkateError::InvalidLength { expected: bases.len() - 1, found: inputs.len() });
+    }
+    let inputs = padded(inputs)?;
+    let n = inputs.len();
+    if proof.rounds.len() != n.ilog2() as usize {
+        return Ok(false);
+    }
+
+    let mut transcript = Transcript::new(vk, &inputs);
+    transcript.absorb(&proof.com_ab);
+    transcript.absorb(&proof.com_c);
+    let (r, r_inverse) = transcript.challenge()?;
+    transcript.absorb(&proof.z_ab);
+    transcript.absorb(&proof.z_c);
+    let (mut com_ab, mut com_c, mut z_ab) = (proof.com_ab, proof.com_c, proof.z_ab);
+    let mut z_c = proof.z_c.into_group();
+    let mut challenges = vec![];
+    let mut inverses = vec![];
+    for round in &proof.rounds {
+        transcript.absorb(&round.z_ab);
+        transcript.absorb(&round.com_ab);
+        transcript.absorb(&round.com_c);
+        transcript.absorb(&round.z_c);
+        let (x, x_inverse) = transcript.challenge()?;
+        z_ab += round.z_ab[0] * x + round.z_ab[1] * x_inverse;
+        for k in 0..2 {
+            com_ab[k] += round.com_ab[0][k] * x + round.com_ab[1][k] * x_inverse;
+            com_c[k] += round.com_c[0][k] * x + round.com_c[1][k] * x_inverse;
+        }
+        z_c += round.z_c[0] * x + round.z_c[1] * x_inverse;
+        challenges.push(x);
+        inverses.push(x_inverse);
+    }
+    transcript.absorb(&(proof.a, proof.b, proof.c));
+    transcript.absorb(&(proof.v, proof.w));
+    let (z, _) = transcript.challenge()?;
+
+    // The last A, B and C open the folded commitments
+    let s = inverses.iter().map(|x| Fr::one() + x).product::<Fr>();
+    let opened = (0..2).all(|k| {
+        com_ab[k] == pairing_product(&[proof.a, proof.w[k]], &[proof.v[k], proof.b])
+            && com_c[k] == Bn254::pairing(proof.c, proof.v[k])
+    });
+    if !opened || z_ab != Bn254::pairing(proof.a, proof.b) || z_c != proof.c * s {
+        return Ok(false);
+    }
+
+    // The folded keys are those of the reference string: KZG openings of p_v at z / r and p_w at z
+    let v_at = evaluate_folding(&inverses, z * r_inverse);
+    let w_at = z.pow([n as u64]) * evaluate_folding(&challenges, z);
+    for (k, (g_secret, h_secret)) in [(key.g_a, key.h_a), (key.g_b, key.h_b)].into_iter().enumerate() {
+        let v_opened = Bn254::pairing(g_secret.into_group() - key.g * z, proof.v_openings[k])
+            == Bn254::pairing(key.g, proof.v[k].into_group() - key.h * v_at);
+        let w_opened = Bn254::pairing(proof.w_openings[k], h_secret.into_group() - key.h * z)
+            == Bn254::pairing(proof.w[k].into_group() - key.g * w_at, key.h);
+        if !v_opened || !w_opened {
+            return Ok(false);
+        }
+    }
+
+    // The Groth16 equation of the n proofs combined with the powers of r
+    let r_powers = powers(r, n);
+    let mut coefficients = vec![Fr::zero(); bases.len()];
+    for (inputs, power) in inputs.iter().zip(&r_powers) {
+        coefficients[0] += power;
+        for (coefficient, input) in coefficients[1..].iter_mut().zip(inputs) {
+            *coefficient += *power * input;
+        }
+    }
+    let prepared_inputs = G1Projective::msm_unchecked(bases, &coefficients).into_affine();
+    let right = Bn254::multi_miller_loop(
+        [<Bn254 as Pairing>::G1Prepared::from(prepared_inputs), proof.z_c.into()],
+        [vk.gamma_g2_neg_pc.clone(), vk.delta_g2_neg_pc.clone()],
+    );
+    let right = Bn254::final_exponentiation(right).ok_or_else(|| invalid("unexpected identity"))?;
+    Ok(proof.z_ab + right == PairingOutput(vk.alpha_g1_beta_g2) * r_powers.iter().sum::<Fr>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_groth16::{prepare_verifying_key, Groth16};
+    use ark_relations::lc;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError, Variable};
+    use ark_std::rand::rngs::StdRng;
+    use ark_std::rand::SeedableRng;
+
+    // x * y = z with z and x + y public
+    #[derive(Clone)]
+    struct Product(Fr, Fr);
+
+    impl ConstraintSynthesizer<Fr> for Product {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> std::result::Result<(), SynthesisError> {
+            let z = cs.new_input_variable(|| Ok(self.0 * self.1))?;
+            let sum = cs.new_input_variable(|| Ok(self.0 + self.1))?;
+            let x = cs.new_witness_variable(|| Ok(self.0))?;
+            let y = cs.new_witness_variable(|| Ok(self.1))?;
+            cs.enforce_constraint(lc!() + x, lc!() + y, lc!() + z)?;
+            cs.enforce_constraint(lc!() + x + y, lc!() + Variable::One, lc!() + sum)
+        }
+    }
+
+    type Proofs = Vec<(Vec<Fr>, Proof<Bn254>)>;
+
+    fn proofs(count: u64, rng: &mut StdRng) -> (PreparedVerifyingKey<Bn254>, Proofs) {
+        let blank = Product(Fr::one(), Fr::one());
+        let pk = Groth16::<Bn254>::generate_random_parameters_with_reduction(blank, rng).unwrap();
+        let proofs = (0..count)
+            .map(|i| {
+                let (x, y) = (Fr::from(i + 2), Fr::from(3 * i + 5));
+                let proof = Groth16::<Bn254>::create_random_proof_with_reduction(Product(x, y), &pk, rng).unwrap();
+                (vec![x * y, x + y], proof)
+            })
+            .collect();
+        (prepare_verifying_key(&pk.vk), proofs)
+    }
+
+    #[test]
+    fn proofs_are_aggregated() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let srs = AggregationSrs::setup(8, &mut rng);
+        assert!(srs.check(&mut rng).is_ok());
+        let key = srs.key();
+        let (vk, proofs) = proofs(5, &mut rng);
+        let inputs: Vec<Vec<Fr>> = proofs.iter().map(|(inputs, _)| inputs.clone()).collect();
+
+        // 5 proofs padded to 8, 3 halving rounds
+        let proof = aggregate(&srs, &vk, &proofs).unwrap();
+        assert_eq!(proof.rounds.len(), 3);
+        assert!(verify_aggregate(&key, &vk, &inputs, &proof).unwrap());
+        assert_eq!(AggregateProof::from_bytes(&proof.to_bytes()).unwrap(), proof);
+        let bytes = proof.to_bytes();
+        assert!(AggregateProof::from_bytes(&bytes[1..]).is_err());
+        assert!(AggregateProof::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(AggregateProof::from_bytes(&[bytes.clone(), vec![0]].concat()).is_err());
+
+        // Two proofs, the fewest aggregated
+        let two = aggregate(&srs, &vk, &proofs[..2]).unwrap();
+        assert_eq!(two.rounds.len(), 1);
+        assert!(verify_aggregate(&key, &vk, &inputs[..2], &two).unwrap());
+
+        // More proofs than the reference string allows
+        let (_, many) = self::proofs(9, &mut rng);
+        assert!(aggregate(&srs, &vk, &many).is_err());
+    }
+
+    #[test]
+    fn tampered_aggregates_are_rejected() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let srs = AggregationSrs::setup(4, &mut rng);
+        let key = srs.key();
+        let (vk, proofs) = proofs(4, &mut rng);
+        let inputs: Vec<Vec<Fr>> = proofs.iter().map(|(inputs, _)| inputs.clone()).collect();
+        let proof = aggregate(&srs, &vk, &proofs).unwrap();
+        assert!(verify_aggregate(&key, &vk, &inputs, &proof).unwrap());
+
+        // Changed, reordered or missing inputs, and inputs of the wrong size
+        let mut changed = inputs.clone();
+        changed[2][1] += Fr::one();
+        assert!(!verify_aggregate(&key, &vk, &changed, &proof).unwrap());
+        let mut reordered = inputs.clone();
+        reordered.swap(0, 1);
+        assert!(!verify_aggregate(&key, &vk, &reordered, &proof).unwrap());
+        assert!(!verify_aggregate(&key, &vk, &inputs[..2], &proof).unwrap());
+        let mut longer = inputs.clone();
+        longer[0].push(Fr::one());
+        assert!(verify_aggregate(&key, &vk, &longer, &proof).is_err());
+
+        // A proof of other inputs among the aggregated ones
+        let mut wrong = proofs.clone();
+        wrong[3].1 = proofs[0].1.clone();
+        let aggregate_wrong = aggregate(&srs, &vk, &wrong).unwrap();
+        assert!(!verify_aggregate(&key, &vk, &inputs, &aggregate_wrong).unwrap());
+
+        // Each element moved
+        let g1 = G1Affine::generator();
+        let g2 = G2Affine::generator();
+        let gt = Bn254::pairing(g1, g2);
+        let mut tampered: Vec<AggregateProof> = vec![];
+        let mut push = |change: &dyn Fn(&mut AggregateProof)| {
+            let mut changed = proof.clone();
+            change(&mut changed);
+            tampered.push(changed);
+        };
+        push(&|p| p.com_ab[1] += gt);
+        push(&|p| p.com_c[0] += gt);
+        push(&|p| p.z_ab += gt);
+        push(&|p| p.z_c = (p.z_c + g1).into_affine());
+        push(&|p| p.rounds[0].z_ab[1] += gt);
+        push(&|p| p.rounds[1].com_ab[0][1] += gt);
+        push(&|p| p.rounds[0].com_c[1][0] += gt);
+        push(&|p| p.rounds[1].z_c[0] = (p.rounds[1].z_c[0] + g1).into_affine());
+        push(&|p| p.a = (p.a + g1).into_affine());
+        push(&|p| p.b = (p.b + g2).into_affine());
+        push(&|p| p.c = (p.c + g1).into_affine());
+        push(&|p| p.v[1] = (p.v[1] + g2).into_affine());
+        push(&|p| p.w[0] = (p.w[0] + g1).into_affine());
+        push(&|p| p.v_openings[0] = (p.v_openings[0] + g2).into_affine());
+        push(&|p| p.w_openings[1] = (p.w_openings[1] + g1).into_affine());
+        push(&|p| {
+            p.rounds.pop();
+        });
+        for changed in &tampered {
+            assert!(!verify_aggregate(&key, &vk, &inputs, changed).unwrap());
+        }
+
+        // The key of another reference string
+        let other = AggregationSrs::setup(4, &mut rng).key();
+        assert!(!verify_aggregate(&other, &vk, &inputs, &proof).unwrap());
+
+        // Inconsistent reference strings
+        let mut broken = srs.clone();
+        broken.g_b[5] = (broken.g_b[5] + g1).into_affine();
+        assert!(broken.check(&mut rng).is_err());
+        let mut broken = srs.clone();
+        broken.h_a.pop();
+        assert!(broken.check(&mut rng).is_err());
+    }
+
+    #[test]
+    fn aggregates_of_an_invalid_proof_are_rejected() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let srs = AggregationSrs::setup(4, &mut rng);
+        let key = srs.key();
+        let (vk, proofs) = proofs(4, &mut rng);
+        let inputs: Vec<Vec<Fr>> = proofs.iter().map(|(inputs, _)| inputs.clone()).collect();
+        let g1 = G1Affine::generator();
+
+        // One proof moved off its inputs, at each position, the others valid
+        for i in 0..proofs.len() {
+            let mut invalid = proofs.clone();
+            invalid[i].1.c = (invalid[i].1.c + g1).into_affine();
+            assert!(!Groth16::<Bn254>::verify_proof(&vk, &invalid[i].1, &inputs[i]).unwrap());
+            let proof = aggregate(&srs, &vk, &invalid).unwrap();
+            assert!(!verify_aggregate(&key, &vk, &inputs, &proof).unwrap(), "proof {}", i);
+        }
+
+        // A random proof, last of 3 so that the padding repeats it
+        let mut invalid = proofs[..3].to_vec();
+        invalid[2].1 = Proof { a: G1Affine::rand(&mut rng), b: G2Affine::rand(&mut rng), c: G1Affine::rand(&mut rng) };
+        let proof = aggregate(&srs, &vk, &invalid).unwrap();
+        assert!(!verify_aggregate(&key, &vk, &inputs[..3], &proof).unwrap());
+    }
+}
@@ -2,7 +2,18 @@ use ark_bn254::Fr;
 use ark_ff::Field;
 use std::str::FromStr;
 
-fn ark_layer(round_number: usize, inputs: [Fr; 2]) -> [Fr; 2] {
+// Shared with the R1CS version of the permutation (see gadgets::hash_2_to_1)
+pub(crate) const ROUNDS: usize = 21;
+pub(crate) const ALPHA_INV: [u64; 4] = [
+    14981214993055009997,
+    6006880321387387405,
+    10624953561019755799,
+    2789598613442376532,
+]; // 1/5
+pub(crate) const DELTA: &str =
+    "8755297148735710088898562298102910035419345760166413737479281674630323398247";
+
+pub(crate) fn ark_layer(round_number: usize, inputs: [Fr; 2]) -> [Fr; 2] {
     let c = [
         Fr::from_str("37").unwrap(),
         Fr::from_str(
@@ -173,7 +184,7 @@ fn ark_layer(round_number: usize, inputs: [Fr; 2]) -> [Fr; 2] {
     [inputs[0] + c[round_number], inputs[1] + d[round_number]]
 }
 
-fn mds_layer(inputs: [Fr; 2]) -> [Fr; 2] {
+pub(crate) fn mds_layer(inputs: [Fr; 2]) -> [Fr; 2] {
     [inputs[0] + inputs[1] + inputs[0], inputs[1] + inputs[0]]
 }
 
@@ -182,18 +193,8 @@ fn s_box(inputs: [Fr; 2]) -> [Fr; 2] {
     let mut y = inputs[1];
 
     x = x - Fr::from(5) * y * y;
-    y = y - x.pow(&[
-        14981214993055009997,
-        6006880321387387405,
-        10624953561019755799,
-        2789598613442376532,
-    ]); // 1/5
-    x = x
-        + Fr::from(5) * y * y
-        + Fr::from_str(
-            "8755297148735710088898562298102910035419345760166413737479281674630323398247",
-        )
-        .unwrap(); //DELTA
+    y = y - x.pow(&ALPHA_INV);
+    x = x + Fr::from(5) * y * y + Fr::from_str(DELTA).unwrap();
 
     [x, y]
 }
@@ -204,7 +205,7 @@ fn round(round_number: usize, inputs: [Fr; 2]) -> [Fr; 2] {
 
 fn permutation(inputs: [Fr; 2]) -> [Fr; 2] {
     let mut state = inputs;
-    for i in 0..21 {
+    for i in 0..ROUNDS {
         state = round(i, state);
     }
     state = mds_layer(state);
@@ -2,7 +2,10 @@ use ark_bn254::Fr;
 use ark_ff::Field;
 use std::str::FromStr;
 
-fn ark_layer(round_number: usize, inputs: [Fr; 4]) -> [Fr; 4] {
+// Shared with the R1CS version of the permutation (see gadgets::hash_4_to_1)
+pub(crate) const ROUNDS: usize = 14;
+
+pub(crate) fn ark_layer(round_number: usize, inputs: [Fr; 4]) -> [Fr; 4] {
     let c = [
         [
             Fr::from_str("37").unwrap(),
@@ -294,7 +297,7 @@ fn ark_layer(round_number: usize, inputs: [Fr; 4]) -> [Fr; 4] {
     ]
 }
 
-fn mds_layer(inputs: [Fr; 4]) -> [Fr; 4] {
+pub(crate) fn mds_layer(inputs: [Fr; 4]) -> [Fr; 4] {
     let x0 = inputs[0] + Fr::from(5) * inputs[1];
     let x1 = Fr::from(5) * inputs[0] + Fr::from(26) * inputs[1];
     let y0 = inputs[3] + Fr::from(5) * inputs[2];
@@ -348,7 +351,7 @@ fn round(round_number: usize, inputs: [Fr; 4]) -> [Fr; 4] {
 
 fn permutation(inputs: [Fr; 4]) -> [Fr; 4] {
     let mut state = inputs;
-    for i in 0..14 {
+    for i in 0..ROUNDS {
         state = round(i, state);
     }
     state = mds_layer(state);
@@ -1,31 +1,103 @@
 // CENTRAL POINT
 
 use ark_bn254::{Bn254, Fr};
-use ark_circom::{read_zkey, CircomBuilder, CircomConfig, CircomReduction};
-use ark_crypto_primitives::snark::SNARK;
 use ark_ff::{Field, PrimeField};
-use ark_groth16::{Groth16, Proof};
+use ark_groth16::Proof;
 use ark_std::rand::{thread_rng, Rng};
-use num_bigint::BigInt;
+use axum::{extract::State, http::StatusCode, routing, Json, Router};
 use reqwest::Error;
 use serde::Deserialize;
-use std::fs::File;
+use std::io;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
-use std::{io, thread};
-use SKATE::hashes::{get_merkle_proof_from_id, robot_root, task_root};
-use SKATE::Skate::{skate, Robot, Task};
+use tokio::sync::Mutex;
+use SKATE::graph::{road_map_witness, DistanceTable, RoadMap};
+use SKATE::position::{Frame, Position};
+use SKATE::lifecycle::{
+    busy_robots, task_state_changes, task_state_witness, TaskLedger, TaskState, TaskStateChange, TaskStateWitness,
+};
+use SKATE::hashes::{
+    distance_table_root, get_merkle_proof_from_id, robot_root, round_seed, task_root,
+};
+use SKATE::error::SkateError;
+#[cfg(feature = "ethereum")]
+use SKATE::ethereum::submit_calldata;
+use SKATE::inputs::{build_rollup_inputs, RollupInputs};
+use SKATE::metrics::{evaluate, RoundMetrics, Summary};
+use SKATE::plonk::Srs;
+use SKATE::proof_system::{Backend, Groth16Backend, ProofSystem};
+use SKATE::prover::Prover;
+use SKATE::registry::{Artifact, CircuitKey, CircuitRegistry};
+use SKATE::snarkjs::{write_proof, write_public_inputs, UpdateProof};
+use SKATE::Skate::{
+    apply_assignments_with_busy, create_robot_with_battery, create_task, is_expired,
+    skate_assignments_with_busy,
+    AssignmentMode, DistanceMetric, Robot, SkateParameters, Task, IDLE_TASK_ID, MAX_PREREQUISITES,
+};
 
-type GrothBn = Groth16<Bn254, CircomReduction>;
 #[derive(Debug, Deserialize)]
 struct ClientConfig {
     list_ip: Vec<String>,
     x: Vec<String>,
     y: Vec<String>,
     z: Vec<String>,
+    capabilities: Vec<String>, // Capability bitmask of each robot
+    #[serde(default)]
+    capacity: Vec<String>, // Battery capacity of each robot, in units of the metric (empty for no limit)
+    #[serde(default)]
+    stations: Vec<[u64; 3]>, // Charging stations (grid positions), each robot uses the closest one
     clock: u64,
     manual_choices: usize, // The number of manually generation per clock
     iterations: usize,     // Number of loop (0 for manual extinction)
+    mode: AssignmentMode,   // Must match the `mode` parameter of the circuits
+    metric: DistanceMetric, // Must match the `metric` parameter of the circuits
+    road_map: Option<String>, // Road map file, required by the graph metric
+    #[serde(default)]
+    frame: Frame, // Conversion of the manual inputs from meters, one meter per grid unit by default
+    listen: Option<String>, // Address of the endpoint receiving the task reports of the robots
+    #[serde(default = "default_requeue_after")]
+    requeue_after: u64, // Rounds without report after which an assigned task is re-queued
+    #[serde(default = "default_max_attempts")]
+    max_attempts: u32, // Assignments of a task before it fails
+    #[serde(default)]
+    fairness_penalty: u16, // Cost added per task already assigned to a robot, with the fair mode
+    #[serde(default)]
+    workload_cap: u32, // Tasks in its lifetime after which a robot takes no more, with the fair mode (0 for no cap)
+    #[serde(default = "default_registry")]
+    registry: String, // Circuit registry, see SKATE::registry
+    #[serde(default = "default_circuit_version")]
+    circuit_version: String, // Version of the registered circuits to prove with
+    #[serde(default = "default_backends")]
+    backends: Vec<Backend>, // Proof systems by preference, the rounds use the first one all the robots accept
+    srs: Option<String>, // Universal reference string, required to prove with PLONK
+    proofs: Option<String>, // Directory where the Groth16 proofs are exported for snarkjs, see update_proof
+}
+
+fn default_registry() -> String {
+    "circuits.toml".to_string()
+}
+
+fn default_circuit_version() -> String {
+    "v1".to_string()
+}
+
+fn default_backends() -> Vec<Backend> {
+    vec![Backend::Groth16]
+}
+
+fn default_requeue_after() -> u64 {
+    2
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+// Shared with the endpoint receiving the task reports
+struct Coordinator {
+    ledger: Mutex<TaskLedger>,
+    round: Mutex<u64>,
 }
 
 #[tokio::main]
@@ -70,214 +142,387 @@ async fn main() -> Result<(), Error> {
         configuration.z.len(),
         3
     );
+    assert_eq!(
+        configuration.capabilities.len(),
+        3
+    );
+    assert!(configuration.capacity.is_empty() || configuration.capacity.len() == 3);
+    assert!(configuration.capacity.is_empty() || !configuration.stations.is_empty());
+    // Batteries spend the travel cost of each move, see DistanceMetric::is_additive
+    assert!(configuration.capacity.is_empty() || configuration.metric.is_additive());
     assert!(configuration.manual_choices <= 6);
-    for i in 0..3 {
-        assert!(configuration.x[i].parse::<Fr>().unwrap() <= Fr::from(10000));
-        assert!(configuration.y[i].parse::<Fr>().unwrap() <= Fr::from(10000));
-        assert!(configuration.z[i].parse::<Fr>().unwrap() <= Fr::from(10000));
+
+    let mut parameters = SkateParameters {
+        mode: configuration.mode,
+        metric: configuration.metric,
+        seed: 0,
+        fairness_penalty: configuration.fairness_penalty,
+        workload_cap: configuration.workload_cap,
+    };
+
+    // With a road map, costs are travel times and the circuits are those of the road map
+    let road_map: Option<DistanceTable> = configuration.road_map.as_ref().map(|file| {
+        let content = match std::fs::read_to_string(std::env::current_dir().unwrap().join(file)) {
+            Ok(contenu) => contenu,
+            Err(e) => {
+                eprintln!("Cannot read the road map : {}", e);
+                std::process::exit(1);
+            }
+        };
+        let road_map = match toml::from_str::<RoadMap>(&content) {
+            Ok(road_map) => road_map,
+            Err(e) => {
+                eprintln!("Cannot deserialize the road map : {}", e);
+                std::process::exit(1);
+            }
+        };
+        match road_map.distance_table() {
+            Ok(table) => table,
+            Err(e) => {
+                eprintln!("Invalid road map : {}", e);
+                std::process::exit(1);
+            }
+        }
+    });
+    assert_eq!(road_map.is_some(), parameters.metric == DistanceMetric::Graph);
+    let road_map_tree = match road_map.as_ref().map(distance_table_root).transpose() {
+        Ok(tree) => tree,
+        Err(e) => {
+            eprintln!("Cannot commit the road map : {}", e);
+            std::process::exit(1);
+        }
+    };
+    let registry = match CircuitRegistry::load(&std::env::current_dir().unwrap().join(&configuration.registry))
+        .and_then(|registry| {
+            registry.check(&[Artifact::Zkey])?;
+            Ok(registry)
+        }) {
+        Ok(registry) => registry,
+        Err(e) => {
+            eprintln!("Invalid circuit registry : {}", e);
+            std::process::exit(1);
+        }
+    };
+    // One circuit per sub-round: 6 tasks, then the 3 left pending
+    let (circuit_6_3, circuit_3_3) = match (
+        registry.find(3, 6, &configuration.circuit_version, road_map.is_some()),
+        registry.find(3, 3, &configuration.circuit_version, road_map.is_some()),
+    ) {
+        (Ok(circuit_6_3), Ok(circuit_3_3)) => (circuit_6_3, circuit_3_3),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Invalid circuit registry : {}", e);
+            std::process::exit(1);
+        }
+    };
+    // The rounds are proven with the first backend of the preferences that all the robots accept
+    let mut accepted = vec![];
+    for ip in &configuration.list_ip {
+        accepted.push(accepted_backends(ip).await);
+    }
+    let backend = match Backend::negotiate(&configuration.backends, &accepted) {
+        Some(backend) => backend,
+        None => {
+            eprintln!("No proof system accepted by all the robots : {:?}", accepted);
+            std::process::exit(1);
+        }
+    };
+    println!("Rounds proven with {}", backend);
+    // The zkeys are parsed once for all the rounds, and the PLONK keys computed
+    let srs = match (backend, &configuration.srs) {
+        (Backend::Plonk, Some(path)) => {
+            match Srs::read(&std::env::current_dir().unwrap().join(path)).and_then(|srs| {
+                srs.check(&mut rng)?;
+                Ok(srs)
+            }) {
+                Ok(srs) => Some(srs),
+                Err(e) => {
+                    eprintln!("Invalid reference string : {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        (Backend::Plonk, None) => {
+            eprintln!("The PLONK backend needs a reference string");
+            std::process::exit(1);
+        }
+        (Backend::Groth16, _) => None,
+    };
+    let loaded = match &srs {
+        Some(srs) => Prover::load_with_srs([circuit_6_3, circuit_3_3], srs),
+        None => Prover::load([circuit_6_3, circuit_3_3]),
+    };
+    let prover = match loaded {
+        Ok(prover) => prover,
+        Err(e) => {
+            eprintln!("Cannot load the circuits : {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut stations = vec![];
+    for station in &configuration.stations {
+        let station = Position::new(station[0], station[1], station[2])
+            .expect("Charging station out of the grid");
+        stations.push(match &road_map {
+            Some(table) => table.nearest_node(station),
+            None => station,
+        });
     }
 
     let mut list_robot = vec![];
     for i in 0..3 {
-        list_robot.push(Robot {
-            robot_id: Fr::from(i as i32),
-            list_tasks: [
-                Task {
-                    task_id: Fr::from(0),
-                    x: Fr::from(0),
-                    y: Fr::from(0),
-                    z: Fr::from(0),
-                },
-                Task {
-                    task_id: Fr::from(1),
-                    x: configuration.x[i].parse::<Fr>().unwrap(),
-                    y: configuration.y[i].parse::<Fr>().unwrap(),
-                    z: configuration.z[i].parse::<Fr>().unwrap(),
-                },
-            ],
-        })
+        let position = Position::from_coordinates([
+            configuration.x[i].parse::<Fr>().unwrap(),
+            configuration.y[i].parse::<Fr>().unwrap(),
+            configuration.z[i].parse::<Fr>().unwrap(),
+        ])
+        .expect("Robot position out of the grid");
+        let robot = create_robot_with_battery(
+            Fr::from(i as i32),
+            configuration.capabilities[i].parse::<Fr>().unwrap(),
+            [Fr::from(IDLE_TASK_ID); 2],
+            [Position::default(), position],
+            configuration
+                .capacity
+                .get(i)
+                .map_or(Fr::from(0), |capacity| capacity.parse::<Fr>().unwrap()),
+            closest_station(&stations, position),
+        );
+        match robot {
+            Ok(robot) => list_robot.push(robot),
+            Err(e) => {
+                eprintln!("Invalid robot {} : {}", i, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(table) = &road_map {
+        for robot in &list_robot {
+            let task = robot.list_tasks[1];
+            assert!(table.node(task.position).is_some());
+        }
+    }
+    let mut root = match robot_root(&list_robot) {
+        Ok((root, _)) => root,
+        Err(e) => {
+            eprintln!("Cannot commit the robots : {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let coordinator = Arc::new(Coordinator {
+        ledger: Mutex::new(TaskLedger::new(configuration.requeue_after, configuration.max_attempts)),
+        round: Mutex::new(0),
+    });
+    if let Some(address) = &configuration.listen {
+        let app = Router::new()
+            .route("/status", routing::post(status))
+            .with_state(coordinator.clone());
+        let listener = tokio::net::TcpListener::bind(address).await.unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
     }
-    let mut root = robot_root(&list_robot).0;
 
+    let mut list_metrics: Vec<RoundMetrics> = vec![];
     let mut counter: usize = 0;
+    // Leaves of the task-state root last sent to the robots, the empty tree before the first round
+    let mut published_states = vec![];
     while counter < configuration.iterations {
+        let round = Fr::from(counter as u64);
+        *coordinator.round.lock().await = counter as u64;
         let mut list_task = vec![];
         for i in 0..configuration.manual_choices {
 
             let mut user_input_x = String::new();
             let mut user_input_y = String::new();
             let mut user_input_z = String::new();
+            let mut user_input_priority = String::new();
+            let mut user_input_deadline = String::new();
+            let mut user_input_requirements = String::new();
+            let mut user_input_team = String::new();
+            let mut user_input_prerequisites = String::new();
 
-            println!("Enter the task x coordinates:");
+            println!("Enter the task x coordinate (meters):");
             io::stdin()
                 .read_line(&mut user_input_x)
                 .expect("Failed to read line");
-            println!("Enter the task y coordinates:");
+            println!("Enter the task y coordinate (meters):");
             io::stdin()
                 .read_line(&mut user_input_y)
                 .expect("Failed to read line");
-            println!("Enter the task z coordinates:");
+            println!("Enter the task z coordinate (meters):");
             io::stdin()
                 .read_line(&mut user_input_z)
                 .expect("Failed to read line");
+            println!("Enter the task priority (0 to 255, empty for 0):");
+            io::stdin()
+                .read_line(&mut user_input_priority)
+                .expect("Failed to read line");
+            println!("Enter the task deadline round (empty for no deadline):");
+            io::stdin()
+                .read_line(&mut user_input_deadline)
+                .expect("Failed to read line");
+            println!("Enter the capabilities required by the task (bitmask, empty for none):");
+            io::stdin()
+                .read_line(&mut user_input_requirements)
+                .expect("Failed to read line");
+            println!("Enter the number of robots the task needs at once (empty for 1):");
+            io::stdin()
+                .read_line(&mut user_input_team)
+                .expect("Failed to read line");
+            println!("Enter the ids of the tasks to be done first (at most 2, comma separated, empty for none):");
+            io::stdin()
+                .read_line(&mut user_input_prerequisites)
+                .expect("Failed to read line");
 
-            let x = user_input_x.trim().parse::<Fr>().unwrap();
-            let y = user_input_y.trim().parse::<Fr>().unwrap();
-            let z = user_input_z.trim().parse::<Fr>().unwrap();
-            if x < Fr::from(10000) && y < Fr::from(10000) && z < Fr::from(10000) {
-                list_task.push(Task {
-                    task_id: Fr::from(i as u32),
-                    x,
-                    y,
-                    z,
-                })
-            } else {
-                println!("invalid input, a random task will be generated instead.");
-                list_task.push(Task {
-                    task_id: Fr::from(i as i32),
-                    x: Fr::from(rng.gen_range(0..10000)),
-                    y: Fr::from(rng.gen_range(0..10000)),
-                    z: Fr::from(rng.gen_range(0..10000)),
-                })
+            let position = match [&user_input_x, &user_input_y, &user_input_z]
+                .map(|input| input.trim().parse::<f64>())
+            {
+                [Ok(x), Ok(y), Ok(z)] => configuration.frame.from_meters([x, y, z]),
+                _ => None,
+            };
+            let prerequisites = parse_prerequisites(&user_input_prerequisites);
+            let fields = [&user_input_priority, &user_input_deadline, &user_input_requirements, &user_input_team]
+                .map(|input| parse_or_zero(input));
+            // The round keeps its number of tasks, an invalid one is replaced by a random task
+            match (position, prerequisites, fields) {
+                (
+                    Some(position),
+                    Some(prerequisites),
+                    [Some(priority), Some(deadline), Some(requirements), Some(team_size)],
+                ) => {
+                    let task = Task {
+                        task_id: Fr::from(i as u32),
+                        position,
+                        priority,
+                        deadline,
+                        requirements,
+                        team_size,
+                        prerequisites,
+                    };
+                    // Fields out of their range, such as a deadline above MAX_DEADLINE, are rejected here
+                    match is_expired(&task, round) {
+                        Ok(false) => list_task.push(task),
+                        Ok(true) => {
+                            println!("expired task, replaced by a random task.");
+                            list_task.push(random_task(i, &configuration.frame, &mut rng));
+                        }
+                        Err(e) => {
+                            println!("invalid task ({}), replaced by a random task.", e);
+                            list_task.push(random_task(i, &configuration.frame, &mut rng));
+                        }
+                    }
+                }
+                _ => {
+                    println!("invalid input, replaced by a random task.");
+                    list_task.push(random_task(i, &configuration.frame, &mut rng));
+                }
             }
             println!();
         }
 
-        for i in configuration.manual_choices..6
-        {
-            list_task.push(Task {
-                task_id: Fr::from(i as i32),
-                x: Fr::from(rng.gen_range(0..10000)),
-                y: Fr::from(rng.gen_range(0..10000)),
-                z: Fr::from(rng.gen_range(0..10000)),
-            })
-        }
-
-        let task_root = task_root(&list_task);
-        let list_new_robot = skate(&list_robot, &list_task);
-        let (new_robot_root, merkle_proofs) =
-            robot_root(&list_new_robot);
-        let cfg = CircomConfig::<Bn254>::new(
-            std::env::current_dir()
-                .unwrap()
-                .join("skate_6_3.wasm"),
-            std::env::current_dir()
-                .unwrap()
-                .join("skate_6_3.r1cs"),
-        )
-        .unwrap();
-        let mut builder = CircomBuilder::new(cfg);
-
-        for i in 0..list_robot.len() {
-            builder.push_input(
-                "robots_id",
-                Fr::from(i as i32)
-                    .into_bigint()
-                    .to_string()
-                    .parse::<BigInt>()
-                    .unwrap(),
-            );
-        }
-        for i in 0..list_robot.len() {
-            builder.push_input(
-                "old_robots_tasks_id",
-                list_robot[i].list_tasks[0]
-                    .task_id
-                    .into_bigint()
-                    .to_string()
-                    .parse::<BigInt>()
-                    .unwrap(),
-            );
-            builder.push_input(
-                "old_robots_tasks_id",
-                list_robot[i].list_tasks[1]
-                    .task_id
-                    .into_bigint()
-                    .to_string()
-                    .parse::<BigInt>()
-                    .unwrap(),
-            );
-        }
-        for i in 0..list_robot.len() {
-            for k in 0..2 {
-                for j in 0..3 {
-                    builder.push_input(
-                        "old_robots_positions",
-                        match j {
-                            0i32 => list_robot[i].list_tasks[k].x,
-                            1i32 => list_robot[i].list_tasks[k].y,
-                            2i32 => list_robot[i].list_tasks[k].z,
-                            _ => Fr::from(0),
-                        }
-                        .into_bigint()
-                        .to_string()
-                        .parse::<BigInt>()
-                        .unwrap(),
-                    );
+        // New tasks join the pending ones, abandoned tasks are pending again
+        let batch = {
+            let mut ledger = coordinator.ledger.lock().await;
+            match ledger.requeue_abandoned(counter as u64) {
+                Ok(requeued) if !requeued.is_empty() => println!("Re-queued tasks: {:?}", requeued),
+                Ok(_) => {}
+                Err(e) => eprintln!("Cannot re-queue the tasks : {}", e),
+            }
+            for mut task in list_task {
+                if let Some(table) = &road_map {
+                    task.position = table.nearest_node(task.position);
+                }
+                if let Err(e) = ledger.add(task, counter as u64) {
+                    eprintln!("Task dropped : {}", e);
                 }
             }
-        }
-        builder.push_input(
-            "old_robots_root",
-            root.into_bigint().to_string().parse::<BigInt>().unwrap(),
-        );
-        for i in 0..list_task.len() {
-            builder.push_input(
-                "tasks_id",
-                list_task[i]
-                    .task_id
-                    .into_bigint()
-                    .to_string()
-                    .parse::<BigInt>()
-                    .unwrap(),
-            );
-        }
-        for i in 0..list_task.len() {
-            for j in 0..3 {
-                builder.push_input(
-                    "tasks_positions",
-                    match j {
-                        0 => list_task[i].x,
-                        1 => list_task[i].y,
-                        2 => list_task[i].z,
-                        _ => Fr::from(0),
-                    }
-                    .into_bigint()
-                    .to_string()
-                    .parse::<BigInt>()
-                    .unwrap(),
-                );
+            next_batch(&mut ledger, 6, counter as u64, &configuration.frame, road_map.as_ref(), &mut rng)
+                .map(|(batch, list_task)| (batch, list_task, ledger.states()))
+        };
+        let (batch, list_task, states) = match batch {
+            Ok(batch) => batch,
+            Err(e) => {
+                eprintln!("Round {} skipped : {}", counter, e);
+                counter = counter + 1;
+                continue;
             }
-        }
-        builder.push_input(
-            "tasks_root",
-            task_root
-                .into_bigint()
-                .to_string()
-                .parse::<BigInt>()
-                .unwrap(),
-        );
-
-        let params = read_zkey(
-            &mut File::open(
-                std::env::current_dir()
-                    .unwrap()
-                    .join("skate_6_3.zkey"),
-            )
-            .unwrap(),
-        )
-        .unwrap()
-        .0;
-
-        let circom = builder.build().unwrap();
+        };
+
+        // A seed per proof, fixed by the root and the round so that no robot is always favored on ties and
+        // the seed cannot be chosen (checked by the proof)
+        parameters.seed = round_seed(root, round);
+        let (task_root, assignments, list_new_robot, new_robot_root, merkle_proofs, task_state) =
+            match plan(counter as u64, &list_robot, &list_task, &states, &parameters, road_map.as_ref()) {
+                Ok((plan, metrics)) => {
+                    println!("{}", metrics);
+                    list_metrics.push(metrics);
+                    plan
+                }
+                Err(e) => {
+                    eprintln!("Round {} skipped : {}", counter, e);
+                    counter = counter + 1;
+                    continue;
+                }
+            };
+        let inputs = match circuit_inputs(
+            &list_robot,
+            &list_task,
+            root,
+            task_root,
+            round,
+            &parameters,
+            &task_state,
+            road_map.as_ref().zip(road_map_tree.as_ref()),
+        ) {
+            Ok(inputs) => inputs,
+            Err(e) => {
+                eprintln!("Round {} skipped : {}", counter, e);
+                counter = counter + 1;
+                continue;
+            }
+        };
+        let zkproof = match prover
+            .prove_with(&circuit_6_3.key(), backend, &inputs, &mut rng)
+            .and_then(|(proof, public_inputs)| {
+                update_proof(configuration.proofs.as_deref(), counter, &circuit_6_3.key(), backend, &proof, &public_inputs)
+            }) {
+            Ok(proof) => proof,
+            Err(e) => {
+                eprintln!("Round {} skipped : {}", counter, e);
+                counter = counter + 1;
+                continue;
+            }
+        };
+
+        // Leaves changed since the root last sent to the robots, they replay them up to the root of the proof
+        let changes = match task_state_changes(&published_states, &states) {
+            Ok(changes) => changes,
+            Err(e) => {
+                eprintln!("Round {} skipped : {}", counter, e);
+                counter = counter + 1;
+                continue;
+            }
+        };
 
-        let zkproof = GrothBn::prove(&params, circom, &mut rng).unwrap();
+        // The ledger records the assignments the proof commits to before the robots learn them
+        if let Err(e) = coordinator.ledger.lock().await.assign(counter as u64, &batch, &list_robot, &assignments) {
+            eprintln!("Round {} skipped, cannot record the assignments : {}", counter, e);
+            counter = counter + 1;
+            continue;
+        }
+        published_states = assigned_states(&states, &list_task, &assignments);
 
         for i in 0..list_robot.len() {
-            let merkle_proof = get_merkle_proof_from_id(
+            let merkle_proof = match get_merkle_proof_from_id(
                 Fr::from(i as u32),
                 &merkle_proofs,
-            );
+            ) {
+                Ok(proof) => proof,
+                Err(e) => {
+                    eprintln!("No Merkle proof for robot {} : {}", i, e);
+                    continue;
+                }
+            };
             let _ = post(
                 new_robot_root,
                 task_root,
@@ -285,153 +530,108 @@ async fn main() -> Result<(), Error> {
                 list_new_robot[i],
                 merkle_proof,
                 &configuration.list_ip[i],
-                "6_3"
+                &circuit_6_3.key().to_string(),
+                backend,
+                round,
+                parameters.seed,
+                &task_state,
+                &changes,
             )
             .await;
         }
-
         list_robot = list_new_robot;
         root = new_robot_root;
 
 
-        for i in 0..list_robot.len() {
-            for j in (0..list_task.len()).rev() {
-                if list_task[j].task_id == list_robot[i].list_tasks[1].task_id {
-                    _ = list_task.remove(j);
-                }
+        // The tasks left pending are assigned in the second sub-round
+        let batch = {
+            let mut ledger = coordinator.ledger.lock().await;
+            next_batch(&mut ledger, 3, counter as u64, &configuration.frame, road_map.as_ref(), &mut rng)
+                .map(|(batch, list_task)| (batch, list_task, ledger.states()))
+        };
+        let (batch, list_task, states) = match batch {
+            Ok(batch) => batch,
+            Err(e) => {
+                eprintln!("Round {} skipped : {}", counter, e);
+                counter = counter + 1;
+                continue;
             }
-        }
-        for i in 0..list_task.len() {
-            list_task[i].task_id = Fr::from(i as u32);
-        }
-
-
-        let task_root = SKATE::hashes::task_root(&list_task);
-        let list_new_robot = skate(&list_robot, &list_task);
-        let (new_robot_root, merkle_proofs) =
-            robot_root(&list_new_robot);
-
-        let cfg = CircomConfig::<Bn254>::new(
-            std::env::current_dir()
-                .unwrap()
-                .join("skate_3_3.wasm"),
-            std::env::current_dir()
-                .unwrap()
-                .join("skate_3_3.r1cs"),
-        )
-            .unwrap();
-        let mut builder = CircomBuilder::new(cfg);
-
-        for i in 0..list_robot.len() {
-            builder.push_input(
-                "robots_id",
-                Fr::from(i as i32)
-                    .into_bigint()
-                    .to_string()
-                    .parse::<BigInt>()
-                    .unwrap(),
-            );
-        }
-        for i in 0..list_robot.len() {
-            builder.push_input(
-                "old_robots_tasks_id",
-                list_robot[i].list_tasks[0]
-                    .task_id
-                    .into_bigint()
-                    .to_string()
-                    .parse::<BigInt>()
-                    .unwrap(),
-            );
-            builder.push_input(
-                "old_robots_tasks_id",
-                list_robot[i].list_tasks[1]
-                    .task_id
-                    .into_bigint()
-                    .to_string()
-                    .parse::<BigInt>()
-                    .unwrap(),
-            );
-        }
-        for i in 0..list_robot.len() {
-            for k in 0..2 {
-                for j in 0..3 {
-                    builder.push_input(
-                        "old_robots_positions",
-                        match j {
-                            0i32 => list_robot[i].list_tasks[k].x,
-                            1i32 => list_robot[i].list_tasks[k].y,
-                            2i32 => list_robot[i].list_tasks[k].z,
-                            _ => Fr::from(0),
-                        }
-                            .into_bigint()
-                            .to_string()
-                            .parse::<BigInt>()
-                            .unwrap(),
-                    );
+        };
+
+        parameters.seed = round_seed(root, round);
+        let (task_root, assignments, list_new_robot, new_robot_root, merkle_proofs, task_state) =
+            match plan(counter as u64, &list_robot, &list_task, &states, &parameters, road_map.as_ref()) {
+                Ok((plan, metrics)) => {
+                    println!("{}", metrics);
+                    list_metrics.push(metrics);
+                    plan
+                }
+                Err(e) => {
+                    eprintln!("Round {} skipped : {}", counter, e);
+                    counter = counter + 1;
+                    continue;
                 }
+            };
+
+        let inputs = match circuit_inputs(
+            &list_robot,
+            &list_task,
+            root,
+            task_root,
+            round,
+            &parameters,
+            &task_state,
+            road_map.as_ref().zip(road_map_tree.as_ref()),
+        ) {
+            Ok(inputs) => inputs,
+            Err(e) => {
+                eprintln!("Round {} skipped : {}", counter, e);
+                counter = counter + 1;
+                continue;
             }
-        }
-        builder.push_input(
-            "old_robots_root",
-            root.into_bigint().to_string().parse::<BigInt>().unwrap(),
-        );
-        for i in 0..list_task.len() {
-            builder.push_input(
-                "tasks_id",
-                list_task[i]
-                    .task_id
-                    .into_bigint()
-                    .to_string()
-                    .parse::<BigInt>()
-                    .unwrap(),
-            );
-        }
-        for i in 0..list_task.len() {
-            for j in 0..3 {
-                builder.push_input(
-                    "tasks_positions",
-                    match j {
-                        0 => list_task[i].x,
-                        1 => list_task[i].y,
-                        2 => list_task[i].z,
-                        _ => Fr::from(0),
-                    }
-                        .into_bigint()
-                        .to_string()
-                        .parse::<BigInt>()
-                        .unwrap(),
-                );
+        };
+        let zkproof = match prover
+            .prove_with(&circuit_3_3.key(), backend, &inputs, &mut rng)
+            .and_then(|(proof, public_inputs)| {
+                update_proof(configuration.proofs.as_deref(), counter, &circuit_3_3.key(), backend, &proof, &public_inputs)
+            }) {
+            Ok(proof) => proof,
+            Err(e) => {
+                eprintln!("Round {} skipped : {}", counter, e);
+                counter = counter + 1;
+                continue;
             }
-        }
-        builder.push_input(
-            "tasks_root",
-            task_root
-                .into_bigint()
-                .to_string()
-                .parse::<BigInt>()
-                .unwrap(),
-        );
-
-        let params = read_zkey(
-            &mut File::open(
-                std::env::current_dir()
-                    .unwrap()
-                    .join("skate_3_3.zkey"),
-            )
-                .unwrap(),
-        )
-            .unwrap()
-            .0;
-
-        let circom = builder.build().unwrap();
+        };
+
+        // Leaves changed since the root last sent to the robots, they replay them up to the root of the proof
+        let changes = match task_state_changes(&published_states, &states) {
+            Ok(changes) => changes,
+            Err(e) => {
+                eprintln!("Round {} skipped : {}", counter, e);
+                counter = counter + 1;
+                continue;
+            }
+        };
 
-        let zkproof = GrothBn::prove(&params, circom, &mut rng).unwrap();
+        // The ledger records the assignments the proof commits to before the robots learn them
+        if let Err(e) = coordinator.ledger.lock().await.assign(counter as u64, &batch, &list_robot, &assignments) {
+            eprintln!("Round {} skipped, cannot record the assignments : {}", counter, e);
+            counter = counter + 1;
+            continue;
+        }
+        published_states = assigned_states(&states, &list_task, &assignments);
 
         for i in 0..list_robot.len() {
-            let merkle_proof = get_merkle_proof_from_id(
+            let merkle_proof = match get_merkle_proof_from_id(
                 Fr::from(i as u32),
                 &merkle_proofs,
-            );
+            ) {
+                Ok(proof) => proof,
+                Err(e) => {
+                    eprintln!("No Merkle proof for robot {} : {}", i, e);
+                    continue;
+                }
+            };
             let _ = post(
                 new_robot_root,
                 task_root,
@@ -439,31 +639,89 @@ async fn main() -> Result<(), Error> {
                 list_new_robot[i],
                 merkle_proof,
                 &configuration.list_ip[i],
-                "3_3"
+                &circuit_3_3.key().to_string(),
+                backend,
+                round,
+                parameters.seed,
+                &task_state,
+                &changes,
             )
                 .await;
         }
-
         list_robot = list_new_robot;
         root = new_robot_root;
 
         counter = counter + 1;
 
         if counter < configuration.iterations {
-            thread::sleep(Duration::from_secs(configuration.clock));
+            tokio::time::sleep(Duration::from_secs(configuration.clock)).await;
         }
     }
+    println!("{}", Summary::new(&list_metrics));
     Ok(())
 }
 
+// Proof of the updates of a round, its Groth16 proof also written as <round>/<tasks>_<robots>/proof.json
+// and public.json in the export directory, to be checked with snarkjs, with the calldata of the rollup
+// contract (see SKATE::ethereum)
+fn update_proof(
+    export: Option<&str>,
+    round: usize,
+    circuit: &CircuitKey,
+    backend: Backend,
+    proof: &[String],
+    public_inputs: &[Fr],
+) -> Result<UpdateProof, SkateError> {
+    let update_proof = UpdateProof::new(backend, proof)?;
+    if let (Some(export), Backend::Groth16) = (export, backend) {
+        let directory = PathBuf::from(export).join(round.to_string()).join(format!("{}_{}", circuit.tasks, circuit.robots));
+        std::fs::create_dir_all(&directory)
+            .map_err(|e| SkateError::Artifact(format!("{}: {}", directory.display(), e)))?;
+        write_proof(&directory.join("proof.json"), &Groth16Backend.decode(proof)?)?;
+        write_public_inputs(&directory.join("public.json"), public_inputs)?;
+        #[cfg(feature = "ethereum")]
+        {
+            let calldata = submit_calldata(&Groth16Backend.decode(proof)?, public_inputs)?;
+            let path = directory.join("calldata.txt");
+            std::fs::write(&path, calldata.to_string())
+                .map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))?;
+        }
+    }
+    Ok(update_proof)
+}
+
+// Backends accepted by a robot, Groth16 for the robots predating the negotiation
+async fn accepted_backends(ip: &str) -> Vec<Backend> {
+    let response = match reqwest::get(format!("http://{}/backends", ip)).await {
+        Ok(response) if response.status().is_success() => response.text().await,
+        Ok(_) => return vec![Backend::Groth16],
+        Err(e) => {
+            eprintln!("Cannot reach robot {}, Groth16 assumed : {}", ip, e);
+            return vec![Backend::Groth16];
+        }
+    };
+    match response.map(|body| serde_json::from_str::<Vec<Backend>>(&body)) {
+        Ok(Ok(backends)) => backends,
+        _ => {
+            eprintln!("Invalid backends from robot {}, Groth16 assumed", ip);
+            vec![Backend::Groth16]
+        }
+    }
+}
+
 async fn post(
     robot_root: Fr,
     task_root: Fr,
-    proof: &Proof<Bn254>,
+    proof: &UpdateProof,
     robot: Robot,
     merkle: Vec<Fr>,
     ip: &str,
-    circuit: &str
+    circuit: &str,
+    backend: Backend,
+    round: Fr,
+    seed: u32,
+    task_state: &TaskStateWitness,
+    changes: &[TaskStateChange],
 ) -> Result<(), Error> {
     let mut url = "http://".to_owned();
     url.push_str(&ip);
@@ -476,32 +734,39 @@ async fn post(
     json_data.push_str(&task_root.into_bigint().to_string());
     json_data.push_str(r#"","circuit" : ""#);
     json_data.push_str(circuit);
-    json_data.push_str(r#"","proof" : [""#);
-    json_data.push_str(&proof.a.x.into_bigint().to_string());
-    json_data.push_str(r#"",""#);
-    json_data.push_str(&proof.a.y.into_bigint().to_string());
-    json_data.push_str(r#"",""#);
-    let mut b1 = proof.b.x.to_base_prime_field_elements();
-    let mut b2 = proof.b.y.to_base_prime_field_elements();
-    json_data.push_str(&b1.next().unwrap().into_bigint().to_string());
-    json_data.push_str(r#"",""#);
-    json_data.push_str(&b1.next().unwrap().into_bigint().to_string());
-    json_data.push_str(r#"",""#);
-    json_data.push_str(&b2.next().unwrap().into_bigint().to_string());
-    json_data.push_str(r#"",""#);
-    json_data.push_str(&b2.next().unwrap().into_bigint().to_string());
-    json_data.push_str(r#"",""#);
-    json_data.push_str(&proof.c.x.into_bigint().to_string());
-    json_data.push_str(r#"",""#);
-    json_data.push_str(&proof.c.y.into_bigint().to_string());
-    json_data.push_str(r#""],"task_id" : ""#);
+    json_data.push_str(r#"","backend" : ""#);
+    json_data.push_str(&backend.to_string());
+    json_data.push_str(r#"","round" : ""#);
+    json_data.push_str(&round.into_bigint().to_string());
+    json_data.push_str(r#"","seed" : ""#);
+    json_data.push_str(&seed.to_string());
+    json_data.push_str(r#"","task_state_root" : ""#);
+    json_data.push_str(&task_state.root.into_bigint().to_string());
+    json_data.push_str(r#"","new_task_state_root" : ""#);
+    json_data.push_str(&task_state.new_root.into_bigint().to_string());
+    json_data.push_str(r#"","task_state_changes" : "#);
+    let changes: Vec<_> = changes
+        .iter()
+        .map(|change| {
+            serde_json::json!({
+                "slot": change.slot,
+                "old": [change.old.0.into_bigint().to_string(), change.old.1.into_bigint().to_string()],
+                "new": [change.new.0.into_bigint().to_string(), change.new.1.into_bigint().to_string()],
+                "path": change.path.iter().map(|hash| hash.into_bigint().to_string()).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    json_data.push_str(&serde_json::to_string(&changes).unwrap());
+    json_data.push_str(r#","proof" : "#);
+    json_data.push_str(&serde_json::to_string(proof).unwrap());
+    json_data.push_str(r#","task_id" : ""#);
     json_data.push_str(&robot.list_tasks[1].task_id.into_bigint().to_string());
-    json_data.push_str(r#"","x" : ""#);
-    json_data.push_str(&robot.list_tasks[1].x.into_bigint().to_string());
-    json_data.push_str(r#"","y" : ""#);
-    json_data.push_str(&robot.list_tasks[1].y.into_bigint().to_string());
-    json_data.push_str(r#"","z" : ""#);
-    json_data.push_str(&robot.list_tasks[1].z.into_bigint().to_string());
+    json_data.push_str(r#"","position" : "#);
+    json_data.push_str(&serde_json::to_string(&robot.list_tasks[1].position).unwrap());
+    json_data.push_str(r#","battery" : ""#);
+    json_data.push_str(&robot.battery.into_bigint().to_string());
+    json_data.push_str(r#"","workload" : ""#);
+    json_data.push_str(&robot.workload.into_bigint().to_string());
     json_data.push_str(r#"","merkle_proof" : [""#);
     for i in 0..merkle.len() {
         json_data.push_str(&merkle[i].into_bigint().to_string());
@@ -566,13 +831,9 @@ async fn post_3_3(
     json_data.push_str(&proof.c.y.into_bigint().to_string());
     json_data.push_str(r#""],"task_id" : ""#);
     json_data.push_str(&robot.list_tasks[1].task_id.into_bigint().to_string());
-    json_data.push_str(r#"","x" : ""#);
-    json_data.push_str(&robot.list_tasks[1].x.into_bigint().to_string());
-    json_data.push_str(r#"","y" : ""#);
-    json_data.push_str(&robot.list_tasks[1].y.into_bigint().to_string());
-    json_data.push_str(r#"","z" : ""#);
-    json_data.push_str(&robot.list_tasks[1].z.into_bigint().to_string());
-    json_data.push_str(r#"","merkle_proof" : [""#);
+    json_data.push_str(r#"","position" : "#);
+    json_data.push_str(&serde_json::to_string(&robot.list_tasks[1].position).unwrap());
+    json_data.push_str(r#","merkle_proof" : [""#);
     for i in 0..merkle.len() {
         json_data.push_str(&merkle[i].into_bigint().to_string());
         if i != merkle.len() - 1 {
@@ -598,4 +859,165 @@ async fn post_3_3(
     );
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// A random task is always valid, the position is drawn inside the frame
+fn random_task(i: usize, frame: &Frame, rng: &mut impl Rng) -> Task {
+    create_task(
+        Fr::from(i as i32),
+        frame
+            .position(
+                rng.gen_range(0..frame.bounds()[0]),
+                rng.gen_range(0..frame.bounds()[1]),
+                rng.gen_range(0..frame.bounds()[2]),
+            )
+            .unwrap(),
+    )
+    .unwrap()
+}
+
+// Task root, assignment, new robot tree and task-state witness of a round, with the quality of the
+// assignment. `states` are the leaves of the task-state tree (see TaskLedger::states): the robots still
+// holding a task keep it
+fn plan(
+    round: u64,
+    robots: &Vec<Robot>,
+    tasks: &Vec<Task>,
+    states: &Vec<(Fr, Fr)>,
+    parameters: &SkateParameters,
+    road_map: Option<&DistanceTable>,
+) -> Result<((Fr, Vec<Option<usize>>, Vec<Robot>, Fr, Vec<Vec<Fr>>, TaskStateWitness), RoundMetrics), SkateError> {
+    let task_root = task_root(tasks)?;
+    let busy = busy_robots(states, robots)?;
+    let assignments = skate_assignments_with_busy(robots, tasks, parameters, road_map, &busy)?;
+    let metrics = evaluate(round, robots, tasks, &assignments, parameters, road_map)?;
+    let new_robots = apply_assignments_with_busy(robots, tasks, &assignments, parameters.metric, road_map, &busy)?;
+    let (new_robot_root, merkle_proofs) = robot_root(&new_robots)?;
+    let task_state = task_state_witness(states, robots, tasks, &assignments)?;
+    Ok(((task_root, assignments, new_robots, new_robot_root, merkle_proofs, task_state), metrics))
+}
+
+// Leaves of the task-state tree of a round once its tasks are assigned, those of the new root of its proof
+fn assigned_states(states: &[(Fr, Fr)], tasks: &[Task], assignments: &[Option<usize>]) -> Vec<(Fr, Fr)> {
+    let assigned: Vec<Fr> = assignments.iter().flatten().map(|k| tasks[*k].task_id).collect();
+    states
+        .iter()
+        .map(|(id, state)| (*id, if assigned.contains(id) { TaskState::Assigned.value() } else { *state }))
+        .collect()
+}
+
+// Tops the pending tasks up with random ones and returns the ledger ids of the `size` oldest, with
+// their tasks. An error if the task-state tree has no free slot left
+fn next_batch(
+    ledger: &mut TaskLedger,
+    size: usize,
+    round: u64,
+    frame: &Frame,
+    road_map: Option<&DistanceTable>,
+    rng: &mut impl Rng,
+) -> Result<(Vec<usize>, Vec<Task>), SkateError> {
+    while ledger.pending(size).len() < size {
+        let mut task = random_task(0, frame, rng);
+        if let Some(table) = road_map {
+            task.position = table.nearest_node(task.position);
+        }
+        ledger.add(task, round)?;
+    }
+    let batch = ledger.pending(size);
+    // Pending ids come oldest first, so the task ids of the batch are increasing
+    let tasks = batch.iter().map(|id| ledger.entry(*id).unwrap().task).collect();
+    Ok((batch, tasks))
+}
+
+#[derive(Deserialize)]
+struct StatusReport {
+    robot_id: String,
+    state: TaskState, // in_progress, done or failed
+}
+
+// Task reports of the robots, committed in the task-state root of the next round
+async fn status(
+    State(coordinator): State<Arc<Coordinator>>,
+    Json(report): Json<StatusReport>,
+) -> (StatusCode, Json<String>) {
+    let robot_id = match report.robot_id.parse::<Fr>() {
+        Ok(robot_id) => robot_id,
+        Err(_) => return (StatusCode::BAD_REQUEST, Json("Invalid robot id".to_string())),
+    };
+    let round = *coordinator.round.lock().await;
+    let mut ledger = coordinator.ledger.lock().await;
+    let result = match report.state {
+        TaskState::InProgress => ledger.start(robot_id, round),
+        TaskState::Done => ledger.complete(robot_id, true, round),
+        TaskState::Failed => ledger.complete(robot_id, false, round),
+        _ => return (StatusCode::BAD_REQUEST, Json("Invalid state".to_string())),
+    };
+    match result {
+        Ok(id) => {
+            println!("Task {} of robot {}: {:?}", id, report.robot_id, report.state);
+            (StatusCode::OK, Json("OK".to_string()))
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, Json(e.to_string())),
+    }
+}
+
+// An integer field of a manual task, 0 if empty, None if it is not an integer
+fn parse_or_zero(input: &str) -> Option<Fr> {
+    if input.trim().is_empty() {
+        Some(Fr::from(0))
+    } else {
+        input.trim().parse::<u64>().ok().map(Fr::from)
+    }
+}
+
+// Comma separated task ids, None if one of them is invalid or there are too many
+fn parse_prerequisites(input: &str) -> Option<[Option<Fr>; MAX_PREREQUISITES]> {
+    let mut prerequisites = [None; MAX_PREREQUISITES];
+    let ids: Vec<&str> = input.split(',').map(|id| id.trim()).filter(|id| !id.is_empty()).collect();
+    if ids.len() > MAX_PREREQUISITES {
+        return None;
+    }
+    for (slot, id) in ids.iter().enumerate() {
+        prerequisites[slot] = Some(Fr::from(id.parse::<u64>().ok()?));
+    }
+    Some(prerequisites)
+}
+
+// Inputs of the circuit of a sub-round, checked against the signals of the rollup (with the road map
+// and its tree)
+fn circuit_inputs(
+    robots: &Vec<Robot>,
+    tasks: &Vec<Task>,
+    root: Fr,
+    task_root: Fr,
+    round: Fr,
+    parameters: &SkateParameters,
+    task_state: &TaskStateWitness,
+    road_map: Option<(&DistanceTable, &(Fr, Vec<Vec<Fr>>))>,
+) -> Result<RollupInputs, SkateError> {
+    let mut inputs = build_rollup_inputs(robots, tasks, root, task_root)
+        .with_round(tasks, round, parameters, task_state)?;
+    let mut depth = None;
+    if let Some((table, tree)) = road_map {
+        let road_map = road_map_witness(table, tree, robots, tasks)?;
+        depth = Some(road_map.depth);
+        inputs = inputs.with_road_map(&road_map);
+    }
+    inputs.check(robots.len(), tasks.len(), depth)?;
+    Ok(inputs)
+}
+
+// Charging station of a robot, the origin without any station
+fn closest_station(stations: &Vec<Position>, position: Position) -> Position {
+    let grid = position.grid();
+    stations
+        .iter()
+        .copied()
+        .min_by_key(|station| {
+            let station = station.grid();
+            (0..3)
+                .map(|i| (station[i] as i64 - grid[i] as i64).pow(2) as u64)
+                .sum::<u64>()
+        })
+        .unwrap_or_default()
+}
@@ -1,25 +1,29 @@
 // ROBOTS (or taxi or any agent)
 
-use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
-use ark_circom::CircomReduction;
-use ark_crypto_primitives::snark::SNARK;
-use ark_ff::{Field, PrimeField};
-use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, VerifyingKey};
-use ark_std::Zero;
-use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use ark_bn254::{Bn254, Fr};
+use ark_ff::PrimeField;
+use ark_groth16::{prepare_verifying_key, PreparedVerifyingKey};
+use ark_std::rand::thread_rng;
+use axum::{extract::State, http::StatusCode, routing::{get, post}, Json, Router};
 use serde::Deserialize;
-use serde_json::from_str;
-use std::fs::File;
-use std::io::Read;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 //use rosrust::Message;
 use tokio;
-use tokio::sync::{Mutex, MutexGuard};
-use SKATE::hashes::verify_robot_in_tree;
-use SKATE::Skate::{Robot, Task};
-
-type GrothBn = Groth16<Bn254, CircomReduction>;
+use tokio::sync::Mutex;
+use SKATE::aggregation::{verify_aggregate, AggregateProof, AggregationKey, AggregationSrs};
+use SKATE::error::SkateError;
+use SKATE::hashes::{replace_task_state, task_state_root, verify_robot_in_tree, TASK_STATE_DEPTH};
+use SKATE::lifecycle::TaskState;
+use SKATE::plonk::{self, Srs};
+use SKATE::position::{Frame, Position};
+use SKATE::proof_system::{Backend, Groth16Backend, ProofSystem};
+use SKATE::prover::circuit_matrices;
+use SKATE::registry::{Artifact, CircuitKey, CircuitRegistry};
+use SKATE::snarkjs::UpdateProof;
+use SKATE::verifying_key::read_verifying_key;
+use SKATE::Skate::{create_robot_with_battery, Robot, IDLE_TASK_ID};
 
 #[derive(Debug, Deserialize)]
 struct ServerConfig {
@@ -30,7 +34,51 @@ struct ServerConfig {
     x: String,
     y: String,
     z: String,
-    scale: f64, //max in cm
+    capabilities: String, // Capability bitmask of the robot
+    capacity: Option<String>, // Battery capacity, in units of the metric of the coordinator (none for no limit)
+    station: Option<[u64; 3]>, // Charging station (grid position), the closest one to the robot in the coordinator list
+    road_map_root: Option<String>, // Root of the distance table when the coordinator uses a road map
+    task_state_root: Option<String>, // Task-state root of the last round accepted, the empty tree before the first
+    #[serde(default)]
+    fairness_penalty: u16, // Fairness rule expected from the coordinator (fair mode), checked by the proofs
+    #[serde(default)]
+    workload_cap: u32,
+    frame: Option<Frame>, // Conversion of the grid positions to the meters published on ROS
+    scale: Option<f64>, // Former setting of the conversion, replaced by the frame (see Frame::from_scale)
+    coordinator: Option<String>, // Address of the coordinator receiving the task reports
+    #[serde(default = "default_registry")]
+    registry: String, // Circuit registry, only its verifying keys are used
+    #[serde(default = "default_backends")]
+    backends: Vec<Backend>, // Proof systems accepted from the coordinator, see SKATE::proof_system
+    srs: Option<String>, // Universal reference string of the PLONK keys, required to accept PLONK
+    aggregation_srs: Option<String>, // Reference string of the aggregated proofs, required by /aggregate
+}
+
+fn default_registry() -> String {
+    "circuits.toml".to_string()
+}
+
+fn default_backends() -> Vec<Backend> {
+    vec![Backend::Groth16]
+}
+
+// The configurations written before the frame only have a scale, still accepted with the conversion it
+// meant
+fn frame(configuration: &ServerConfig) -> Result<Frame, String> {
+    match (configuration.frame, configuration.scale) {
+        (Some(frame), None) => Ok(frame),
+        (None, Some(scale)) => {
+            let frame = Frame::from_scale(scale)?;
+            eprintln!(
+                "`scale = {}` is deprecated, use a [frame] with origin = [-2.0, -2.0, -2.0] and resolution = {}",
+                scale,
+                frame.resolution()
+            );
+            Ok(frame)
+        }
+        (Some(_), Some(_)) => Err("`scale` and [frame] are exclusive, keep only the frame".to_string()),
+        (None, None) => Err("missing [frame]".to_string()),
+    }
 }
 
 #[tokio::main]
@@ -57,41 +105,92 @@ async fn main() {
         }
     };
 
-    assert!(configuration.x.parse::<Fr>().unwrap() < Fr::from(10000));
-    assert!(configuration.y.parse::<Fr>().unwrap() < Fr::from(10000));
-    assert!(configuration.z.parse::<Fr>().unwrap() < Fr::from(10000));
-
     tracing_subscriber::fmt::init();
 
-    let verifier_key_6_3 = read_verifying_key("verification_key_6_3.json".to_owned());
-    let verifier_key_3_3 = read_verifying_key("verification_key_3_3.json".to_owned());
+    // The circuits of the road map have their own keys, only the circuits of the mode of the coordinator
+    // are accepted
+    let keys = match load_keys(&configuration) {
+        Ok(keys) => keys,
+        Err(e) => {
+            eprintln!("Invalid circuit registry : {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Only the verifier part of the reference string is kept
+    let aggregation = match &configuration.aggregation_srs {
+        Some(path) => match AggregationSrs::read(&std::env::current_dir().unwrap().join(path))
+            .and_then(|srs| srs.check(&mut thread_rng()).map(|_| srs.key()))
+        {
+            Ok(key) => Some(key),
+            Err(e) => {
+                eprintln!("Invalid aggregation reference string : {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let frame = match frame(&configuration) {
+        Ok(frame) => frame,
+        Err(e) => {
+            eprintln!("Invalid frame : {}", e);
+            std::process::exit(1);
+        }
+    };
 
     rosrust::init("talker");
     let chatter_pub = rosrust::publish("replace_by_corect_topic", 100).unwrap();
 
+    let station = configuration.station.map_or(Position::default(), |station| {
+        Position::new(station[0], station[1], station[2]).expect("Charging station out of the grid")
+    });
+    let robot = match create_robot_with_battery(
+        configuration.robot_id.parse::<Fr>().unwrap(),
+        configuration.capabilities.parse::<Fr>().unwrap(),
+        [Fr::from(IDLE_TASK_ID); 2],
+        [
+            Position::default(),
+            Position::from_coordinates([
+                configuration.x.parse::<Fr>().unwrap(),
+                configuration.y.parse::<Fr>().unwrap(),
+                configuration.z.parse::<Fr>().unwrap(),
+            ])
+            .expect("Robot position out of the grid"),
+        ],
+        configuration
+            .capacity
+            .as_ref()
+            .map_or(Fr::from(0), |capacity| capacity.parse::<Fr>().unwrap()),
+        station,
+    ) {
+        Ok(robot) => robot,
+        Err(e) => {
+            eprintln!("Invalid robot : {}", e);
+            std::process::exit(1);
+        }
+    };
+
     let state = AppState {
-        robot: Mutex::new(Robot {
-            robot_id: configuration.robot_id.parse::<Fr>().unwrap(),
-            list_tasks: [
-                Task {
-                    task_id: Fr::from(0),
-                    x: Fr::from(0),
-                    y: Fr::from(0),
-                    z: Fr::from(0),
-                },
-                Task {
-                    task_id: Fr::from(1),
-                    x: configuration.x.parse::<Fr>().unwrap(),
-                    y: configuration.y.parse::<Fr>().unwrap(),
-                    z: configuration.z.parse::<Fr>().unwrap(),
-                },
-            ],
-        }),
+        robot: Mutex::new(robot),
         root: Mutex::new(configuration.robot_root.parse::<Fr>().unwrap()),
-        key_6_3: Mutex::new(GrothBn::process_vk(&verifier_key_6_3).unwrap()),
-        key_3_3: Mutex::new(GrothBn::process_vk(&verifier_key_3_3).unwrap()),
+        task_state_root: Mutex::new(match &configuration.task_state_root {
+            Some(root) => root.parse::<Fr>().unwrap(),
+            None => task_state_root(&vec![]).unwrap().0,
+        }),
+        round: Mutex::new(Fr::from(0)),
+        keys,
+        aggregation,
+        backends: configuration.backends.clone(),
+        road_map_root: configuration
+            .road_map_root
+            .as_ref()
+            .map(|root| root.parse::<Fr>().unwrap()),
+        fairness_penalty: Fr::from(configuration.fairness_penalty),
+        workload_cap: Fr::from(configuration.workload_cap),
         number_of_robots: configuration.number_of_agent,
-        scale: configuration.scale,
+        frame,
+        coordinator: configuration.coordinator,
         publisher: chatter_pub
     };
 
@@ -100,6 +199,9 @@ async fn main() {
         // `GET /` goes to `root`
         // `POST /users` goes to `create_user`
         .route("/update", post(update))
+        .route("/report", post(report))
+        .route("/backends", get(backends))
+        .route("/aggregate", post(aggregate))
         .with_state(Arc::new(state));
 
     // run our app with hyper, listening globally on port 3000
@@ -113,193 +215,410 @@ async fn update(
     // as JSON into a `CreateUser` type
     State(state): State<Arc<AppState>>,
     Json(payload): Json<Update>,
-) -> (StatusCode, Json<String>) {
+) -> Result<(StatusCode, Json<String>), (StatusCode, Json<String>)> {
     // insert your application logic here
 
     let mut root = state.root.lock().await;
+    let mut task_state = state.task_state_root.lock().await;
     let mut robot = state.robot.lock().await;
-    let mut key: MutexGuard<PreparedVerifyingKey<Bn254>>;
+    let mut round = state.round.lock().await;
+    let circuit = match payload
+        .circuit
+        .parse::<CircuitKey>()
+        .ok()
+        .filter(|circuit| state.keys.contains(payload.backend, circuit))
+    {
+        Some(circuit) => circuit,
+        None => {
+            println!("Unknown circuit {} for {}", payload.circuit, payload.backend);
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json("Unknown circuit".to_string()),
+            ));
+        }
+    };
 
-    if payload.circuit == "6_3".to_owned() {
-        key = state.key_6_3.lock().await;
-    } else {
-        key = state.key_3_3.lock().await;
+    // Rounds never go backward, otherwise expired tasks could be assigned again
+    let payload_round = field("round", &payload.round)?;
+    if payload_round < *round {
+        println!("Outdated round");
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json("Round is outdated".to_string()),
+        ));
     }
 
-    let mut inputs = [Fr::from(0); 3];
-    inputs[0] = payload.robot_root.parse::<Fr>().unwrap();
-    inputs[1] = *root;
-    inputs[2] = payload.task_root.parse::<Fr>().unwrap();
-
-    let mut proof = Proof::default();
-    proof.a = to_g1(vec![payload.proof[0].clone(), payload.proof[1].clone()]);
-    proof.b = to_g2(vec![
-        vec![payload.proof[2].clone(), payload.proof[3].clone()],
-        vec![payload.proof[4].clone(), payload.proof[5].clone()],
-    ]);
-    proof.c = to_g1(vec![payload.proof[6].clone(), payload.proof[7].clone()]);
+    // The task-state root of the proof continues from the one of the previous round, through the leaves the
+    // coordinator changed since then (new tasks, reports, retired tasks)
+    let payload_task_state_root = field("task_state_root", &payload.task_state_root)?;
+    let mut continued_root = *task_state;
+    for change in &payload.task_state_changes {
+        let old = (field("task_state_changes", &change.old[0])?, field("task_state_changes", &change.old[1])?);
+        let new = (field("task_state_changes", &change.new[0])?, field("task_state_changes", &change.new[1])?);
+        let path = change
+            .path
+            .iter()
+            .map(|hash| field("task_state_changes", hash))
+            .collect::<Result<Vec<Fr>, _>>()?;
+        if path.len() != TASK_STATE_DEPTH {
+            return Err(malformed(SkateError::InvalidLength { expected: TASK_STATE_DEPTH, found: path.len() }));
+        }
+        continued_root = match replace_task_state(continued_root, change.slot, old, new, &path) {
+            Ok(Some(root)) => root,
+            Ok(None) => {
+                println!("Task-state change of slot {} not under the root", change.slot);
+                return Err((StatusCode::BAD_REQUEST, Json("Task-state change is incorrect".to_string())));
+            }
+            Err(e) => return Err(malformed(e)),
+        };
+    }
+    if continued_root != payload_task_state_root {
+        println!("Task-state root does not continue the previous round");
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json("Task-state root does not continue the previous round".to_string()),
+        ));
+    }
+    let new_task_state_root = field("new_task_state_root", &payload.new_task_state_root)?;
+
+    let new_robot_root = field("robot_root", &payload.robot_root)?;
+    let mut inputs = vec![
+        new_robot_root,
+        *root,
+        field("task_root", &payload.task_root)?,
+        payload_round,
+        field("seed", &payload.seed)?,
+        payload_task_state_root,
+        state.fairness_penalty,
+        state.workload_cap,
+        new_task_state_root,
+    ];
+    if let Some(road_map_root) = state.road_map_root {
+        inputs.push(road_map_root);
+    }
 
-    let correct_proof = GrothBn::verify_with_processed_vk(&key, &inputs, &proof).unwrap();
+    let proof = match payload.proof.strings() {
+        Ok(proof) => proof,
+        Err(e) => return Err(malformed(e)),
+    };
+    let correct_proof = match state.keys.verify(payload.backend, &circuit, &inputs, &proof) {
+        Ok(correct_proof) => correct_proof,
+        Err(e) => return Err(malformed(e)),
+    };
 
+    let depth = state.number_of_robots.ilog2() as usize + 1;
+    if payload.merkle_proof.len() < depth {
+        return Err(malformed(SkateError::InvalidLength { expected: depth, found: payload.merkle_proof.len() }));
+    }
     let mut merkle = vec![];
-    for i in 0..state.number_of_robots.ilog2() as usize + 1 {
-        merkle.push(payload.merkle_proof[i].parse::<Fr>().unwrap());
+    for i in 0..depth {
+        merkle.push(field("merkle_proof", &payload.merkle_proof[i])?);
     }
+    let task_id = field("task_id", &payload.task_id)?;
     let mut new_robot = robot.clone();
     new_robot.list_tasks[0] = robot.list_tasks[1].clone();
-    new_robot.list_tasks[1].task_id = payload.task_id.parse::<Fr>().unwrap();
-    new_robot.list_tasks[1].x = payload.x.parse::<Fr>().unwrap();
-    new_robot.list_tasks[1].y = payload.y.parse::<Fr>().unwrap();
-    new_robot.list_tasks[1].z = payload.z.parse::<Fr>().unwrap();
-    let in_tree = verify_robot_in_tree(
+    new_robot.list_tasks[1].task_id = task_id;
+    new_robot.list_tasks[1].position = payload.position;
+    new_robot.battery = field("battery", &payload.battery)?;
+    new_robot.workload = field("workload", &payload.workload)?;
+    let in_tree = match verify_robot_in_tree(
         new_robot,
-        payload.robot_root.parse::<Fr>().unwrap(),
+        new_robot_root,
         merkle,
-    );
+    ) {
+        Ok(in_tree) => in_tree,
+        Err(e) => return Err(malformed(e)),
+    };
 
     if in_tree && correct_proof {
         robot.list_tasks[0] = robot.list_tasks[1].clone();
-        robot.list_tasks[1].task_id = payload.task_id.parse::<Fr>().unwrap();
-        robot.list_tasks[1].x = payload.x.parse::<Fr>().unwrap();
-        robot.list_tasks[1].y = payload.y.parse::<Fr>().unwrap();
-        robot.list_tasks[1].z = payload.z.parse::<Fr>().unwrap();
-        *root = payload.robot_root.parse::<Fr>().unwrap();
-
+        robot.list_tasks[1].task_id = task_id;
+        robot.list_tasks[1].position = payload.position;
+        robot.battery = new_robot.battery;
+        robot.workload = new_robot.workload;
+        *root = new_robot_root;
+        *task_state = new_task_state_root;
+        *round = payload_round;
+
+        let meters = state.frame.to_meters(&robot.list_tasks[1].position);
         println!(
             "Preuve ok. Assigned Task:\tid: {}\tx: {}\ty: {}\tz: {}",
             robot.list_tasks[1].task_id.into_bigint().to_string(),
-            (robot.list_tasks[1].x.into_bigint().to_string().parse::<f64>().unwrap() / 10000f64) * state.scale - 2f64,
-            (robot.list_tasks[1].y.into_bigint().to_string().parse::<f64>().unwrap() / 10000f64) * state.scale - 2f64,
-            (robot.list_tasks[1].z.into_bigint().to_string().parse::<f64>().unwrap() / 10000f64) * state.scale - 2f64
+            meters[0],
+            meters[1],
+            meters[2]
         );
 
         //ROS publish
 
         let mut msg = rosrust_msg::geometry_msgs::Point::default();
-        msg.x = (robot.list_tasks[1].x.into_bigint().to_string().parse::<f64>().unwrap() / 10000f64) * 4f64 - 2f64;
-        msg.y = (robot.list_tasks[1].y.into_bigint().to_string().parse::<f64>().unwrap() / 10000f64) * 4f64 - 2f64;
-        msg.z = (robot.list_tasks[1].z.into_bigint().to_string().parse::<f64>().unwrap() / 10000f64) * 4f64 - 2f64;
+        msg.x = meters[0];
+        msg.y = meters[1];
+        msg.z = meters[2];
 
         // Send string message to topic via publisher
         state.publisher.send(msg).unwrap();
 
+        send_report(&state, robot.robot_id, TaskState::InProgress).await;
 
 
 
-        (StatusCode::OK, Json("OK".to_string()))
+
+        Ok((StatusCode::OK, Json("OK".to_string())))
     } else {
         if in_tree {
             println!("Incorrect ZkProof");
-            (
+            Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json("Zk Proof is incorrect".to_string()),
-            )
+            ))
         } else {
             if correct_proof {
                 println!("Incorrect Merkle Proof");
-                (
+                Err((
                     StatusCode::INTERNAL_SERVER_ERROR,
                     Json("Merkle Proof is incorrect".to_string()),
-                )
+                ))
             } else {
                 println!("Incorrect ZkProof and Merkle Proof");
-                (
+                Err((
                     StatusCode::INTERNAL_SERVER_ERROR,
                     Json("Zk Proof and Merkle Proof are incorrect".to_string()),
-                )
+                ))
             }
         }
     }
 }
 
+// The backends of the proofs accepted, queried by the coordinator before the first round
+async fn backends(State(state): State<Arc<AppState>>) -> Json<Vec<Backend>> {
+    Json(state.backends.clone())
+}
+
+// Proofs of a circuit aggregated into one (see SKATE::aggregation), for instance the rounds of several
+// fleets. The state of the robot is left untouched
+#[derive(Deserialize)]
+struct Aggregate {
+    circuit: String, // see registry::CircuitKey
+    public_inputs: Vec<Vec<String>>, // Of each proof, in the order of the aggregation
+    proof: String, // AggregateProof::to_bytes, in hex
+}
+
+async fn aggregate(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<Aggregate>,
+) -> (StatusCode, Json<String>) {
+    let Some(key) = &state.aggregation else {
+        return (StatusCode::BAD_REQUEST, Json("No aggregation reference string".to_string()));
+    };
+    match state.keys.verify_aggregate(key, &payload) {
+        Ok(true) => (StatusCode::OK, Json(format!("{} proofs verified", payload.public_inputs.len()))),
+        Ok(false) => (StatusCode::INTERNAL_SERVER_ERROR, Json("Aggregate proof is incorrect".to_string())),
+        Err(e) => {
+            println!("Malformed aggregate: {}", e);
+            (StatusCode::BAD_REQUEST, Json(e.to_string()))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Report {
+    state: TaskState, // done or failed
+}
+
+// Called once the robot finished (or gave up) its current task, forwarded to the coordinator
+async fn report(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<Report>,
+) -> (StatusCode, Json<String>) {
+    if payload.state != TaskState::Done && payload.state != TaskState::Failed {
+        return (StatusCode::BAD_REQUEST, Json("Invalid state".to_string()));
+    }
+    let robot_id = state.robot.lock().await.robot_id;
+    if send_report(&state, robot_id, payload.state).await {
+        (StatusCode::OK, Json("OK".to_string()))
+    } else {
+        (StatusCode::INTERNAL_SERVER_ERROR, Json("Coordinator unreachable".to_string()))
+    }
+}
+
+async fn send_report(state: &AppState, robot_id: Fr, task_state: TaskState) -> bool {
+    let Some(coordinator) = &state.coordinator else {
+        return false;
+    };
+    let body = serde_json::json!({
+        "robot_id": robot_id.into_bigint().to_string(),
+        "state": task_state,
+    });
+    let response = reqwest::Client::new()
+        .post(format!("http://{}/status", coordinator))
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await;
+    match response {
+        Ok(response) => response.status().is_success(),
+        Err(e) => {
+            println!("Cannot reach the coordinator: {}", e);
+            false
+        }
+    }
+}
+
 // the input to our `create_user` handler
 #[derive(Deserialize)]
 struct Update {
     robot_root: String,
     task_root: String,
-    circuit: String,
-    proof: Vec<String>,
+    circuit: String, // Circuit of the proof, see registry::CircuitKey
+    #[serde(default)]
+    backend: Backend, // Proof system of the proof, Groth16 from the coordinators predating PLONK
+    round: String,
+    seed: String,
+    task_state_root: String,
+    new_task_state_root: String, // Task-state root once the tasks of the round are assigned
+    proof: UpdateProof, // proof.json of snarkjs for Groth16
     task_id: String,
-    x: String,
-    y: String,
-    z: String,
+    position: Position, // Range-checked when deserialized
+    battery: String, // Battery once at the assigned task, committed in the new robot root
+    workload: String, // Tasks assigned so far, committed in the new robot root
     merkle_proof: Vec<String>,
+    #[serde(default)]
+    task_state_changes: Vec<TaskStateChange>, // Leaves changed since the previous round (lifecycle::task_state_changes)
 }
 
-fn read_verifying_key(directory: String) -> VerifyingKey<Bn254> {
-    let mut file = File::open(
-        std::env::current_dir()
-            .unwrap()
-            .join(PathBuf::from(directory)),
-    )
-    .expect("Impossible d'ouvrir le fichier");
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .expect("Impossible de lire le fichier");
-
-    let mut key = VerifyingKey::default();
-    let json: VerifierFile = from_str(&contents).expect("Impossible de désérialiser le JSON");
-    key.alpha_g1 = to_g1(json.vk_alpha_1);
-    key.beta_g2 = to_g2(json.vk_beta_2);
-    key.delta_g2 = to_g2(json.vk_delta_2);
-    key.gamma_g2 = to_g2(json.vk_gamma_2);
-    key.gamma_abc_g1 = to_g1_vec(json.IC, 4);
-    key
+// Leaf replaced at a slot of the task-state tree, (task id, state) before and after
+#[derive(Deserialize)]
+struct TaskStateChange {
+    slot: usize,
+    old: [String; 2],
+    new: [String; 2],
+    path: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
-struct VerifierFile {
-    protocol: String,
-    curve: String,
-    nPublic: u32,
-    vk_alpha_1: Vec<String>,
-    vk_beta_2: Vec<Vec<String>>,
-    vk_gamma_2: Vec<Vec<String>>,
-    vk_delta_2: Vec<Vec<String>>,
-    vk_alphabeta_12: Vec<Vec<Vec<String>>>,
-    IC: Vec<Vec<String>>,
+// A field element of a request, a bad request if malformed
+fn field(name: &str, value: &str) -> Result<Fr, (StatusCode, Json<String>)> {
+    value.parse::<Fr>().map_err(|_| {
+        println!("Malformed {}: {}", name, value);
+        (StatusCode::BAD_REQUEST, Json(format!("Malformed {}", name)))
+    })
 }
 
-fn to_g1(string: Vec<String>) -> G1Affine {
-    let x = string[0].parse::<Fq>().unwrap();
-    let y = string[1].parse::<Fq>().unwrap();
-    let infinity = x.is_zero() && y.is_zero();
-    if infinity {
-        G1Affine::identity()
-    } else {
-        G1Affine::new_unchecked(x, y)
-    }
+fn malformed(e: SkateError) -> (StatusCode, Json<String>) {
+    println!("Malformed update: {}", e);
+    (StatusCode::BAD_REQUEST, Json(e.to_string()))
 }
 
-fn to_g2(string: Vec<Vec<String>>) -> G2Affine {
-    let f1 = Fq2::from_base_prime_field_elems(&[
-        string[0][0].parse::<Fq>().unwrap(),
-        string[0][1].parse::<Fq>().unwrap(),
-    ])
-    .unwrap();
-    let f2 = Fq2::from_base_prime_field_elems(&[
-        string[1][0].parse::<Fq>().unwrap(),
-        string[1][1].parse::<Fq>().unwrap(),
-    ])
-    .unwrap();
-    let infinity = f1.is_zero() && f2.is_zero();
-    if infinity {
-        G2Affine::identity()
-    } else {
-        G2Affine::new_unchecked(f1, f2)
+// Verifying keys of the accepted backends, by circuit as sent with the proofs
+#[derive(Default)]
+struct Keys {
+    groth16: HashMap<CircuitKey, PreparedVerifyingKey<Bn254>>,
+    plonk: HashMap<CircuitKey, plonk::VerifyingKey>,
+}
+
+impl Keys {
+    fn contains(&self, backend: Backend, circuit: &CircuitKey) -> bool {
+        match backend {
+            Backend::Groth16 => self.groth16.contains_key(circuit),
+            Backend::Plonk => self.plonk.contains_key(circuit),
+        }
+    }
+
+    fn verify(&self, backend: Backend, circuit: &CircuitKey, inputs: &[Fr], proof: &[String]) -> Result<bool, SkateError> {
+        let unknown = || SkateError::Artifact(format!("no {} key for circuit {}", backend, circuit));
+        match backend {
+            Backend::Groth16 => {
+                let key = self.groth16.get(circuit).ok_or_else(unknown)?;
+                Groth16Backend.verify(key, inputs, &Groth16Backend.decode(proof)?)
+            }
+            Backend::Plonk => {
+                let key = self.plonk.get(circuit).ok_or_else(unknown)?;
+                plonk::verify(key, inputs, &plonk::Proof::from_strings(proof)?)
+            }
+        }
+    }
+
+    fn verify_aggregate(&self, key: &AggregationKey, payload: &Aggregate) -> Result<bool, SkateError> {
+        let circuit = payload.circuit.parse::<CircuitKey>()?;
+        let vk = self
+            .groth16
+            .get(&circuit)
+            .ok_or_else(|| SkateError::Artifact(format!("no groth16 key for circuit {}", circuit)))?;
+        let inputs = payload
+            .public_inputs
+            .iter()
+            .map(|inputs| {
+                inputs
+                    .iter()
+                    .map(|input| input.parse::<Fr>().map_err(|_| SkateError::Proof(format!("invalid input {}", input))))
+                    .collect::<Result<Vec<Fr>, SkateError>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let bytes = hex::decode(&payload.proof).map_err(|e| SkateError::Proof(e.to_string()))?;
+        verify_aggregate(key, vk, &inputs, &AggregateProof::from_bytes(&bytes)?)
     }
 }
 
-fn to_g1_vec(string: Vec<Vec<String>>, n_vars: usize) -> Vec<G1Affine> {
-    (0..n_vars).map(|i| to_g1(string[i].clone())).collect()
+// Keys of the registered circuits with the road map of the coordinator or without. The PLONK ones are
+// computed from the native circuit and the reference string, there is no key file to trust
+fn load_keys(configuration: &ServerConfig) -> Result<Keys, SkateError> {
+    let directory = std::env::current_dir().unwrap();
+    let registry = CircuitRegistry::load(&directory.join(&configuration.registry))?;
+    let accepts = |backend| configuration.backends.contains(&backend);
+    if accepts(Backend::Groth16) {
+        registry.check(&[Artifact::VerifyingKey])?;
+    } else {
+        registry.check(&[])?;
+    }
+    let srs = match (accepts(Backend::Plonk), &configuration.srs) {
+        (true, Some(path)) => {
+            let srs = Srs::read(&directory.join(path))?;
+            srs.check(&mut thread_rng())?;
+            Some(srs)
+        }
+        (true, None) => {
+            return Err(SkateError::Artifact("the PLONK backend needs a reference string".to_string()))
+        }
+        (false, _) => None,
+    };
+    let mut keys = Keys::default();
+    for circuit in registry.circuits() {
+        if circuit.road_map != configuration.road_map_root.is_some()
+            || circuit.robots != configuration.number_of_agent
+        {
+            continue;
+        }
+        if accepts(Backend::Groth16) {
+            let key = read_verifying_key(circuit.path(Artifact::VerifyingKey)?)?;
+            keys.groth16.insert(circuit.key(), prepare_verifying_key(&key));
+        }
+        if let Some(srs) = &srs {
+            keys.plonk.insert(circuit.key(), plonk::preprocess(srs, &circuit_matrices(circuit)?)?.vk);
+        }
+    }
+    if keys.groth16.is_empty() && keys.plonk.is_empty() {
+        return Err(SkateError::Artifact(format!(
+            "no circuit for {} robots {} the road map",
+            configuration.number_of_agent,
+            if configuration.road_map_root.is_some() { "with" } else { "without" }
+        )));
+    }
+    Ok(keys)
 }
 
 struct AppState {
     robot: Mutex<Robot>,
     root: Mutex<Fr>,
-    key_6_3: Mutex<PreparedVerifyingKey<Bn254>>,
-    key_3_3: Mutex<PreparedVerifyingKey<Bn254>>,
+    task_state_root: Mutex<Fr>, // Anchored like the robot root, moved by the accepted proofs only
+    round: Mutex<Fr>,
+    road_map_root: Option<Fr>,
+    fairness_penalty: Fr,
+    workload_cap: Fr,
+    keys: Keys,
+    aggregation: Option<AggregationKey>,
+    backends: Vec<Backend>,
     number_of_robots: usize,
-    scale: f64,
+    frame: Frame,
+    coordinator: Option<String>,
     publisher: rosrust::Publisher<rosrust_msg::geometry_msgs::Point>
 }
@@ -0,0 +1,197 @@
+// KEY MANAGEMENT
+
+use ark_bn254::{Bn254, Fr};
+use ark_circom::read_zkey;
+use ark_ff::PrimeField;
+use ark_groth16::{prepare_verifying_key, ProvingKey};
+use ark_relations::r1cs::ConstraintMatrices;
+use ark_std::rand::thread_rng;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use SKATE::aggregation::{aggregate, AggregationSrs};
+use SKATE::error::SkateError;
+#[cfg(feature = "ethereum")]
+use SKATE::ethereum::{contract_name, rollup_contract, solidity_verifier};
+use SKATE::plonk::Srs;
+use SKATE::proof_system::{Groth16Backend, ProofSystem};
+use SKATE::registry::{Artifact, CircuitRegistry};
+use SKATE::rollup::RollupCircuit;
+use SKATE::setup::{
+    check_keys, contribute, matrix_parameters, outlined_matrices, verify_contributions, Contribution,
+};
+use SKATE::snarkjs::{read_proof, read_public_inputs};
+use SKATE::verifying_key::{read_verifying_key, write_verifying_key};
+use SKATE::zkey::write_zkey;
+
+const USAGE: &str = "Usage:
+    setup native <circuits.toml>
+        Groth16 setup of the circuits of a registry, writes their zkey and verifying key
+    setup contribute <in.zkey> <out.zkey> <transcript.json> <name>
+        Re-randomizes the delta of a key, the contribution is appended to the transcript. Only delta is
+        contributed to (phase 2): tau, alpha and beta stay those sampled by native
+    setup verify <initial.zkey> <final.zkey> <transcript.json>
+        Checks that the final key follows from the initial one through the transcript
+    setup export <circuit.zkey> <verification_key.json>
+        Writes the verifying key of a proving key
+    setup check <circuit.zkey> <verification_key.json>
+        Checks that a verifying key matches a proving key
+    setup srs <gates> <srs.bin>
+        Universal reference string of the PLONK backend, for the circuits of up to <gates> gates
+    setup aggregation-srs <proofs> <aggregation_srs.bin>
+        Reference string of the aggregation of up to <proofs> Groth16 proofs, from the RNG of this machine:
+        for tests only, a deployment loads the string of a multi-party ceremony (see the README)
+    setup aggregate <aggregation_srs.bin> <verification_key.json> <circuit> <request.json> <proof.json> <public.json>...
+        Aggregates pairs of proof and public inputs of a circuit into the body of POST /aggregate of the servers
+    setup check-proof <verification_key.json> <proof.json> <public.json>
+        Checks a Groth16 proof in the snarkjs format, as snarkjs groth16 verify
+    setup solidity <circuits.toml> <directory>
+        Solidity verifier and rollup contracts of the registered circuits (ethereum feature)";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["native", registry] => native(Path::new(registry)),
+        ["contribute", input, output, transcript, name] => {
+            contribute_to(Path::new(input), Path::new(output), Path::new(transcript), name)
+        }
+        ["verify", initial, last, transcript] => {
+            verify(Path::new(initial), Path::new(last), Path::new(transcript))
+        }
+        ["export", zkey, vk] => read_keys(Path::new(zkey))
+            .and_then(|(pk, _)| write_verifying_key(Path::new(vk), &pk.vk)),
+        ["check", zkey, vk] => read_keys(Path::new(zkey))
+            .and_then(|(pk, _)| check_keys(&pk, &read_verifying_key(Path::new(vk))?)),
+        ["srs", gates, srs] => match gates.parse::<usize>() {
+            Ok(gates) => Srs::setup(gates, &mut thread_rng()).write(Path::new(srs)),
+            Err(e) => Err(SkateError::Artifact(format!("{}: {}", gates, e))),
+        },
+        ["aggregation-srs", proofs, srs] => match proofs.parse::<usize>() {
+            Ok(proofs) => AggregationSrs::setup(proofs, &mut thread_rng()).write(Path::new(srs)),
+            Err(e) => Err(SkateError::Artifact(format!("{}: {}", proofs, e))),
+        },
+        ["aggregate", srs, vk, circuit, request, files @ ..] if !files.is_empty() && files.len() % 2 == 0 => {
+            aggregate_to(Path::new(srs), Path::new(vk), circuit, Path::new(request), files)
+        }
+        ["check-proof", vk, proof, public] => check_proof(Path::new(vk), Path::new(proof), Path::new(public)),
+        #[cfg(feature = "ethereum")]
+        ["solidity", registry, directory] => solidity(Path::new(registry), Path::new(directory)),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    };
+    match result {
+        Ok(()) => println!("Done"),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn read_keys(path: &Path) -> Result<(ProvingKey<Bn254>, ConstraintMatrices<Fr>), SkateError> {
+    let file = File::open(path).map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))?;
+    read_zkey(&mut BufReader::new(file)).map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))
+}
+
+fn check_proof(vk: &Path, proof: &Path, public: &Path) -> Result<(), SkateError> {
+    let vk = prepare_verifying_key(&read_verifying_key(vk)?);
+    if Groth16Backend.verify(&vk, &read_public_inputs(public)?, &read_proof(proof)?)? {
+        Ok(())
+    } else {
+        Err(SkateError::Proof("the proof is incorrect".to_string()))
+    }
+}
+
+// The proofs are checked one by one first, an aggregate of a false proof would only fail at the server
+fn aggregate_to(srs: &Path, vk: &Path, circuit: &str, request: &Path, files: &[&str]) -> Result<(), SkateError> {
+    let srs = AggregationSrs::read(srs)?;
+    srs.check(&mut thread_rng())?;
+    let vk = prepare_verifying_key(&read_verifying_key(vk)?);
+    let mut proofs = vec![];
+    for pair in files.chunks(2) {
+        let (proof, inputs) = (read_proof(Path::new(pair[0]))?, read_public_inputs(Path::new(pair[1]))?);
+        if !Groth16Backend.verify(&vk, &inputs, &proof)? {
+            return Err(SkateError::Proof(format!("{}: the proof is incorrect", pair[0])));
+        }
+        proofs.push((inputs, proof));
+    }
+    let aggregate = aggregate(&srs, &vk, &proofs)?;
+    let body = serde_json::json!({
+        "circuit": circuit,
+        "public_inputs": proofs
+            .iter()
+            .map(|(inputs, _)| inputs.iter().map(|input| input.into_bigint().to_string()).collect::<Vec<_>>())
+            .collect::<Vec<_>>(),
+        "proof": hex::encode(aggregate.to_bytes()),
+    });
+    std::fs::write(request, body.to_string()).map_err(|e| SkateError::Artifact(format!("{}: {}", request.display(), e)))
+}
+
+#[cfg(feature = "ethereum")]
+fn solidity(registry: &Path, directory: &Path) -> Result<(), SkateError> {
+    let registry = CircuitRegistry::load(registry)?;
+    registry.check(&[Artifact::VerifyingKey])?;
+    let write = |name: &str, contents: String| {
+        let path = directory.join(format!("{}.sol", name));
+        std::fs::write(&path, contents).map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))
+    };
+    for circuit in registry.circuits() {
+        let vk = read_verifying_key(circuit.path(Artifact::VerifyingKey)?)?;
+        let verifier = contract_name("SkateVerifier", &circuit.key());
+        let rollup = contract_name("SkateRollup", &circuit.key());
+        write(&verifier, solidity_verifier(&verifier, &vk))?;
+        write(&rollup, rollup_contract(&rollup, &verifier, circuit.road_map))?;
+        println!("{}: {}, {}", circuit.key(), verifier, rollup);
+    }
+    Ok(())
+}
+
+fn read_transcript(path: &Path) -> Result<Vec<Contribution>, SkateError> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))?;
+    serde_json::from_str(&contents).map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))
+}
+
+fn native(registry: &Path) -> Result<(), SkateError> {
+    let registry = CircuitRegistry::load(registry)?;
+    registry.check(&[])?;
+    for circuit in registry.circuits() {
+        let rollup =
+            RollupCircuit::blank(circuit.robots, circuit.tasks, &circuit.parameters(), circuit.road_map_depth())?;
+        let matrices = outlined_matrices(rollup)?;
+        let pk = matrix_parameters(&matrices, &mut thread_rng())?;
+        println!(
+            "Setup of {}: {} constraints, {} public inputs",
+            circuit.key(),
+            matrices.num_constraints,
+            matrices.num_instance_variables - 1
+        );
+        write_zkey(circuit.path(Artifact::Zkey)?, &pk, &matrices)?;
+        write_verifying_key(circuit.path(Artifact::VerifyingKey)?, &pk.vk)?;
+    }
+    Ok(())
+}
+
+fn contribute_to(input: &Path, output: &Path, transcript: &Path, name: &str) -> Result<(), SkateError> {
+    let (mut pk, matrices) = read_keys(input)?;
+    let mut contributions = read_transcript(transcript)?;
+    contributions.push(contribute(&mut pk, name, &mut thread_rng()));
+    write_zkey(output, &pk, &matrices)?;
+    std::fs::write(transcript, serde_json::to_string_pretty(&contributions).unwrap())
+        .map_err(|e| SkateError::Artifact(format!("{}: {}", transcript.display(), e)))
+}
+
+fn verify(initial: &Path, last: &Path, transcript: &Path) -> Result<(), SkateError> {
+    let contributions = read_transcript(transcript)?;
+    verify_contributions(&read_keys(initial)?.0, &read_keys(last)?.0, &contributions, &mut thread_rng())?;
+    for contribution in &contributions {
+        println!("Contribution of {}", contribution.name);
+    }
+    Ok(())
+}
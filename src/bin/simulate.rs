@@ -0,0 +1,120 @@
+// FLEET SIMULATION
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use SKATE::error::SkateError;
+use SKATE::graph::{DistanceTable, RoadMap};
+use SKATE::prover::Prover;
+use SKATE::registry::{Artifact, CircuitRegistry};
+use SKATE::simulator::{ArrivalProcess, FleetRobot, SimulationConfig, SimulationReport, Simulator};
+use SKATE::Skate::{AssignmentMode, DistanceMetric, SkateParameters};
+
+const USAGE: &str = "Usage:
+    simulate <simulation.toml>
+        Runs the simulation of a fleet and prints the metrics of its rounds, see src/client/simulation.toml";
+
+/* A simulation file: the simulator::SimulationConfig in [simulation], the parameters of the rounds, the
+   fleet and the arrival process. With a registry, each round is proven with the circuit of its size
+   (proof mode). Relative paths are relative to the simulation file */
+#[derive(Debug, Deserialize)]
+struct SimulationFile {
+    simulation: SimulationConfig,
+    #[serde(default = "default_mode")]
+    mode: AssignmentMode,
+    #[serde(default = "default_metric")]
+    metric: DistanceMetric,
+    #[serde(default)]
+    fairness_penalty: u16,
+    #[serde(default)]
+    workload_cap: u32,
+    road_map: Option<String>, // Road map file, required by the graph metric
+    registry: Option<String>, // Circuit registry of the proof mode, see SKATE::registry
+    fleet: Vec<FleetRobot>,
+    arrivals: ArrivalProcess,
+}
+
+fn default_mode() -> AssignmentMode {
+    AssignmentMode::Distance
+}
+
+fn default_metric() -> DistanceMetric {
+    DistanceMetric::SquaredEuclidean
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [path] = args.as_slice() else {
+        eprintln!("{}", USAGE);
+        std::process::exit(1);
+    };
+    match simulate(Path::new(path)) {
+        Ok(report) => {
+            for round in &report.rounds {
+                println!("{}", round);
+            }
+            println!("{}", report.summary());
+            println!(
+                "{} tasks: {} completed, {} expired, mean completion time {}",
+                report.tasks.len(),
+                report.completed(),
+                report.expired(),
+                match report.mean_completion_time() {
+                    Some(time) => format!("{:.2}s", time),
+                    None => "n/a".to_string(),
+                }
+            );
+            if !report.proofs.is_empty() {
+                let verified = report.proofs.iter().filter(|verified| **verified).count();
+                println!("{}/{} proofs verified", verified, report.proofs.len());
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<String, SkateError> {
+    std::fs::read_to_string(path).map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))
+}
+
+fn simulate(path: &Path) -> Result<SimulationReport, SkateError> {
+    let file: SimulationFile =
+        toml::from_str(&read(path)?).map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))?;
+    let directory = path.parent().map(Path::to_path_buf).unwrap_or_else(PathBuf::new);
+
+    let road_map: Option<DistanceTable> = match &file.road_map {
+        Some(road_map) => {
+            let road_map_path = directory.join(road_map);
+            let road_map: RoadMap = toml::from_str(&read(&road_map_path)?)
+                .map_err(|e| SkateError::Artifact(format!("{}: {}", road_map_path.display(), e)))?;
+            Some(road_map.distance_table()?)
+        }
+        None => None,
+    };
+    let mut prover = match &file.registry {
+        Some(registry) => {
+            let registry = CircuitRegistry::load(&directory.join(registry))?;
+            registry.check(&[Artifact::Zkey])?;
+            Some(Prover::load(registry.circuits())?)
+        }
+        None => None,
+    };
+
+    let parameters = SkateParameters {
+        mode: file.mode,
+        metric: file.metric,
+        seed: 0, // derived from the robot root of each round
+        fairness_penalty: file.fairness_penalty,
+        workload_cap: file.workload_cap,
+    };
+    let mut simulator = Simulator::new(file.simulation, parameters, file.fleet, file.arrivals);
+    if let Some(table) = &road_map {
+        simulator = simulator.with_road_map(table);
+    }
+    if let Some(prover) = prover.as_mut() {
+        simulator = simulator.with_prover(prover);
+    }
+    simulator.run()
+}
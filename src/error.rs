@@ -0,0 +1,55 @@
+use ark_bn254::Fr;
+use thiserror::Error;
+
+// Errors of the Skate and hashes libraries, a malformed round must not crash the coordinator or an agent
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SkateError {
+    #[error("task id {0} does not fit on 64 bits")]
+    InvalidTaskId(Fr),
+    #[error("task ids must be strictly increasing, {0} is out of order")]
+    UnsortedTaskIds(Fr),
+    #[error("priority {0} is above the maximum priority")]
+    InvalidPriority(Fr),
+    #[error("deadline {0} does not fit on 32 bits")]
+    InvalidDeadline(Fr),
+    #[error("capabilities {0} do not fit on 16 bits")]
+    InvalidCapabilities(Fr),
+    #[error("team size {0} is above the maximum team size")]
+    InvalidTeamSize(Fr),
+    #[error("battery {0} does not fit on 32 bits or is too low for the move")]
+    InvalidBattery(Fr),
+    #[error("workload {0} does not fit on 32 bits")]
+    InvalidWorkload(Fr),
+    #[error("expected {expected} elements, found {found}")]
+    InvalidLength { expected: usize, found: usize },
+    #[error("{0} is not an index below {1}")]
+    InvalidIndex(Fr, usize),
+    #[error("no robot or no task to assign")]
+    Empty,
+    #[error("robot {0} has a battery, which needs an additive distance metric")]
+    NonAdditiveMetric(Fr),
+    #[error("the graph metric needs a distance table")]
+    MissingRoadMap,
+    #[error("invalid road map: {0}")]
+    InvalidRoadMap(String),
+    #[error("a position is not a node of the road map")]
+    NotOnRoadMap,
+    #[error("robot {0} holds no task")]
+    NoTask(Fr),
+    #[error("robot {0} already holds a task")]
+    Busy(Fr),
+    #[error("task {0} is not pending in the task-state tree")]
+    NotPending(Fr),
+    #[error("task {0} is not in the task-state tree")]
+    NotInTree(Fr),
+    #[error("task {0} takes the slot of another task in the task-state tree")]
+    SlotTaken(Fr),
+    #[error("the {0} slots of the task-state tree hold unfinished tasks or their prerequisites")]
+    LedgerFull(usize),
+    #[error("proof error: {0}")]
+    Proof(String),
+    #[error("circuit artifact error: {0}")]
+    Artifact(String),
+}
+
+pub type Result<T> = std::result::Result<T, SkateError>;
@@ -0,0 +1,451 @@
+use crate::error::{Result, SkateError};
+use crate::registry::CircuitKey;
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{Proof, VerifyingKey};
+use ethers_core::abi::{encode, short_signature, ParamType, Token};
+use ethers_core::types::{Bytes, U256};
+use std::fmt::Write;
+
+/* Settlement of the rounds on Ethereum. Each circuit gets a Groth16 verifier contract, its verifying key
+   in constants, checked with the pairing precompile, and a rollup contract that stores the robot root and
+   moves it to the new root of each round proven from it (see rollup_contract). The public inputs of a
+   round that the contract does not keep are passed along with the proof: task root, round, seed and
+   task-state roots before and after the round. The points are encoded as the calldata of snarkjs, the Fq2 coordinates of B with
+   their c1 first as the precompile expects them.
+   The contracts are only generated, compiling them needs solc which the crate does not depend on. The
+   tests run them in revm */
+
+// Name of the contracts of a circuit, the version reduced to the characters of an identifier
+pub fn contract_name(prefix: &str, circuit: &CircuitKey) -> String {
+    let version: String = circuit
+        .version
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}_{}_{}_{}", prefix, version, circuit.tasks, circuit.robots)
+}
+
+fn fq(value: &Fq) -> String {
+    value.into_bigint().to_string()
+}
+
+// The point at infinity is (0, 0), as for the precompiles
+fn g1(point: &G1Affine) -> [Fq; 2] {
+    if point.infinity {
+        [Fq::from(0); 2]
+    } else {
+        [point.x, point.y]
+    }
+}
+
+fn g2(point: &G2Affine) -> [Fq; 4] {
+    let (x, y) = if point.infinity { (Fq2::default(), Fq2::default()) } else { (point.x, point.y) };
+    [x.c1, x.c0, y.c1, y.c0]
+}
+
+pub fn solidity_verifier(name: &str, vk: &VerifyingKey<Bn254>) -> String {
+    let inputs = vk.gamma_abc_g1.len() - 1;
+    let mut constants = String::new();
+    let [x, y] = g1(&vk.alpha_g1).map(|coordinate| fq(&coordinate));
+    writeln!(constants, "    uint256 constant ALPHA_X = {};\n    uint256 constant ALPHA_Y = {};", x, y).unwrap();
+    for (point, label) in [(&vk.beta_g2, "BETA"), (&vk.gamma_g2, "GAMMA"), (&vk.delta_g2, "DELTA")] {
+        for (coordinate, suffix) in g2(point).iter().zip(["X1", "X0", "Y1", "Y0"]) {
+            writeln!(constants, "    uint256 constant {}_{} = {};", label, suffix, fq(coordinate)).unwrap();
+        }
+    }
+    for (i, point) in vk.gamma_abc_g1.iter().enumerate() {
+        let [x, y] = g1(point).map(|coordinate| fq(&coordinate));
+        writeln!(constants, "    uint256 constant IC{}_X = {};\n    uint256 constant IC{}_Y = {};", i, x, i, y).unwrap();
+    }
+    let mut accumulation = String::new();
+    for i in 0..inputs {
+        writeln!(
+            accumulation,
+            "        require(input[{i}] < R, \"input out of the field\");\n        x = add(x, mul([IC{}_X, IC{}_Y], input[{i}]));",
+            i + 1,
+            i + 1
+        )
+        .unwrap();
+    }
+    let mut pairing = String::new();
+    let terms = [
+        "a[0]", "(Q - a[1] % Q) % Q", "b[0][0]", "b[0][1]", "b[1][0]", "b[1][1]",
+        "ALPHA_X", "ALPHA_Y", "BETA_X1", "BETA_X0", "BETA_Y1", "BETA_Y0",
+        "x[0]", "x[1]", "GAMMA_X1", "GAMMA_X0", "GAMMA_Y1", "GAMMA_Y0",
+        "c[0]", "c[1]", "DELTA_X1", "DELTA_X0", "DELTA_Y1", "DELTA_Y0",
+    ];
+    for (i, term) in terms.iter().enumerate() {
+        writeln!(pairing, "        p[{}] = {};", i, term).unwrap();
+    }
+    format!(
+        r#"// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.0;
+
+// Groth16 verifier of a SKATE circuit, generated from its verifying key
+contract {name} {{
+    uint256 constant R = {r};
+    uint256 constant Q = {q};
+
+{constants}
+    // e(-A, B) e(alpha, beta) e(x, gamma) e(C, delta) = 1, x combining the public inputs
+    function verifyProof(
+        uint256[2] calldata a,
+        uint256[2][2] calldata b,
+        uint256[2] calldata c,
+        uint256[{inputs}] calldata input
+    ) public view returns (bool) {{
+        uint256[2] memory x = [IC0_X, IC0_Y];
+{accumulation}
+        uint256[24] memory p;
+{pairing}
+        uint256[1] memory result;
+        bool success;
+        assembly {{
+            success := staticcall(gas(), 8, p, 768, result, 32)
+        }}
+        return success && result[0] == 1;
+    }}
+
+    function add(uint256[2] memory p1, uint256[2] memory p2) internal view returns (uint256[2] memory r) {{
+        uint256[4] memory input = [p1[0], p1[1], p2[0], p2[1]];
+        bool success;
+        assembly {{
+            success := staticcall(gas(), 6, input, 128, r, 64)
+        }}
+        require(success, "point addition failed");
+    }}
+
+    function mul(uint256[2] memory p, uint256 s) internal view returns (uint256[2] memory r) {{
+        uint256[3] memory input = [p[0], p[1], s];
+        bool success;
+        assembly {{
+            success := staticcall(gas(), 7, input, 96, r, 64)
+        }}
+        require(success, "scalar multiplication failed");
+    }}
+}}
+"#,
+        r = Fr::MODULUS,
+        q = Fq::MODULUS,
+    )
+}
+
+// Rollup of a circuit with the given verifier, the road map root is fixed at deployment like the fairness
+// rule. Anyone may submit a round, as any coordinator may send updates to the agents: the proof is what
+// makes the new root valid
+pub fn rollup_contract(name: &str, verifier: &str, road_map: bool) -> String {
+    let inputs = 9 + road_map as usize;
+    let (field, parameter, assignment, input) = if road_map {
+        (
+            "    uint256 public immutable roadMapRoot;\n",
+            ", uint256 _roadMapRoot",
+            "        roadMapRoot = _roadMapRoot;\n",
+            "        input[9] = roadMapRoot;\n",
+        )
+    } else {
+        ("", "", "", "")
+    };
+    format!(
+        r#"// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.0;
+
+interface I{verifier} {{
+    function verifyProof(
+        uint256[2] calldata a,
+        uint256[2][2] calldata b,
+        uint256[2] calldata c,
+        uint256[{inputs}] calldata input
+    ) external view returns (bool);
+}}
+
+// Robot root of a SKATE fleet, moved by the proven rounds
+contract {name} {{
+    I{verifier} public immutable verifier;
+    uint256 public robotRoot;
+    uint256 public round;
+    uint256 public immutable fairnessPenalty;
+    uint256 public immutable workloadCap;
+{field}
+    event RoundSettled(uint256 indexed newRound, uint256 newRoot, uint256 taskRoot);
+
+    constructor(I{verifier} _verifier, uint256 genesisRoot, uint256 _fairnessPenalty, uint256 _workloadCap{parameter}) {{
+        verifier = _verifier;
+        robotRoot = genesisRoot;
+        fairnessPenalty = _fairnessPenalty;
+        workloadCap = _workloadCap;
+{assignment}    }}
+
+    // Rounds never go backward, the sub-rounds of a round share its number
+    function submitRound(
+        uint256[2] calldata a,
+        uint256[2][2] calldata b,
+        uint256[2] calldata c,
+        uint256 newRoot,
+        uint256 taskRoot,
+        uint256 newRound,
+        uint256 seed,
+        uint256 taskStateRoot,
+        uint256 newTaskStateRoot
+    ) external {{
+        require(newRound >= round, "outdated round");
+        uint256[{inputs}] memory input;
+        input[0] = newRoot;
+        input[1] = robotRoot;
+        input[2] = taskRoot;
+        input[3] = newRound;
+        input[4] = seed;
+        input[5] = taskStateRoot;
+        input[6] = fairnessPenalty;
+        input[7] = workloadCap;
+        input[8] = newTaskStateRoot;
+{input}        require(verifier.verifyProof(a, b, c, input), "invalid proof");
+        robotRoot = newRoot;
+        round = newRound;
+        emit RoundSettled(newRound, newRoot, taskRoot);
+    }}
+}}
+"#
+    )
+}
+
+fn uint<B: BigInteger>(value: B) -> Token {
+    Token::Uint(U256::from_little_endian(&value.to_bytes_le()))
+}
+
+fn uint_array(values: Vec<Token>) -> Token {
+    Token::FixedArray(values)
+}
+
+fn point_type() -> ParamType {
+    ParamType::FixedArray(Box::new(ParamType::Uint(256)), 2)
+}
+
+// (a, b, c) as the verifier takes them
+fn proof_tokens(proof: &Proof<Bn254>) -> Vec<Token> {
+    let coordinates = |coordinates: &[Fq]| uint_array(coordinates.iter().map(|c| uint(c.into_bigint())).collect());
+    let b = g2(&proof.b);
+    vec![
+        coordinates(&g1(&proof.a)),
+        uint_array(vec![coordinates(&b[0..2]), coordinates(&b[2..4])]),
+        coordinates(&g1(&proof.c)),
+    ]
+}
+
+fn proof_types() -> Vec<ParamType> {
+    vec![point_type(), ParamType::FixedArray(Box::new(point_type()), 2), point_type()]
+}
+
+// Call of verifyProof of the verifier of the circuit
+pub fn verify_calldata(proof: &Proof<Bn254>, public_inputs: &[Fr]) -> Bytes {
+    let mut types = proof_types();
+    types.push(ParamType::FixedArray(Box::new(ParamType::Uint(256)), public_inputs.len()));
+    let mut tokens = proof_tokens(proof);
+    tokens.push(uint_array(public_inputs.iter().map(|input| uint(input.into_bigint())).collect()));
+    [short_signature("verifyProof", &types).to_vec(), encode(&tokens)].concat().into()
+}
+
+// Call of submitRound of the rollup, from the public inputs of the round in the order of the circuit
+pub fn submit_calldata(proof: &Proof<Bn254>, public_inputs: &[Fr]) -> Result<Bytes> {
+    if public_inputs.len() < 9 {
+        return Err(SkateError::InvalidLength { expected: 9, found: public_inputs.len() });
+    }
+    let mut types = proof_types();
+    types.extend(vec![ParamType::Uint(256); 6]);
+    let mut tokens = proof_tokens(proof);
+    // New robot root, task root, round, seed and task-state roots, the old root is the one of the contract
+    for i in [0, 2, 3, 4, 5, 8] {
+        tokens.push(uint(public_inputs[i].into_bigint()));
+    }
+    Ok([short_signature("submitRound", &types).to_vec(), encode(&tokens)].concat().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::TaskState;
+    use crate::position::Position;
+    use crate::proof_system::{Groth16Backend, ProofSystem};
+    use crate::rollup::RollupCircuit;
+    use crate::Skate::{create_robot, create_task, SkateParameters, IDLE_TASK_ID};
+    use ark_ec::AffineRepr;
+    use ark_groth16::PreparedVerifyingKey;
+    use ark_std::rand::rngs::StdRng;
+    use ark_std::rand::SeedableRng;
+    use ethers_core::abi::decode;
+    use revm::db::{CacheDB, EmptyDB};
+    use revm::primitives::{Address, ExecutionResult, Output, TxKind};
+    use revm::Evm;
+
+    /* The contracts are run in revm. The verifier needs solc to be compiled, which the crate does not
+       depend on: its test is ignored, run it with `cargo test -- --ignored` and solc on the PATH. The
+       check the verifier makes is also run on the precompiles from the calldata, without solc */
+
+    fn round() -> (PreparedVerifyingKey<Bn254>, Proof<Bn254>, Vec<Fr>) {
+        let mut rng = StdRng::seed_from_u64(0);
+        let position = Position::new(3, 4, 0).unwrap();
+        let robot = create_robot(Fr::from(0), [Fr::from(IDLE_TASK_ID); 2], [Position::default(), position]).unwrap();
+        let (robots, tasks) = (vec![robot], vec![create_task(Fr::from(0), Position::new(1, 1, 0).unwrap()).unwrap()]);
+        let states = vec![(Fr::from(0), TaskState::Pending.value())];
+        let circuit = RollupCircuit::new(&robots, &tasks, &SkateParameters::default(), Fr::from(1), &states).unwrap();
+        let (pk, vk) = Groth16Backend.keys(circuit.clone(), &mut rng).unwrap();
+        let proof = Groth16Backend.prove(&pk, circuit.clone(), &mut rng).unwrap();
+        (vk, proof, circuit.public_inputs())
+    }
+
+    // The result of a transaction from the zero address, committed to the state
+    fn transact(db: &mut CacheDB<EmptyDB>, to: TxKind, data: Vec<u8>) -> ExecutionResult {
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_tx_env(|tx| {
+                tx.transact_to = to;
+                tx.data = data.into();
+                tx.gas_limit = 30_000_000;
+            })
+            .build();
+        evm.transact_commit().unwrap()
+    }
+
+    fn output(result: ExecutionResult) -> Option<Vec<u8>> {
+        match result {
+            ExecutionResult::Success { output: Output::Call(bytes), .. } => Some(bytes.to_vec()),
+            _ => None,
+        }
+    }
+
+    fn word(token: &Token) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        token.clone().into_uint().unwrap().to_big_endian(&mut bytes);
+        bytes
+    }
+
+    fn point(point: G1Affine) -> Vec<u8> {
+        g1(&point).iter().flat_map(|coordinate| coordinate.into_bigint().to_bytes_be()).collect()
+    }
+
+    fn precompile(db: &mut CacheDB<EmptyDB>, address: u8, input: Vec<u8>) -> Option<Vec<u8>> {
+        output(transact(db, TxKind::Call(Address::with_last_byte(address)), input))
+    }
+
+    // The pairing of verifyProof, from its calldata, with the public inputs combined by the precompiles
+    fn pairing_check(db: &mut CacheDB<EmptyDB>, vk: &VerifyingKey<Bn254>, calldata: &[u8]) -> bool {
+        let mut types = proof_types();
+        types.push(ParamType::FixedArray(Box::new(ParamType::Uint(256)), vk.gamma_abc_g1.len() - 1));
+        let tokens = decode(&types, &calldata[4..]).unwrap();
+        let words = |token: &Token| token.clone().into_fixed_array().unwrap();
+        let (a, b, c, inputs) = (words(&tokens[0]), words(&tokens[1]), words(&tokens[2]), words(&tokens[3]));
+
+        let mut x = point(vk.gamma_abc_g1[0]);
+        for (base, input) in vk.gamma_abc_g1[1..].iter().zip(&inputs) {
+            let Some(product) = precompile(db, 7, [point(*base), word(input).to_vec()].concat()) else {
+                return false;
+            };
+            x = precompile(db, 6, [x, product].concat()).unwrap();
+        }
+        let g2_words = |point: &G2Affine| -> Vec<u8> {
+            g2(point).iter().flat_map(|coordinate| coordinate.into_bigint().to_bytes_be()).collect()
+        };
+        let negated_y = -Fq::from_be_bytes_mod_order(&word(&a[1]));
+        let mut input = [word(&a[0]).to_vec(), negated_y.into_bigint().to_bytes_be()].concat();
+        input.extend(b.iter().flat_map(words).flat_map(|coordinate| word(&coordinate)));
+        input.extend([point(vk.alpha_g1), g2_words(&vk.beta_g2)].concat());
+        input.extend([x, g2_words(&vk.gamma_g2)].concat());
+        input.extend([word(&c[0]), word(&c[1])].concat());
+        input.extend(g2_words(&vk.delta_g2));
+        precompile(db, 8, input).is_some_and(|result| result[31] == 1)
+    }
+
+    #[test]
+    fn proofs_are_accepted_by_the_precompiles() {
+        let (vk, proof, inputs) = round();
+        let mut db = CacheDB::new(EmptyDB::default());
+        assert!(pairing_check(&mut db, &vk.vk, &verify_calldata(&proof, &inputs)));
+
+        // Another new root, or B with the c0 of its coordinates first
+        let mut changed = inputs.clone();
+        changed[0] += Fr::from(1);
+        assert!(!pairing_check(&mut db, &vk.vk, &verify_calldata(&proof, &changed)));
+        let mut swapped = proof.clone();
+        let (x, y) = (proof.b.x, proof.b.y);
+        swapped.b = G2Affine::new_unchecked(Fq2::new(x.c1, x.c0), Fq2::new(y.c1, y.c0));
+        assert!(!pairing_check(&mut db, &vk.vk, &verify_calldata(&swapped, &inputs)));
+        let mut other = proof.clone();
+        other.c = (proof.c + G1Affine::generator()).into();
+        assert!(!pairing_check(&mut db, &vk.vk, &verify_calldata(&other, &inputs)));
+    }
+
+    // The binaries of the contracts compiled by solc, by name
+    fn compile(sources: &[(&str, String)]) -> serde_json::Value {
+        let directory = std::env::temp_dir().join(format!("skate_solc_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let mut paths = vec![];
+        for (name, source) in sources {
+            let path = directory.join(format!("{}.sol", name));
+            std::fs::write(&path, source).unwrap();
+            paths.push(path);
+        }
+        let output = std::process::Command::new("solc").args(["--combined-json", "bin"]).args(&paths).output();
+        std::fs::remove_dir_all(&directory).unwrap();
+        let output = output.expect("solc is not on the PATH");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice(&output.stdout).unwrap()
+    }
+
+    fn binary(compiled: &serde_json::Value, name: &str) -> Vec<u8> {
+        let contracts = compiled["contracts"].as_object().unwrap();
+        let (_, contract) = contracts.iter().find(|(path, _)| path.ends_with(&format!(":{}", name))).unwrap();
+        hex::decode(contract["bin"].as_str().unwrap()).unwrap()
+    }
+
+    fn deploy(db: &mut CacheDB<EmptyDB>, code: Vec<u8>) -> Address {
+        match transact(db, TxKind::Create, code) {
+            ExecutionResult::Success { output: Output::Create(_, Some(address)), .. } => address,
+            result => panic!("deployment failed: {:?}", result),
+        }
+    }
+
+    fn call(db: &mut CacheDB<EmptyDB>, contract: Address, data: Vec<u8>) -> Option<Vec<u8>> {
+        output(transact(db, TxKind::Call(contract), data))
+    }
+
+    #[test]
+    #[ignore = "needs solc on the PATH"]
+    fn rounds_are_settled_by_the_contracts() {
+        let (vk, proof, inputs) = round();
+        let compiled = compile(&[
+            ("Verifier", solidity_verifier("SkateVerifier", &vk.vk)),
+            ("Rollup", rollup_contract("SkateRollup", "SkateVerifier", false)),
+        ]);
+        let mut db = CacheDB::new(EmptyDB::default());
+        let verifier = deploy(&mut db, binary(&compiled, "SkateVerifier"));
+        let accepted = call(&mut db, verifier, verify_calldata(&proof, &inputs).to_vec()).unwrap();
+        assert_eq!(accepted[31], 1);
+        let mut changed = inputs.clone();
+        changed[0] += Fr::from(1);
+        let rejected = call(&mut db, verifier, verify_calldata(&proof, &changed).to_vec()).unwrap();
+        assert_eq!(rejected[31], 0);
+
+        // Genesis root, fairness penalty and workload cap as the public inputs of the round take them
+        let constructor = encode(&[
+            Token::Address(verifier.into_array().into()),
+            uint(inputs[1].into_bigint()),
+            uint(inputs[6].into_bigint()),
+            uint(inputs[7].into_bigint()),
+        ]);
+        let rollup = deploy(&mut db, [binary(&compiled, "SkateRollup"), constructor].concat());
+        let robot_root = short_signature("robotRoot", &[]).to_vec();
+        let root = |db: &mut CacheDB<EmptyDB>| call(db, rollup, robot_root.clone()).unwrap();
+
+        let mut swapped = proof.clone();
+        let (x, y) = (proof.b.x, proof.b.y);
+        swapped.b = G2Affine::new_unchecked(Fq2::new(x.c1, x.c0), Fq2::new(y.c1, y.c0));
+        assert!(call(&mut db, rollup, submit_calldata(&swapped, &inputs).unwrap().to_vec()).is_none());
+        assert!(call(&mut db, rollup, submit_calldata(&proof, &changed).unwrap().to_vec()).is_none());
+        assert_eq!(root(&mut db), inputs[1].into_bigint().to_bytes_be());
+
+        assert!(call(&mut db, rollup, submit_calldata(&proof, &inputs).unwrap().to_vec()).is_some());
+        assert_eq!(root(&mut db), inputs[0].into_bigint().to_bytes_be());
+        // The round does not start from the new root
+        assert!(call(&mut db, rollup, submit_calldata(&proof, &inputs).unwrap().to_vec()).is_none());
+    }
+}
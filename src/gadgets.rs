@@ -0,0 +1,341 @@
+use crate::{anemoi_2_to_1, anemoi_4_to_1};
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, Field, One, PrimeField, Zero};
+use ark_relations::r1cs::{ConstraintSystemRef, LinearCombination, SynthesisError, Variable};
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+
+/* R1CS building blocks of the rollup (see rollup.rs), most of them after a circomlib template. A signal
+   is a linear combination of variables with its value: linear operations are free, every product
+   allocates a witness.
+   The constraints never depend on the values, so a circuit built from placeholder values has the same
+   shape as the real one (see rollup::RollupCircuit::blank) */
+
+#[derive(Debug, Clone)]
+pub struct Signal {
+    pub lc: LinearCombination<Fr>,
+    pub value: Fr,
+}
+
+impl Signal {
+    pub fn constant(value: Fr) -> Signal {
+        Signal {
+            lc: LinearCombination::from((value, Variable::One)),
+            value,
+        }
+    }
+
+    pub fn zero() -> Signal {
+        Signal::constant(Fr::zero())
+    }
+
+    pub fn one() -> Signal {
+        Signal::constant(Fr::one())
+    }
+}
+
+impl Add<&Signal> for &Signal {
+    type Output = Signal;
+
+    fn add(self, other: &Signal) -> Signal {
+        Signal {
+            lc: &self.lc + &other.lc,
+            value: self.value + other.value,
+        }
+    }
+}
+
+impl Sub<&Signal> for &Signal {
+    type Output = Signal;
+
+    fn sub(self, other: &Signal) -> Signal {
+        Signal {
+            lc: &self.lc - &other.lc,
+            value: self.value - other.value,
+        }
+    }
+}
+
+impl Add<Fr> for &Signal {
+    type Output = Signal;
+
+    fn add(self, constant: Fr) -> Signal {
+        self + &Signal::constant(constant)
+    }
+}
+
+impl Mul<Fr> for &Signal {
+    type Output = Signal;
+
+    fn mul(self, scalar: Fr) -> Signal {
+        Signal {
+            lc: &self.lc * scalar,
+            value: self.value * scalar,
+        }
+    }
+}
+
+pub fn input(cs: &ConstraintSystemRef<Fr>, value: Fr) -> Result<Signal, SynthesisError> {
+    let variable = cs.new_input_variable(|| Ok(value))?;
+    Ok(Signal {
+        lc: LinearCombination::from(variable),
+        value,
+    })
+}
+
+pub fn witness(cs: &ConstraintSystemRef<Fr>, value: Fr) -> Result<Signal, SynthesisError> {
+    let variable = cs.new_witness_variable(|| Ok(value))?;
+    Ok(Signal {
+        lc: LinearCombination::from(variable),
+        value,
+    })
+}
+
+// a * b === c
+pub fn enforce_product(
+    cs: &ConstraintSystemRef<Fr>,
+    a: &Signal,
+    b: &Signal,
+    c: &Signal,
+) -> Result<(), SynthesisError> {
+    cs.enforce_constraint(a.lc.clone(), b.lc.clone(), c.lc.clone())
+}
+
+// a === b
+pub fn enforce_equal(cs: &ConstraintSystemRef<Fr>, a: &Signal, b: &Signal) -> Result<(), SynthesisError> {
+    enforce_product(cs, &(a - b), &Signal::one(), &Signal::zero())
+}
+
+pub fn mul(cs: &ConstraintSystemRef<Fr>, a: &Signal, b: &Signal) -> Result<Signal, SynthesisError> {
+    let c = witness(cs, a.value * b.value)?;
+    enforce_product(cs, a, b, &c)?;
+    Ok(c)
+}
+
+// The same signal through a symbolic combination: inlined in the constraints with the Constraints goal
+// (Groth16, the matrices are unchanged), given its own variable when used often with the Weight goal
+// (PLONK, which pays for each term, see proof_system)
+pub fn symbolic(cs: &ConstraintSystemRef<Fr>, signal: &Signal) -> Result<Signal, SynthesisError> {
+    Ok(Signal {
+        lc: LinearCombination::from(cs.new_lc(signal.lc.clone())?),
+        value: signal.value,
+    })
+}
+
+pub fn sum(signals: &[Signal]) -> Signal {
+    signals.iter().fold(Signal::zero(), |total, signal| &total + signal)
+}
+
+// IsZero
+pub fn is_zero(cs: &ConstraintSystemRef<Fr>, a: &Signal) -> Result<Signal, SynthesisError> {
+    let inverse = witness(cs, a.value.inverse().unwrap_or_else(Fr::zero))?;
+    let product = mul(cs, a, &inverse)?;
+    let out = &Signal::one() - &product;
+    enforce_product(cs, a, &out, &Signal::zero())?;
+    Ok(out)
+}
+
+// IsEqual
+pub fn is_equal(cs: &ConstraintSystemRef<Fr>, a: &Signal, b: &Signal) -> Result<Signal, SynthesisError> {
+    is_zero(cs, &(a - b))
+}
+
+// Num2Bits, little-endian
+pub fn num2bits(
+    cs: &ConstraintSystemRef<Fr>,
+    a: &Signal,
+    bits: usize,
+) -> Result<Vec<Signal>, SynthesisError> {
+    let value = a.value.into_bigint();
+    let mut out = vec![];
+    let mut total = Signal::zero();
+    for i in 0..bits {
+        let bit = witness(cs, Fr::from(value.get_bit(i)))?;
+        enforce_product(cs, &bit, &(&bit + -Fr::one()), &Signal::zero())?;
+        total = &total + &(&bit * Fr::from(2u64).pow([i as u64]));
+        out.push(bit);
+    }
+    enforce_equal(cs, &total, a)?;
+    Ok(out)
+}
+
+// Num2Bits_strict: the 254 bits of a, which must be the canonical ones (below the modulus) so that the
+// low bits of a field element cannot be chosen among two decompositions
+pub fn num2bits_strict(cs: &ConstraintSystemRef<Fr>, a: &Signal) -> Result<Vec<Signal>, SynthesisError> {
+    let bits = num2bits(cs, a, Fr::MODULUS_BIT_SIZE as usize)?;
+    let mut bound = Fr::MODULUS;
+    bound.sub_with_borrow(&<Fr as PrimeField>::BigInt::from(1u64));
+    // From the top bit, equal is 1 while the bits match those of p - 1, a bit above it is rejected
+    let mut equal = Signal::one();
+    for i in (0..bits.len()).rev() {
+        if bound.get_bit(i) {
+            equal = mul(cs, &equal, &bits[i])?;
+        } else {
+            enforce_product(cs, &equal, &bits[i], &Signal::zero())?;
+        }
+    }
+    Ok(bits)
+}
+
+// LessThan(bits): a < b for a and b on `bits` bits
+pub fn less_than(
+    cs: &ConstraintSystemRef<Fr>,
+    a: &Signal,
+    b: &Signal,
+    bits: usize,
+) -> Result<Signal, SynthesisError> {
+    assert!(bits <= 252);
+    let shifted = &(a + Fr::from(2u64).pow([bits as u64])) - b;
+    let out = num2bits(cs, &shifted, bits + 1)?;
+    Ok(&Signal::one() - &out[bits])
+}
+
+// LessEqThan(bits)
+pub fn less_eq(
+    cs: &ConstraintSystemRef<Fr>,
+    a: &Signal,
+    b: &Signal,
+    bits: usize,
+) -> Result<Signal, SynthesisError> {
+    less_than(cs, a, &(b + Fr::one()), bits)
+}
+
+// GreaterEqThan(bits)
+pub fn greater_eq(
+    cs: &ConstraintSystemRef<Fr>,
+    a: &Signal,
+    b: &Signal,
+    bits: usize,
+) -> Result<Signal, SynthesisError> {
+    less_than(cs, b, &(a + Fr::one()), bits)
+}
+
+// Number of bits of the indexes 0..=length, so that `length` itself (no task) fits
+pub fn selector_bits(length: usize) -> usize {
+    (usize::BITS - length.leading_zeros()) as usize
+}
+
+// QuinSelector: values[index], with index < values.len()
+pub fn select(
+    cs: &ConstraintSystemRef<Fr>,
+    values: &[Signal],
+    index: &Signal,
+) -> Result<Signal, SynthesisError> {
+    let bits = selector_bits(values.len());
+    let below = less_than(cs, index, &Signal::constant(Fr::from(values.len() as u64)), bits)?;
+    enforce_equal(cs, &below, &Signal::one())?;
+    let mut selected = vec![];
+    for (i, value) in values.iter().enumerate() {
+        let eq = is_equal(cs, &Signal::constant(Fr::from(i as u64)), index)?;
+        selected.push(mul(cs, &eq, value)?);
+    }
+    Ok(sum(&selected))
+}
+
+// The s-box of Anemoi on the pair (x, y): x -= 5y^2, y -= x^(1/5), x += 5y^2 + delta
+fn s_box(
+    cs: &ConstraintSystemRef<Fr>,
+    x: &Signal,
+    y: &Signal,
+) -> Result<(Signal, Signal), SynthesisError> {
+    let five = Fr::from(5u64);
+    let y_square = mul(cs, y, y)?;
+    let x = x - &(&y_square * five);
+    let root = witness(cs, x.value.pow(anemoi_2_to_1::ALPHA_INV))?;
+    let root_2 = mul(cs, &root, &root)?;
+    let root_4 = mul(cs, &root_2, &root_2)?;
+    enforce_product(cs, &root_4, &root, &x)?;
+    let y = y - &root;
+    let y_square = mul(cs, &y, &y)?;
+    let x = &(&x + &(&y_square * five)) + Fr::from_str(anemoi_2_to_1::DELTA).unwrap();
+    Ok((x, y))
+}
+
+// Applies a linear layer, given by its action on the field elements, to signals
+fn linear_layer<const W: usize>(state: &[Signal; W], layer: fn([Fr; W]) -> [Fr; W]) -> [Signal; W] {
+    let mut out: [Signal; W] = std::array::from_fn(|_| Signal::zero());
+    for l in 0..W {
+        let mut unit = [Fr::zero(); W];
+        unit[l] = Fr::one();
+        let column = layer(unit);
+        for k in 0..W {
+            out[k] = &out[k] + &(&state[l] * column[k]);
+        }
+    }
+    out
+}
+
+// Anemoi permutation with W / 2 columns, the s-box pairs the element i with the element i + W / 2
+fn anemoi<const W: usize>(
+    cs: &ConstraintSystemRef<Fr>,
+    inputs: &[Signal; W],
+    rounds: usize,
+    ark_layer: fn(usize, [Fr; W]) -> [Fr; W],
+    mds_layer: fn([Fr; W]) -> [Fr; W],
+) -> Result<Signal, SynthesisError> {
+    let mut state = inputs.clone();
+    for round in 0..rounds {
+        let constants = ark_layer(round, [Fr::zero(); W]);
+        for k in 0..W {
+            state[k] = &state[k] + constants[k];
+        }
+        state = linear_layer(&state, mds_layer);
+        for k in 0..W / 2 {
+            let (x, y) = s_box(cs, &state[k], &state[k + W / 2])?;
+            state[k] = symbolic(cs, &x)?;
+            state[k + W / 2] = symbolic(cs, &y)?;
+        }
+    }
+    state = linear_layer(&state, mds_layer);
+    Ok(&sum(inputs) + &sum(&state))
+}
+
+pub fn hash_2_to_1(cs: &ConstraintSystemRef<Fr>, a: &Signal, b: &Signal) -> Result<Signal, SynthesisError> {
+    anemoi(
+        cs,
+        &[a.clone(), b.clone()],
+        anemoi_2_to_1::ROUNDS,
+        anemoi_2_to_1::ark_layer,
+        anemoi_2_to_1::mds_layer,
+    )
+}
+
+pub fn hash_4_to_1(cs: &ConstraintSystemRef<Fr>, inputs: [&Signal; 4]) -> Result<Signal, SynthesisError> {
+    anemoi(
+        cs,
+        &inputs.map(|input| input.clone()),
+        anemoi_4_to_1::ROUNDS,
+        anemoi_4_to_1::ark_layer,
+        anemoi_4_to_1::mds_layer,
+    )
+}
+
+// merkle_tree, the number of leaves is a power of two (at least 2)
+pub fn merkle_root(cs: &ConstraintSystemRef<Fr>, leaves: Vec<Signal>) -> Result<Signal, SynthesisError> {
+    assert!(leaves.len() >= 2 && leaves.len().is_power_of_two());
+    let mut nodes = leaves;
+    while nodes.len() > 1 {
+        let mut parents = vec![];
+        for pair in nodes.chunks(2) {
+            parents.push(hash_2_to_1(cs, &pair[0], &pair[1])?);
+        }
+        nodes = parents;
+    }
+    Ok(nodes.remove(0))
+}
+
+// Root of the path from a leaf, the bits of its index tell at each level if the node is the right child
+pub fn merkle_path_root(
+    cs: &ConstraintSystemRef<Fr>,
+    leaf: &Signal,
+    index_bits: &[Signal],
+    path: &[Signal],
+) -> Result<Signal, SynthesisError> {
+    let mut node = leaf.clone();
+    for (bit, sibling) in index_bits.iter().zip(path) {
+        let left = &node + &mul(cs, bit, &(sibling - &node))?;
+        let right = &(&node + sibling) - &left;
+        node = hash_2_to_1(cs, &left, &right)?;
+    }
+    Ok(node)
+}
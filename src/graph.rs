@@ -0,0 +1,280 @@
+use crate::error::{Result, SkateError};
+use crate::hashes::get_travel_time_proof;
+use crate::position::Position;
+use crate::Skate::{check_length, Robot, Task};
+use ark_bn254::Fr;
+use serde::Deserialize;
+
+// Travel times are on 29 bits so that graph costs fit the same range checks as the squared
+// euclidean distance (see DistanceMetric::bits)
+pub const MAX_TRAVEL_TIME: u64 = (1 << 29) - 3;
+
+/* A road map is:
+    - a list of nodes (grid positions, range-checked when deserialized)
+    - a list of undirected edges between two nodes with a travel time
+*/
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoadMap {
+    pub nodes: Vec<Position>,
+    pub edges: Vec<(usize, usize, u64)>,
+}
+
+// Shortest travel time between every pair of nodes of a road map
+#[derive(Debug, Clone)]
+pub struct DistanceTable {
+    pub nodes: Vec<Position>,
+    pub travel_times: Vec<Vec<Fr>>,
+}
+
+impl RoadMap {
+    // Floyd-Warshall, the road map must be connected and its edges at most MAX_TRAVEL_TIME
+    pub fn distance_table(&self) -> Result<DistanceTable> {
+        let n = self.nodes.len();
+        let mut times = vec![vec![u64::MAX; n]; n];
+        for i in 0..n {
+            times[i][i] = 0;
+        }
+        for &(from, to, time) in &self.edges {
+            if from >= n || to >= n {
+                return Err(SkateError::InvalidRoadMap(format!("edge ({}, {}) between unknown nodes", from, to)));
+            }
+            if time > MAX_TRAVEL_TIME {
+                return Err(SkateError::InvalidRoadMap(format!("edge ({}, {}) above the maximum travel time", from, to)));
+            }
+            if time < times[from][to] {
+                times[from][to] = time;
+                times[to][from] = time;
+            }
+        }
+        for k in 0..n {
+            for i in 0..n {
+                for j in 0..n {
+                    // u64::MAX for no path yet, a sum above it is no shorter path
+                    let through_k = times[i][k].saturating_add(times[k][j]);
+                    if through_k < times[i][j] {
+                        times[i][j] = through_k;
+                    }
+                }
+            }
+        }
+
+        let mut travel_times = vec![vec![Fr::from(0); n]; n];
+        for i in 0..n {
+            for j in 0..n {
+                if times[i][j] == u64::MAX {
+                    return Err(SkateError::InvalidRoadMap(format!("no path from node {} to node {}", i, j)));
+                }
+                if times[i][j] > MAX_TRAVEL_TIME {
+                    return Err(SkateError::InvalidRoadMap(format!(
+                        "the path from node {} to node {} is above the maximum travel time",
+                        i, j
+                    )));
+                }
+                travel_times[i][j] = Fr::from(times[i][j]);
+            }
+        }
+
+        Ok(DistanceTable {
+            nodes: self.nodes.clone(),
+            travel_times,
+        })
+    }
+}
+
+impl DistanceTable {
+    pub fn node(&self, position: Position) -> Option<usize> {
+        self.nodes.iter().position(|node| *node == position)
+    }
+
+    pub fn travel_time(&self, from: Position, to: Position) -> Option<Fr> {
+        Some(self.travel_times[self.node(from)?][self.node(to)?])
+    }
+
+    // Tasks must be placed on a node of the road map, the coordinator snaps them to the closest one
+    pub fn nearest_node(&self, position: Position) -> Position {
+        let grid = position.grid();
+        *self
+            .nodes
+            .iter()
+            .min_by_key(|node| {
+                let node = node.grid();
+                (0..3)
+                    .map(|i| (node[i] as i64 - grid[i] as i64).pow(2) as u64)
+                    .sum::<u64>()
+            })
+            .unwrap()
+    }
+
+    // Index of the leaf committing the travel time between the nodes `from` and `to`
+    pub fn entry_index(&self, from: usize, to: usize) -> usize {
+        from * self.nodes.len() + to
+    }
+}
+
+// A travel time of the distance table with the index and the Merkle path of its leaf
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableEntry {
+    pub travel_time: Fr,
+    pub index: usize,
+    pub path: Vec<Fr>,
+}
+
+impl TableEntry {
+    // Entry of a robot without battery limit, unchecked by the circuit
+    fn unchecked(depth: usize) -> TableEntry {
+        TableEntry {
+            travel_time: Fr::from(0),
+            index: 0,
+            path: vec![Fr::from(0); depth],
+        }
+    }
+}
+
+// Witness of the distance table for a round, the travel times read by the rollup with a road map
+#[derive(Debug, Clone)]
+pub struct RoadMapWitness {
+    pub root: Fr,
+    pub depth: usize,
+    // From the current position of each robot to each task
+    pub travel: Vec<Vec<TableEntry>>,
+    // From each task to the station of each robot, and from each robot to its station, unchecked for
+    // the robots without battery limit
+    pub returns: Vec<Vec<TableEntry>>,
+    pub charges: Vec<TableEntry>,
+}
+
+impl RoadMapWitness {
+    // Entries of n robots and m tasks in a table of the given depth, with placeholder values
+    pub fn blank(n: usize, m: usize, depth: usize) -> RoadMapWitness {
+        RoadMapWitness {
+            root: Fr::from(0),
+            depth,
+            travel: vec![vec![TableEntry::unchecked(depth); m]; n],
+            returns: vec![vec![TableEntry::unchecked(depth); m]; n],
+            charges: vec![TableEntry::unchecked(depth); n],
+        }
+    }
+
+    // The part of the distance table the entries give, enough to replay the round: the robots only
+    // move between these positions. Two entries cannot give different travel times to the same pair
+    pub fn table(&self, robots: &[Robot], tasks: &[Task]) -> Result<DistanceTable> {
+        check_length(self.travel.len(), robots.len())?;
+        check_length(self.returns.len(), robots.len())?;
+        check_length(self.charges.len(), robots.len())?;
+        let mut entries: Vec<(Position, Position, Fr)> = vec![];
+        let mut add = |from: Position, to: Position, travel_time: Fr| {
+            match entries.iter().find(|(a, b, _)| *a == from && *b == to) {
+                Some((_, _, known)) if *known != travel_time => Err(SkateError::InvalidRoadMap(format!(
+                    "two travel times from {:?} to {:?}",
+                    from, to
+                ))),
+                Some(_) => Ok(()),
+                None => {
+                    entries.push((from, to, travel_time));
+                    Ok(())
+                }
+            }
+        };
+        for (i, robot) in robots.iter().enumerate() {
+            check_length(self.travel[i].len(), tasks.len())?;
+            check_length(self.returns[i].len(), tasks.len())?;
+            let position = robot.list_tasks[1].position;
+            for (task, entry) in tasks.iter().zip(&self.travel[i]) {
+                add(position, task.position, entry.travel_time)?;
+            }
+            if robot.capacity != Fr::from(0) {
+                for (task, entry) in tasks.iter().zip(&self.returns[i]) {
+                    add(task.position, robot.station, entry.travel_time)?;
+                }
+                add(position, robot.station, self.charges[i].travel_time)?;
+            }
+        }
+
+        let mut nodes: Vec<Position> = vec![];
+        for (from, to, _) in &entries {
+            for position in [from, to] {
+                if !nodes.contains(position) {
+                    nodes.push(*position);
+                }
+            }
+        }
+        let mut table = DistanceTable {
+            travel_times: vec![vec![Fr::from(0); nodes.len()]; nodes.len()],
+            nodes,
+        };
+        for (from, to, travel_time) in entries {
+            let (from, to) = (table.node(from).unwrap(), table.node(to).unwrap());
+            table.travel_times[from][to] = travel_time;
+        }
+        Ok(table)
+    }
+}
+
+// `tree` is the root and the levels of the table (see hashes::distance_table_root)
+pub fn road_map_witness(
+    table: &DistanceTable,
+    tree: &(Fr, Vec<Vec<Fr>>),
+    robots: &[Robot],
+    tasks: &[Task],
+) -> Result<RoadMapWitness> {
+    let (root, levels) = tree;
+    let depth = levels.len();
+    let entry = |from: Position, to: Position| {
+        let (travel_time, index, path) = get_travel_time_proof(table, from, to, levels)?;
+        Ok(TableEntry { travel_time, index, path })
+    };
+    let station_entry = |robot: &Robot, from: Position| {
+        if robot.capacity == Fr::from(0) {
+            Ok(TableEntry::unchecked(depth))
+        } else {
+            entry(from, robot.station)
+        }
+    };
+    let mut witness = RoadMapWitness {
+        root: *root,
+        depth,
+        travel: vec![],
+        returns: vec![],
+        charges: vec![],
+    };
+    for robot in robots {
+        let position = robot.list_tasks[1].position;
+        witness.travel.push(tasks.iter().map(|task| entry(position, task.position)).collect::<Result<_>>()?);
+        witness.returns.push(tasks.iter().map(|task| station_entry(robot, task.position)).collect::<Result<_>>()?);
+        witness.charges.push(station_entry(robot, position)?);
+    }
+    Ok(witness)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn road_map(edges: Vec<(usize, usize, u64)>) -> RoadMap {
+        RoadMap {
+            nodes: vec![Position::new(0, 0, 0).unwrap(), Position::new(1, 0, 0).unwrap(), Position::new(2, 0, 0).unwrap()],
+            edges,
+        }
+    }
+
+    #[test]
+    fn shortest_paths_go_through_nodes() {
+        let table = road_map(vec![(0, 1, 2), (1, 2, 3), (0, 2, 10)]).distance_table().unwrap();
+        assert_eq!(table.travel_times[0][2], Fr::from(5));
+        assert_eq!(table.travel_times[2][0], Fr::from(5));
+        assert_eq!(table.travel_times[1][1], Fr::from(0));
+    }
+
+    #[test]
+    fn travel_times_stay_in_range() {
+        // Each edge fits, the path through both does not
+        let long = road_map(vec![(0, 1, MAX_TRAVEL_TIME), (1, 2, MAX_TRAVEL_TIME)]);
+        assert!(matches!(long.distance_table(), Err(SkateError::InvalidRoadMap(_))));
+        let edge = road_map(vec![(0, 1, u64::MAX), (1, 2, 1)]);
+        assert!(matches!(edge.distance_table(), Err(SkateError::InvalidRoadMap(_))));
+        let disconnected = road_map(vec![(0, 1, 1)]);
+        assert!(matches!(disconnected.distance_table(), Err(SkateError::InvalidRoadMap(_))));
+        let unknown = road_map(vec![(0, 1, 1), (1, 3, 1)]);
+        assert!(matches!(unknown.distance_table(), Err(SkateError::InvalidRoadMap(_))));
+    }
+}
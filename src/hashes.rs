@@ -1,23 +1,74 @@
 use crate::anemoi_2_to_1::hash_2_to_1;
 use crate::anemoi_4_to_1::hash_4_to_1;
-use crate::Skate::{Robot, Task};
+use crate::graph::DistanceTable;
+use crate::position::Position;
+use crate::error::{Result, SkateError};
+use crate::Skate::{
+    check_length, check_robot, check_task, prerequisite_values, to_index, Robot, Task, IDLE_TASK_ID, MAX_TASK_ID,
+};
 use ark_bn254::Fr;
 use ark_ff::PrimeField;
 use ark_std::iterable::Iterable;
 
+// The priority (8 bits), the deadline (32 bits), the requirements (16 bits), the team size (4 bits)
+// and the two prerequisites (2 * 65 bits) are packed above y and z (2 * 14 bits), a task without any
+// of them keeps the same hash as before
 fn hash_task(task: Task) -> Fr {
+    let prerequisites = prerequisite_values(&task);
     hash_2_to_1(
-        task.task_id * Fr::from(2i32.pow(14)) + task.x,
-        task.y * Fr::from(2i32.pow(14)) + task.z,
+        task.task_id * Fr::from(2i32.pow(14)) + task.position.x(),
+        task.position.y() * Fr::from(2i32.pow(14))
+            + task.position.z()
+            + task.priority * Fr::from(2u64.pow(28))
+            + task.deadline * Fr::from(2u64.pow(36))
+            + task.requirements * Fr::from(2u128.pow(68))
+            + task.team_size * Fr::from(2u128.pow(84))
+            + prerequisites[0] * Fr::from(2u128.pow(88))
+            + prerequisites[1] * Fr::from(2u128.pow(88)) * Fr::from(2u128.pow(65)),
     )
 }
 
+// The tasks stored in a robot leaf only commit the id and the position
+fn hash_waypoint(task: Task) -> Fr {
+    hash_2_to_1(
+        task.task_id * Fr::from(2i32.pow(14)) + task.position.x(),
+        task.position.y() * Fr::from(2i32.pow(14)) + task.position.z(),
+    )
+}
+
+// The battery (32 bits), the capacity (32 bits), the packed station (42 bits) and the workload (32 bits)
+// are stacked above the capabilities (16 bits), a robot without battery limit nor task keeps the same
+// hash as before
 fn hash_robot(robot: Robot) -> Fr {
     let hash = [
-        hash_task(robot.list_tasks[0]),
-        hash_task(robot.list_tasks[1]),
+        hash_waypoint(robot.list_tasks[0]),
+        hash_waypoint(robot.list_tasks[1]),
     ];
-    hash_4_to_1(robot.robot_id, hash[0], hash[1], Fr::from(0))
+    hash_4_to_1(
+        robot.robot_id,
+        hash[0],
+        hash[1],
+        robot.capabilities
+            + robot.battery * Fr::from(2u64.pow(16))
+            + robot.capacity * Fr::from(2u64.pow(48))
+            + pack_position(robot.station) * Fr::from(2u128.pow(80))
+            + robot.workload * Fr::from(2u128.pow(122)),
+    )
+}
+
+// Seed breaking the ties of a round: the low 32 bits of hash(old robot root, round), fixed by the state
+// the round starts from so that the coordinator cannot try seeds until one favors a robot
+pub fn round_seed(old_robots_root: Fr, round: Fr) -> u32 {
+    hash_2_to_1(old_robots_root, round).into_bigint().0[0] as u32
+}
+
+fn pack_position(position: Position) -> Fr {
+    position.x() * Fr::from(2u64.pow(28)) + position.y() * Fr::from(2u64.pow(14)) + position.z()
+}
+
+// A leaf of the distance table commits both end points and the travel time between them
+fn hash_table_entry(from: Position, to: Position, travel_time: Fr) -> Fr {
+    hash_4_to_1(pack_position(from), pack_position(to), travel_time, Fr::from(0))
 }
 
 /*fn merkle_tree(nodes: [Fr; 2usize.pow(N as u32)]) -> Fr {
@@ -37,6 +88,12 @@ fn hash_robot(robot: Robot) -> Fr {
     h[N - 1][0]
 }*/
 
+// Padding leaves of the task and robot trees: the hashes of a task and of a robot with all values 0
+pub(crate) const EMPTY_TASK_LEAF: &str =
+    "103860425244306721054357604449078699979184018657001128167783972180760304967";
+pub(crate) const EMPTY_ROBOT_LEAF: &str =
+    "19803829510264496905782185690924016388609305741426681378119315514000584486177";
+
 fn merkle_tree(nodes: Vec<Fr>) -> Vec<Fr> {
     if nodes.len() == 2 {
         vec![hash_2_to_1(nodes[0], nodes[1])]
@@ -64,63 +121,241 @@ fn merkle_tree_with_proof(nodes: Vec<Fr>, proofs: &mut Vec<Vec<Fr>>, level: usiz
     }
 }
 
-pub fn task_root(tasks: &Vec<Task>) -> Fr {
+pub fn task_root(tasks: &Vec<Task>) -> Result<Fr> {
+    if tasks.is_empty() {
+        return Err(SkateError::Empty);
+    }
+    for task in tasks {
+        check_task(task)?;
+    }
+    // The circuit binds each id to a single leaf by requiring strictly increasing ids
+    for pair in tasks.windows(2) {
+        if pair[0].task_id >= pair[1].task_id {
+            return Err(SkateError::UnsortedTaskIds(pair[1].task_id));
+        }
+    }
     let mut first_hash: Vec<Fr> = (0..tasks.len())
         .map(|i| hash_task(tasks[i]))
         .collect();
     while first_hash.len() < 2usize.pow(tasks.len().ilog2() + 1) {
-        first_hash.push("103860425244306721054357604449078699979184018657001128167783972180760304967".parse::<Fr>().unwrap());
+        first_hash.push(EMPTY_TASK_LEAF.parse::<Fr>().unwrap());
     }
-    merkle_tree(first_hash)[0]
+    Ok(merkle_tree(first_hash)[0])
 }
 
-pub fn robot_root(robots: &Vec<Robot>) -> (Fr, Vec<Vec<Fr>>) {
-
+pub fn robot_root(robots: &Vec<Robot>) -> Result<(Fr, Vec<Vec<Fr>>)> {
+    if robots.is_empty() {
+        return Err(SkateError::Empty);
+    }
+    for robot in robots {
+        check_robot(robot)?;
+    }
     let mut first_hash: Vec<Fr> = (0..robots.len())
         .map(|i| hash_robot(robots[i]))
         .collect();
     while first_hash.len() < 2usize.pow(robots.len().ilog2() + 1) {
-        first_hash.push("19803829510264496905782185690924016388609305741426681378119315514000584486177".parse::<Fr>().unwrap());
+        first_hash.push(EMPTY_ROBOT_LEAF.parse::<Fr>().unwrap());
     }
     let mut proofs = vec![vec![]; robots.len().ilog2() as usize + 1];
     for i in 0..2usize.pow(robots.len().ilog2() + 1) {
         proofs[0].push(first_hash[i]);
     }
-    (merkle_tree_with_proof(first_hash, &mut proofs, 0), proofs)
+    Ok((merkle_tree_with_proof(first_hash, &mut proofs, 0), proofs))
 }
 
-pub fn verify_robot_in_tree(robot: Robot, root: Fr, proof: Vec<Fr>) -> bool {
+pub fn verify_robot_in_tree(robot: Robot, root: Fr, proof: Vec<Fr>) -> Result<bool> {
+    check_robot(&robot)?;
+    if proof.len() >= usize::BITS as usize {
+        return Err(SkateError::InvalidLength {
+            expected: usize::BITS as usize - 1,
+            found: proof.len(),
+        });
+    }
+    let id = to_index(robot.robot_id, 1 << proof.len())?;
     let mut hash = hash_robot(robot);
     let mut selector;
     for i in 0..proof.len() {
-        selector = (robot
-            .robot_id
-            .into_bigint()
-            .to_string()
-            .parse::<i32>()
-            .unwrap()
-            >> i)
-            & 1;
+        selector = (id >> i) & 1;
         if selector == 1 {
             hash = hash_2_to_1(proof[i], hash);
         } else {
             hash = hash_2_to_1(hash, proof[i]);
         }
     }
-    hash == root
+    Ok(hash == root)
 }
 
-pub fn get_merkle_proof_from_id(robot_id: Fr, proofs: &Vec<Vec<Fr>>) -> Vec<Fr> {
+pub fn get_merkle_proof_from_id(robot_id: Fr, proofs: &Vec<Vec<Fr>>) -> Result<Vec<Fr>> {
+    if proofs.is_empty() {
+        return Err(SkateError::Empty);
+    }
+    let id = to_index(robot_id, proofs[0].len())?;
     let mut proof = vec![Fr::from(0); proofs[0].len().ilog2() as usize];
 
     for i in 0..proofs[0].len().ilog2() as usize {
-        if robot_id.into_bigint().to_string().parse::<usize>().unwrap() >> i & 1 == 1 {
-            proof[i] =
-                proofs[i][(robot_id.into_bigint().to_string().parse::<usize>().unwrap() >> i) - 1];
+        if id >> i & 1 == 1 {
+            proof[i] = proofs[i][(id >> i) - 1];
         } else {
-            proof[i] =
-                proofs[i][(robot_id.into_bigint().to_string().parse::<usize>().unwrap() >> i) + 1];
+            proof[i] = proofs[i][(id >> i) + 1];
+        }
+    }
+    Ok(proof)
+}
+
+// The table is padded with entries from (0, 0, 0) to itself with a travel time of 0
+pub fn distance_table_root(table: &DistanceTable) -> Result<(Fr, Vec<Vec<Fr>>)> {
+    let n = table.nodes.len();
+    if n == 0 {
+        return Err(SkateError::Empty);
+    }
+    check_length(table.travel_times.len(), n)?;
+    for row in &table.travel_times {
+        check_length(row.len(), n)?;
+    }
+    let mut first_hash = vec![];
+    for i in 0..n {
+        for j in 0..n {
+            first_hash.push(hash_table_entry(
+                table.nodes[i],
+                table.nodes[j],
+                table.travel_times[i][j],
+            ));
+        }
+    }
+    let length = (n * n).next_power_of_two().max(2);
+    while first_hash.len() < length {
+        first_hash.push(hash_table_entry(Position::default(), Position::default(), Fr::from(0)));
+    }
+    let mut proofs = vec![vec![]; length.ilog2() as usize];
+    for i in 0..length {
+        proofs[0].push(first_hash[i]);
+    }
+    Ok((merkle_tree_with_proof(first_hash, &mut proofs, 0), proofs))
+}
+
+// Returns the travel time with its leaf index and Merkle proof in the distance table
+pub fn get_travel_time_proof(
+    table: &DistanceTable,
+    from: Position,
+    to: Position,
+    proofs: &Vec<Vec<Fr>>,
+) -> Result<(Fr, usize, Vec<Fr>)> {
+    let from_node = table.node(from).ok_or(SkateError::NotOnRoadMap)?;
+    let to_node = table.node(to).ok_or(SkateError::NotOnRoadMap)?;
+    let index = table.entry_index(from_node, to_node);
+    Ok((
+        table.travel_times[from_node][to_node],
+        index,
+        get_merkle_proof_from_id(Fr::from(index as u64), proofs)?,
+    ))
+}
+
+pub fn verify_travel_time(
+    from: Position,
+    to: Position,
+    travel_time: Fr,
+    root: Fr,
+    index: usize,
+    proof: Vec<Fr>,
+) -> Result<bool> {
+    if proof.len() >= usize::BITS as usize {
+        return Err(SkateError::InvalidLength {
+            expected: usize::BITS as usize - 1,
+            found: proof.len(),
+        });
+    }
+    if index >> proof.len() != 0 {
+        return Err(SkateError::InvalidIndex(Fr::from(index as u64), 1 << proof.len()));
+    }
+    let mut hash = hash_table_entry(from, to, travel_time);
+    for i in 0..proof.len() {
+        if (index >> i) & 1 == 1 {
+            hash = hash_2_to_1(proof[i], hash);
+        } else {
+            hash = hash_2_to_1(hash, proof[i]);
+        }
+    }
+    Ok(hash == root)
+}
+
+// A leaf of the task-state tree commits the id of a task and its state (see lifecycle::TaskState)
+fn hash_task_state(task_id: Fr, state: Fr) -> Fr {
+    hash_2_to_1(task_id, state)
+}
+
+// Depth of the task-state tree in the circuits, at most 2^TASK_STATE_DEPTH tasks are in the tree at once
+pub const TASK_STATE_DEPTH: usize = 10;
+
+// Leaf index of a task in the task-state tree: the low TASK_STATE_DEPTH bits of its id. Its leaf commits
+// the whole id, so the ids 2^TASK_STATE_DEPTH apart share a slot, one at a time (see lifecycle::TaskLedger)
+pub fn task_state_slot(task_id: Fr) -> usize {
+    task_id.into_bigint().0[0] as usize & ((1 << TASK_STATE_DEPTH) - 1)
+}
+
+// Leaf of a slot without task, the one of the idle waypoint which is never a task of the tree
+pub fn empty_task_state_leaf() -> Fr {
+    hash_task_state(Fr::from(IDLE_TASK_ID), Fr::from(0))
+}
+
+// The leaf of a task is at its slot, in any order in `states`. Two tasks of the same slot are an error
+pub fn task_state_root(states: &Vec<(Fr, Fr)>) -> Result<(Fr, Vec<Vec<Fr>>)> {
+    let length = 1 << TASK_STATE_DEPTH;
+    let mut first_hash = vec![empty_task_state_leaf(); length];
+    let mut taken = vec![false; length];
+    for (task_id, state) in states {
+        if *task_id > Fr::from(MAX_TASK_ID) {
+            return Err(SkateError::InvalidTaskId(*task_id));
+        }
+        let slot = task_state_slot(*task_id);
+        if taken[slot] {
+            return Err(SkateError::SlotTaken(*task_id));
+        }
+        taken[slot] = true;
+        first_hash[slot] = hash_task_state(*task_id, *state);
+    }
+    let mut proofs = vec![vec![]; length.ilog2() as usize];
+    for i in 0..length {
+        proofs[0].push(first_hash[i]);
+    }
+    Ok((merkle_tree_with_proof(first_hash, &mut proofs, 0), proofs))
+}
+
+pub fn verify_task_state(
+    task_id: Fr,
+    state: Fr,
+    root: Fr,
+    index: usize,
+    proof: Vec<Fr>,
+) -> Result<bool> {
+    Ok(task_state_path_root(task_id, state, index, &proof)? == root)
+}
+
+// Root once the leaf at `index` is replaced by `new`, none if `old` is not that leaf under `root`. The path
+// of a slot does not depend on its leaf, so the one of the tree before is the one of the tree after
+pub fn replace_task_state(root: Fr, index: usize, old: (Fr, Fr), new: (Fr, Fr), proof: &[Fr]) -> Result<Option<Fr>> {
+    if task_state_path_root(old.0, old.1, index, proof)? != root {
+        return Ok(None);
+    }
+    Ok(Some(task_state_path_root(new.0, new.1, index, proof)?))
+}
+
+fn task_state_path_root(task_id: Fr, state: Fr, index: usize, proof: &[Fr]) -> Result<Fr> {
+    if proof.len() >= usize::BITS as usize {
+        return Err(SkateError::InvalidLength {
+            expected: usize::BITS as usize - 1,
+            found: proof.len(),
+        });
+    }
+    if index >> proof.len() != 0 {
+        return Err(SkateError::InvalidIndex(Fr::from(index as u64), 1 << proof.len()));
+    }
+    let mut hash = hash_task_state(task_id, state);
+    for i in 0..proof.len() {
+        if (index >> i) & 1 == 1 {
+            hash = hash_2_to_1(proof[i], hash);
+        } else {
+            hash = hash_2_to_1(hash, proof[i]);
         }
     }
-    proof
+    Ok(hash)
 }
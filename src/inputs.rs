@@ -0,0 +1,311 @@
+use crate::error::{Result, SkateError};
+use crate::graph::{RoadMapWitness, TableEntry};
+use crate::hashes::TASK_STATE_DEPTH;
+use crate::lifecycle::TaskStateWitness;
+use crate::Skate::{check_length, prerequisite_values, Robot, SkateParameters, Task, MAX_PREREQUISITES};
+use ark_bn254::Fr;
+
+/* Inputs of the rollups (rollup::RollupCircuit), named after their signals. Multi-dimensional signals
+   are flattened row by row. The names and sizes below are the only mapping between the library types
+   and the signals, every round is checked against it (see check) before it is read into a witness.
+   The signals are given in the order below */
+
+// Signals of the rollup
+pub const ROLLUP_SIGNALS: [&str; 29] = [
+    "robots_id",
+    "robots_capabilities",
+    "robots_battery",
+    "robots_capacity",
+    "robots_station",
+    "robots_workload",
+    "old_robots_tasks_id",
+    "old_robots_positions",
+    "old_robots_root",
+    "tasks_id",
+    "tasks_positions",
+    "tasks_priority",
+    "tasks_deadline",
+    "tasks_requirements",
+    "tasks_team",
+    "tasks_prerequisites",
+    "tasks_root",
+    "round",
+    "seed",
+    "task_state_root",
+    "prerequisites_state",
+    "prerequisites_path",
+    "robots_task_leaf",
+    "robots_task_state",
+    "robots_task_path",
+    "tasks_state_path",
+    "fairness_penalty",
+    "workload_cap",
+    "new_task_state_root",
+];
+
+// Signals only used by the rollup with the road map
+pub const ROAD_MAP_SIGNALS: [&str; 10] = [
+    "road_map_root",
+    "travel_times",
+    "travel_paths",
+    "travel_indexes",
+    "return_times",
+    "return_paths",
+    "return_indexes",
+    "charge_times",
+    "charge_paths",
+    "charge_indexes",
+];
+
+// Number of values of a signal for n robots, m tasks and a distance table of the given depth
+fn signal_length(name: &str, n: usize, m: usize, depth: usize) -> Option<usize> {
+    Some(match name {
+        "robots_id" | "robots_capabilities" | "robots_battery" | "robots_capacity"
+        | "robots_workload" => n,
+        "robots_station" => n * 3,
+        "old_robots_tasks_id" => n * 2,
+        "old_robots_positions" => n * 2 * 3,
+        "tasks_id" | "tasks_priority" | "tasks_deadline" | "tasks_requirements" | "tasks_team" => m,
+        "tasks_positions" => m * 3,
+        "tasks_prerequisites" | "prerequisites_state" => m * MAX_PREREQUISITES,
+        "prerequisites_path" => m * MAX_PREREQUISITES * TASK_STATE_DEPTH,
+        "robots_task_leaf" | "robots_task_state" => n,
+        "robots_task_path" => n * TASK_STATE_DEPTH,
+        "tasks_state_path" => m * TASK_STATE_DEPTH,
+        "old_robots_root" | "tasks_root" | "round" | "seed" | "task_state_root" | "fairness_penalty"
+        | "workload_cap" | "new_task_state_root" | "road_map_root" => 1,
+        "travel_times" | "travel_indexes" | "return_times" | "return_indexes" => n * m,
+        "travel_paths" | "return_paths" => n * m * depth,
+        "charge_times" | "charge_indexes" => n,
+        "charge_paths" => n * depth,
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RollupInputs {
+    signals: Vec<(&'static str, Vec<Fr>)>,
+}
+
+impl RollupInputs {
+    fn push(&mut self, name: &'static str, values: Vec<Fr>) {
+        self.signals.push((name, values));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Vec<Fr>> {
+        self.signals
+            .iter()
+            .find(|(signal, _)| *signal == name)
+            .map(|(_, values)| values)
+    }
+
+    pub fn signals(&self) -> &Vec<(&'static str, Vec<Fr>)> {
+        &self.signals
+    }
+
+    // Inputs of the round once the robots and tasks are set: the public parameters and the reads and
+    // writes of the task-state tree (see lifecycle::task_state_witness)
+    pub fn with_round(
+        mut self,
+        tasks: &[Task],
+        round: Fr,
+        parameters: &SkateParameters,
+        task_state: &TaskStateWitness,
+    ) -> Result<RollupInputs> {
+        check_length(task_state.prerequisites.len(), tasks.len())?;
+        check_length(task_state.tasks.len(), tasks.len())?;
+        self.push("round", vec![round]);
+        self.push("seed", vec![Fr::from(parameters.seed)]);
+        self.push("task_state_root", vec![task_state.root]);
+        self.push(
+            "prerequisites_state",
+            task_state
+                .prerequisites
+                .iter()
+                .flat_map(|slots| slots.iter().map(|(state, _)| *state))
+                .collect(),
+        );
+        self.push(
+            "prerequisites_path",
+            task_state
+                .prerequisites
+                .iter()
+                .flat_map(|slots| slots.iter().flat_map(|(_, path)| path.clone()))
+                .collect(),
+        );
+        self.push("robots_task_leaf", task_state.robots.iter().map(|(leaf, _, _)| *leaf).collect());
+        self.push("robots_task_state", task_state.robots.iter().map(|(_, state, _)| *state).collect());
+        self.push(
+            "robots_task_path",
+            task_state.robots.iter().flat_map(|(_, _, path)| path.clone()).collect(),
+        );
+        self.push("tasks_state_path", task_state.tasks.concat());
+        self.push("fairness_penalty", vec![Fr::from(parameters.fairness_penalty)]);
+        self.push("workload_cap", vec![Fr::from(parameters.workload_cap)]);
+        self.push("new_task_state_root", vec![task_state.new_root]);
+        Ok(self)
+    }
+
+    // Inputs of the rollup with the road map: the travel time used for each (robot, task) pair with its Merkle proof
+    // in the committed distance table, and the travel times to the charging stations (see
+    // graph::road_map_witness)
+    pub fn with_road_map(mut self, road_map: &RoadMapWitness) -> RollupInputs {
+        let returns: Vec<TableEntry> = road_map.returns.concat();
+        self.push("road_map_root", vec![road_map.root]);
+        for (names, entries) in [
+            (["travel_times", "travel_paths", "travel_indexes"], &road_map.travel.concat()),
+            (["return_times", "return_paths", "return_indexes"], &returns),
+            (["charge_times", "charge_paths", "charge_indexes"], &road_map.charges),
+        ] {
+            self.push(names[0], entries.iter().map(|entry| entry.travel_time).collect());
+            self.push(names[1], entries.iter().flat_map(|entry| entry.path.clone()).collect());
+            self.push(names[2], entries.iter().map(|entry| Fr::from(entry.index as u64)).collect());
+        }
+        self
+    }
+
+    // Every signal of the rollup (and of the road map with a distance table of the given depth) is
+    // given once with its size, and no other signal is given
+    pub fn check(&self, n: usize, m: usize, road_map_depth: Option<usize>) -> Result<()> {
+        let mut expected = ROLLUP_SIGNALS.to_vec();
+        if road_map_depth.is_some() {
+            expected.extend(ROAD_MAP_SIGNALS);
+        }
+        check_length(self.signals.len(), expected.len())?;
+        for name in expected {
+            let values = self
+                .get(name)
+                .ok_or(SkateError::Proof(format!("missing input {}", name)))?;
+            let length = signal_length(name, n, m, road_map_depth.unwrap_or(0)).unwrap();
+            check_length(values.len(), length)?;
+        }
+        Ok(())
+    }
+}
+
+// Robots and tasks of the round with the roots they are committed in, see RollupInputs::with_round
+// for the other inputs
+pub fn build_rollup_inputs(robots: &[Robot], tasks: &[Task], old_root: Fr, task_root: Fr) -> RollupInputs {
+    let mut inputs = RollupInputs::default();
+    inputs.push("robots_id", robots.iter().map(|robot| robot.robot_id).collect());
+    inputs.push("robots_capabilities", robots.iter().map(|robot| robot.capabilities).collect());
+    inputs.push("robots_battery", robots.iter().map(|robot| robot.battery).collect());
+    inputs.push("robots_capacity", robots.iter().map(|robot| robot.capacity).collect());
+    inputs.push(
+        "robots_station",
+        robots.iter().flat_map(|robot| robot.station.coordinates()).collect(),
+    );
+    inputs.push("robots_workload", robots.iter().map(|robot| robot.workload).collect());
+    inputs.push(
+        "old_robots_tasks_id",
+        robots
+            .iter()
+            .flat_map(|robot| robot.list_tasks.map(|task| task.task_id))
+            .collect(),
+    );
+    inputs.push(
+        "old_robots_positions",
+        robots
+            .iter()
+            .flat_map(|robot| robot.list_tasks.map(|task| task.position.coordinates()))
+            .flatten()
+            .collect(),
+    );
+    inputs.push("old_robots_root", vec![old_root]);
+    inputs.push("tasks_id", tasks.iter().map(|task| task.task_id).collect());
+    inputs.push(
+        "tasks_positions",
+        tasks.iter().flat_map(|task| task.position.coordinates()).collect(),
+    );
+    inputs.push("tasks_priority", tasks.iter().map(|task| task.priority).collect());
+    inputs.push("tasks_deadline", tasks.iter().map(|task| task.deadline).collect());
+    inputs.push("tasks_requirements", tasks.iter().map(|task| task.requirements).collect());
+    inputs.push("tasks_team", tasks.iter().map(|task| task.team_size).collect());
+    inputs.push("tasks_prerequisites", tasks.iter().flat_map(prerequisite_values).collect());
+    inputs.push("tasks_root", vec![task_root]);
+    inputs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::{task_state_witness, TaskState, TaskStateWitness};
+    use crate::position::Position;
+    use crate::Skate::{create_robot_with_battery, create_task, IDLE_TASK_ID};
+
+    fn round() -> (Vec<Robot>, Vec<Task>, TaskStateWitness, RollupInputs) {
+        let position = |i: u64| Position::new(10 * i + 1, 10 * i + 2, 10 * i + 3).unwrap();
+        let robots: Vec<Robot> = (0..3u64)
+            .map(|i| {
+                create_robot_with_battery(
+                    Fr::from(i),
+                    Fr::from(0),
+                    [Fr::from(IDLE_TASK_ID); 2],
+                    [position(2 * i), position(2 * i + 1)],
+                    Fr::from(100),
+                    position(20 + i),
+                )
+                .unwrap()
+            })
+            .collect();
+        let tasks: Vec<Task> = (0..2u64).map(|j| create_task(Fr::from(j), position(30 + j)).unwrap()).collect();
+        let states = (0..2u64).map(|j| (Fr::from(j), TaskState::Pending.value())).collect();
+        let task_state = task_state_witness(&states, &robots, &tasks, &vec![None; 3]).unwrap();
+        let inputs = build_rollup_inputs(&robots, &tasks, Fr::from(0), Fr::from(0))
+            .with_round(&tasks, Fr::from(1), &SkateParameters::default(), &task_state)
+            .unwrap();
+        (robots, tasks, task_state, inputs)
+    }
+
+    #[test]
+    fn signals_are_given_in_order_with_their_sizes() {
+        let (n, m, depth) = (3, 2, 4);
+        let (_, _, _, inputs) = round();
+        let graph_inputs = inputs.clone().with_road_map(&RoadMapWitness::blank(n, m, depth));
+        for (given, signals) in [
+            (&inputs, ROLLUP_SIGNALS.to_vec()),
+            (&graph_inputs, [ROLLUP_SIGNALS.as_slice(), ROAD_MAP_SIGNALS.as_slice()].concat()),
+        ] {
+            let names: Vec<&str> = given.signals().iter().map(|(name, _)| *name).collect();
+            assert_eq!(names, signals);
+            for (name, values) in given.signals() {
+                assert_eq!(signal_length(name, n, m, depth), Some(values.len()), "{}", name);
+            }
+        }
+        inputs.check(n, m, None).unwrap();
+        graph_inputs.check(n, m, Some(depth)).unwrap();
+        // A missing or extra signal, or a table of another depth, is rejected
+        assert!(inputs.check(n, m, Some(depth)).is_err());
+        assert!(graph_inputs.check(n, m, None).is_err());
+        assert!(graph_inputs.check(n, m, Some(depth - 1)).is_err());
+        assert!(inputs.check(n, m + 1, None).is_err());
+    }
+
+    #[test]
+    fn inputs_are_flattened_row_by_row() {
+        let (robots, tasks, task_state, inputs) = round();
+        inputs.check(3, 2, None).unwrap();
+
+        // Each value at its row-major index
+        let positions = inputs.get("old_robots_positions").unwrap();
+        let stations = inputs.get("robots_station").unwrap();
+        for i in 0..3 {
+            for c in 0..3 {
+                for k in 0..2 {
+                    assert_eq!(positions[(i * 2 + k) * 3 + c], robots[i].list_tasks[k].position.coordinates()[c]);
+                }
+                assert_eq!(stations[i * 3 + c], robots[i].station.coordinates()[c]);
+            }
+        }
+        let tasks_positions = inputs.get("tasks_positions").unwrap();
+        let paths = inputs.get("robots_task_path").unwrap();
+        for j in 0..2 {
+            for c in 0..3 {
+                assert_eq!(tasks_positions[j * 3 + c], tasks[j].position.coordinates()[c]);
+            }
+        }
+        for i in 0..3 {
+            assert_eq!(paths[i * TASK_STATE_DEPTH..(i + 1) * TASK_STATE_DEPTH], task_state.robots[i].2[..]);
+        }
+    }
+}
@@ -1,4 +1,24 @@
 pub mod Skate;
+pub mod aggregation;
 mod anemoi_2_to_1;
 mod anemoi_4_to_1;
+pub mod error;
+#[cfg(feature = "ethereum")]
+pub mod ethereum;
+pub mod gadgets;
+pub mod graph;
 pub mod hashes;
+pub mod inputs;
+pub mod lifecycle;
+pub mod metrics;
+pub mod plonk;
+pub mod position;
+pub mod proof_system;
+pub mod prover;
+pub mod registry;
+pub mod rollup;
+pub mod setup;
+pub mod simulator;
+pub mod snarkjs;
+pub mod verifying_key;
+pub mod zkey;
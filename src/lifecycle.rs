@@ -0,0 +1,585 @@
+use crate::error::{Result, SkateError};
+use crate::hashes::{get_merkle_proof_from_id, task_state_root, task_state_slot, TASK_STATE_DEPTH};
+use crate::Skate::{check_length, is_expired, to_index, Robot, Task, IDLE_TASK_ID, MAX_PREREQUISITES};
+use ark_bn254::Fr;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/* Life of a task in the coordinator:
+    pending -> assigned (by a round) -> in progress (reported by the robot) -> done or failed
+   An assigned or in progress task goes back to pending when it is abandoned: its robot reports a
+   failure or stays silent for `requeue_after` rounds. After `max_attempts` attempts, or once its
+   deadline has passed, a task is failed for good. A robot holding a task is busy and gets no other one
+   until its task is over (see busy_robots), the rounds prove it from the task-state root.
+   A coalition task is held by its whole team: it is abandoned as soon as one of its robots leaves it.
+   A task waits in pending until its prerequisites are done, and fails with any of them. A task over
+   stays in the task-state tree until a new task needs its slot (see TaskLedger::add) */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Pending,
+    Assigned,
+    InProgress,
+    Done,
+    Failed,
+}
+
+impl TaskState {
+    // Value committed in the task-state tree (see hashes::task_state_root)
+    pub fn value(&self) -> Fr {
+        match self {
+            TaskState::Pending => Fr::from(0),
+            TaskState::Assigned => Fr::from(1),
+            TaskState::InProgress => Fr::from(2),
+            TaskState::Done => Fr::from(3),
+            TaskState::Failed => Fr::from(4),
+        }
+    }
+
+    pub fn is_final(&self) -> bool {
+        *self == TaskState::Done || *self == TaskState::Failed
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LedgerEntry {
+    pub task: Task,
+    pub state: TaskState,
+    pub robot_ids: Vec<Fr>, // robots holding the task when assigned or in progress
+    pub updated: u64,         // round of the last change of state
+    pub attempts: u32,
+}
+
+// Tasks the coordinator keeps track of, by ledger id. The ledger id becomes the task id: ids increase
+// with the arrival order and are never reused. The slots of the task-state tree are: a done or failed
+// task is retired when a new task needs its slot (see add), its id is then unknown to the ledger
+#[derive(Debug, Clone)]
+pub struct TaskLedger {
+    entries: BTreeMap<usize, LedgerEntry>,
+    next_id: usize,
+    requeue_after: u64,
+    max_attempts: u32,
+}
+
+impl TaskLedger {
+    pub fn new(requeue_after: u64, max_attempts: u32) -> TaskLedger {
+        TaskLedger {
+            entries: BTreeMap::new(),
+            next_id: 0,
+            requeue_after,
+            max_attempts,
+        }
+    }
+
+    // The task gets the next id whose slot (hashes::task_state_slot) is free or held by a retirable task,
+    // which is retired. Ids whose slot is still in use are skipped, an error if every slot is
+    pub fn add(&mut self, task: Task, round: u64) -> Result<usize> {
+        let slots = 1 << TASK_STATE_DEPTH;
+        for id in self.next_id..self.next_id + slots {
+            let slot = task_state_slot(Fr::from(id as u64));
+            let occupant = self
+                .entries
+                .keys()
+                .copied()
+                .find(|other| task_state_slot(Fr::from(*other as u64)) == slot);
+            if let Some(occupant) = occupant {
+                if !self.is_retirable(occupant) {
+                    continue;
+                }
+                self.entries.remove(&occupant);
+            }
+            self.entries.insert(
+                id,
+                LedgerEntry {
+                    task: Task {
+                        task_id: Fr::from(id as u64),
+                        ..task
+                    },
+                    state: TaskState::Pending,
+                    robot_ids: vec![],
+                    updated: round,
+                    attempts: 0,
+                },
+            );
+            self.next_id = id + 1;
+            return Ok(id);
+        }
+        Err(SkateError::LedgerFull(slots))
+    }
+
+    pub fn entry(&self, id: usize) -> Option<&LedgerEntry> {
+        self.entries.get(&id)
+    }
+
+    // Ledger ids of at most `limit` pending tasks whose prerequisites are done, oldest first
+    pub fn pending(&self, limit: usize) -> Vec<usize> {
+        self.entries
+            .iter()
+            .filter(|(id, entry)| entry.state == TaskState::Pending && self.is_ready(**id))
+            .map(|(id, _)| *id)
+            .take(limit)
+            .collect()
+    }
+
+    pub fn is_ready(&self, id: usize) -> bool {
+        self.prerequisite_states(id)
+            .iter()
+            .all(|state| *state == Some(TaskState::Done))
+    }
+
+    // States of the prerequisites of a task, None for an unknown or retired task
+    fn prerequisite_states(&self, id: usize) -> Vec<Option<TaskState>> {
+        self.entries[&id]
+            .task
+            .prerequisites
+            .iter()
+            .flatten()
+            .map(|prerequisite| {
+                let index = to_index(*prerequisite, self.next_id).ok()?;
+                Some(self.entries.get(&index)?.state)
+            })
+            .collect()
+    }
+
+    // A task leaves the tree once over, unless an unfinished task still waits for it
+    fn is_retirable(&self, id: usize) -> bool {
+        let task_id = Fr::from(id as u64);
+        self.entries[&id].state.is_final()
+            && !self
+                .entries
+                .values()
+                .any(|entry| !entry.state.is_final() && entry.task.prerequisites.contains(&Some(task_id)))
+    }
+
+    // `batch` maps the task indexes of the round to ledger ids, `assignments` come from
+    // Skate::skate_assignments_with_busy on `robots`: a robot still holding a task cannot get another one
+    pub fn assign(
+        &mut self,
+        round: u64,
+        batch: &Vec<usize>,
+        robots: &Vec<Robot>,
+        assignments: &Vec<Option<usize>>,
+    ) -> Result<()> {
+        check_length(assignments.len(), robots.len())?;
+        for (robot, assignment) in robots.iter().zip(assignments) {
+            let Some(k) = assignment else {
+                continue;
+            };
+            let id = *batch
+                .get(*k)
+                .ok_or(SkateError::InvalidIndex(Fr::from(*k as u64), batch.len()))?;
+            if !self.entries.contains_key(&id) {
+                return Err(SkateError::InvalidIndex(Fr::from(id as u64), self.next_id));
+            }
+            if self.held_by(robot.robot_id).is_some() {
+                return Err(SkateError::Busy(robot.robot_id));
+            }
+            let entry = self.entries.get_mut(&id).unwrap();
+            if entry.robot_ids.is_empty() {
+                entry.attempts += 1; // Once for the whole team
+            }
+            entry.state = TaskState::Assigned;
+            entry.robot_ids.push(robot.robot_id);
+            entry.updated = round;
+        }
+        Ok(())
+    }
+
+    // The robot started its task
+    pub fn start(&mut self, robot_id: Fr, round: u64) -> Result<usize> {
+        let id = self.held_by(robot_id).ok_or(SkateError::NoTask(robot_id))?;
+        let entry = self.entries.get_mut(&id).unwrap();
+        entry.state = TaskState::InProgress;
+        entry.updated = round;
+        Ok(id)
+    }
+
+    // The robot finished its task (done, for the whole team) or gave up (the task is re-queued)
+    pub fn complete(&mut self, robot_id: Fr, done: bool, round: u64) -> Result<usize> {
+        let id = self.held_by(robot_id).ok_or(SkateError::NoTask(robot_id))?;
+        if done {
+            let entry = self.entries.get_mut(&id).unwrap();
+            entry.state = TaskState::Done;
+            entry.robot_ids.clear();
+            entry.updated = round;
+        } else {
+            self.abandon(id, round);
+        }
+        Ok(id)
+    }
+
+    // Called before each round: silent robots lose their task, expired tasks fail, and so do the tasks
+    // waiting for a failed, unknown or retired prerequisite. Returns the ledger ids of the re-queued tasks
+    pub fn requeue_abandoned(&mut self, round: u64) -> Result<Vec<usize>> {
+        let mut requeued = vec![];
+        let ids: Vec<usize> = self.entries.keys().copied().collect();
+        for id in ids {
+            let entry = &self.entries[&id];
+            let held = entry.state == TaskState::Assigned || entry.state == TaskState::InProgress;
+            if held && round >= entry.updated + self.requeue_after {
+                self.abandon(id, round);
+                if self.entries[&id].state == TaskState::Pending {
+                    requeued.push(id);
+                }
+            }
+            let blocked = self
+                .prerequisite_states(id)
+                .iter()
+                .any(|state| state.map_or(true, |state| state == TaskState::Failed));
+            let entry = self.entries.get_mut(&id).unwrap();
+            if entry.state == TaskState::Pending && (blocked || is_expired(&entry.task, Fr::from(round))?) {
+                entry.state = TaskState::Failed;
+                entry.updated = round;
+                requeued.retain(|r| *r != id);
+            }
+        }
+        Ok(requeued)
+    }
+
+    // Leaves of the task-state tree: (ledger id, state) for every task of the ledger
+    pub fn states(&self) -> Vec<(Fr, Fr)> {
+        self.entries
+            .iter()
+            .map(|(id, entry)| (Fr::from(*id as u64), entry.state.value()))
+            .collect()
+    }
+
+    pub fn root(&self) -> Result<Fr> {
+        Ok(task_state_root(&self.states())?.0)
+    }
+
+    fn held_by(&self, robot_id: Fr) -> Option<usize> {
+        self.entries
+            .iter()
+            .find(|(_, entry)| entry.robot_ids.contains(&robot_id) && !entry.state.is_final())
+            .map(|(id, _)| *id)
+    }
+
+    fn abandon(&mut self, id: usize, round: u64) {
+        let max_attempts = self.max_attempts;
+        let entry = self.entries.get_mut(&id).unwrap();
+        entry.robot_ids.clear();
+        entry.updated = round;
+        entry.state = if entry.attempts >= max_attempts {
+            TaskState::Failed
+        } else {
+            TaskState::Pending
+        };
+    }
+}
+
+/* The rounds prove their reads and writes of the task-state tree (see rollup::task_state_transition), from the
+   leaves (task id, state) of TaskLedger::states before the round, each at the slot of its id
+   (hashes::task_state_slot):
+    - the prerequisites of the tasks are done
+    - a robot whose current task is assigned or in progress is busy: it keeps its task. Its task is not in
+      the tree once retired, the leaf of its slot is then the one of another task or the empty leaf, as
+      for the charge and idle waypoints (Skate::CHARGE_TASK_ID, Skate::IDLE_TASK_ID)
+    - the tasks of the round go from pending to assigned if they got their robots, one leaf after the
+      other, which gives the task-state root after the round (see TaskLedger::assign)
+   The states themselves come from the reports of the robots, the coordinator attests them */
+
+// Witness of the task-state tree for a round
+#[derive(Debug, Clone)]
+pub struct TaskStateWitness {
+    pub root: Fr,
+    // (state, path) of each prerequisite slot of each task, a zero state and path for an empty slot
+    pub prerequisites: Vec<[(Fr, Vec<Fr>); MAX_PREREQUISITES]>,
+    // (task id, state, path) of the leaf at the slot of the current task of each robot
+    pub robots: Vec<(Fr, Fr, Vec<Fr>)>,
+    // Path of each task of the round in the tree where the previous tasks are already updated
+    pub tasks: Vec<Vec<Fr>>,
+    pub new_root: Fr,
+}
+
+fn state_of(states: &Vec<(Fr, Fr)>, task_id: Fr) -> Result<Fr> {
+    states
+        .iter()
+        .find(|(id, _)| *id == task_id)
+        .map(|(_, state)| *state)
+        .ok_or(SkateError::NotInTree(task_id))
+}
+
+// Leaf (task id, state) at the slot of a task, the empty leaf (see hashes::empty_task_state_leaf) if none
+fn leaf_at(states: &Vec<(Fr, Fr)>, task_id: Fr) -> (Fr, Fr) {
+    leaf_in_slot(states, task_state_slot(task_id))
+}
+
+fn leaf_in_slot(states: &[(Fr, Fr)], slot: usize) -> (Fr, Fr) {
+    states
+        .iter()
+        .find(|(id, _)| task_state_slot(*id) == slot)
+        .copied()
+        .unwrap_or((Fr::from(IDLE_TASK_ID), TaskState::Pending.value()))
+}
+
+fn slot_path(task_id: Fr, levels: &Vec<Vec<Fr>>) -> Result<Vec<Fr>> {
+    get_merkle_proof_from_id(Fr::from(task_state_slot(task_id) as u64), levels)
+}
+
+// A robot is busy if the leaf at the slot of its current task is this task, assigned or in progress
+pub fn is_busy(task_id: Fr, leaf: (Fr, Fr)) -> bool {
+    leaf.0 == task_id && [TaskState::Assigned.value(), TaskState::InProgress.value()].contains(&leaf.1)
+}
+
+// Robots whose current task is assigned or in progress in `states`
+pub fn busy_robots(states: &Vec<(Fr, Fr)>, robots: &Vec<Robot>) -> Result<Vec<bool>> {
+    Ok(robots
+        .iter()
+        .map(|robot| {
+            let task_id = robot.list_tasks[1].task_id;
+            is_busy(task_id, leaf_at(states, task_id))
+        })
+        .collect())
+}
+
+// Leaf replaced at a slot of the task-state tree, with the path of the slot once the previous changes are made
+#[derive(Debug, Clone)]
+pub struct TaskStateChange {
+    pub slot: usize,
+    pub old: (Fr, Fr),
+    pub new: (Fr, Fr),
+    pub path: Vec<Fr>,
+}
+
+// Leaves changed from the tree of `from` to the one of `to` (new tasks, reports, retired tasks), by slot.
+// The robots replay them from the root of the previous round up to the root of the next one
+// (see hashes::replace_task_state)
+pub fn task_state_changes(from: &[(Fr, Fr)], to: &[(Fr, Fr)]) -> Result<Vec<TaskStateChange>> {
+    let mut current = from.to_vec();
+    let mut changes = vec![];
+    for slot in 0..1 << TASK_STATE_DEPTH {
+        let (old, new) = (leaf_in_slot(&current, slot), leaf_in_slot(to, slot));
+        if old == new {
+            continue;
+        }
+        let path = get_merkle_proof_from_id(Fr::from(slot as u64), &task_state_root(&current)?.1)?;
+        current.retain(|(id, _)| task_state_slot(*id) != slot);
+        if new.0 != Fr::from(IDLE_TASK_ID) {
+            current.push(new);
+        }
+        changes.push(TaskStateChange { slot, old, new, path });
+    }
+    Ok(changes)
+}
+
+// `assignments` are those of the round, from Skate::skate_assignments_with_busy. The tasks of the round
+// must be pending in `states`
+pub fn task_state_witness(
+    states: &Vec<(Fr, Fr)>,
+    robots: &Vec<Robot>,
+    tasks: &Vec<Task>,
+    assignments: &Vec<Option<usize>>,
+) -> Result<TaskStateWitness> {
+    check_length(assignments.len(), robots.len())?;
+    let (root, levels) = task_state_root(states)?;
+    let zero = || (Fr::from(0), vec![Fr::from(0); TASK_STATE_DEPTH]);
+
+    let mut prerequisites = vec![];
+    for task in tasks {
+        let mut proofs: [(Fr, Vec<Fr>); MAX_PREREQUISITES] = std::array::from_fn(|_| zero());
+        for (slot, prerequisite) in task.prerequisites.iter().enumerate() {
+            if let Some(prerequisite) = prerequisite {
+                proofs[slot] = (state_of(states, *prerequisite)?, slot_path(*prerequisite, &levels)?);
+            }
+        }
+        prerequisites.push(proofs);
+    }
+
+    let mut robot_proofs = vec![];
+    for robot in robots {
+        let task_id = robot.list_tasks[1].task_id;
+        let (leaf_id, state) = leaf_at(states, task_id);
+        robot_proofs.push((leaf_id, state, slot_path(task_id, &levels)?));
+    }
+
+    let mut updated = states.clone();
+    let mut paths = vec![];
+    for (k, task) in tasks.iter().enumerate() {
+        let index = updated
+            .iter()
+            .position(|(id, _)| *id == task.task_id)
+            .ok_or(SkateError::NotInTree(task.task_id))?;
+        if updated[index].1 != TaskState::Pending.value() {
+            return Err(SkateError::NotPending(task.task_id));
+        }
+        paths.push(slot_path(task.task_id, &task_state_root(&updated)?.1)?);
+        if assignments.contains(&Some(k)) {
+            updated[index].1 = TaskState::Assigned.value();
+        }
+    }
+
+    Ok(TaskStateWitness {
+        root,
+        prerequisites,
+        robots: robot_proofs,
+        tasks: paths,
+        new_root: task_state_root(&updated)?.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashes::replace_task_state;
+    use crate::position::Position;
+    use crate::Skate::{create_prioritized_task, create_robot, create_task, create_task_with_prerequisites};
+
+    fn task() -> Task {
+        create_task(Fr::from(0), Position::default()).unwrap()
+    }
+
+    fn robot(i: u64) -> Robot {
+        create_robot(Fr::from(i), [Fr::from(IDLE_TASK_ID); 2], [Position::default(); 2]).unwrap()
+    }
+
+    // Assigns a task of the ledger to a robot alone
+    fn give(ledger: &mut TaskLedger, id: usize, robot: u64, round: u64) {
+        ledger.assign(round, &vec![id], &vec![self::robot(robot)], &vec![Some(0)]).unwrap();
+    }
+
+    fn state(ledger: &TaskLedger, id: usize) -> TaskState {
+        ledger.entry(id).unwrap().state
+    }
+
+    #[test]
+    fn silent_robots_lose_their_task() {
+        let mut ledger = TaskLedger::new(3, 5);
+        let id = ledger.add(task(), 0).unwrap();
+        give(&mut ledger, id, 0, 1);
+        assert_eq!(ledger.pending(6), Vec::<usize>::new());
+        assert_eq!(ledger.requeue_abandoned(3).unwrap(), Vec::<usize>::new());
+        ledger.start(Fr::from(0), 3).unwrap();
+        // Silent for 3 rounds since it started
+        assert_eq!(ledger.requeue_abandoned(5).unwrap(), Vec::<usize>::new());
+        assert_eq!(ledger.requeue_abandoned(6).unwrap(), vec![id]);
+        assert_eq!(state(&ledger, id), TaskState::Pending);
+        assert_eq!(ledger.entry(id).unwrap().attempts, 1);
+        assert_eq!(ledger.start(Fr::from(0), 6), Err(SkateError::NoTask(Fr::from(0))));
+    }
+
+    #[test]
+    fn abandoned_tasks_fail_after_max_attempts() {
+        let mut ledger = TaskLedger::new(10, 2);
+        let id = ledger.add(task(), 0).unwrap();
+        give(&mut ledger, id, 0, 1);
+        assert_eq!(ledger.complete(Fr::from(0), false, 2).unwrap(), id);
+        assert_eq!(state(&ledger, id), TaskState::Pending);
+        give(&mut ledger, id, 1, 3);
+        ledger.complete(Fr::from(1), false, 4).unwrap();
+        assert_eq!(state(&ledger, id), TaskState::Failed);
+        assert_eq!(ledger.pending(6), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn robots_hold_one_task_at_a_time() {
+        let mut ledger = TaskLedger::new(10, 2);
+        let (first, second) = (ledger.add(task(), 0).unwrap(), ledger.add(task(), 0).unwrap());
+        give(&mut ledger, first, 0, 1);
+        let busy = ledger.assign(1, &vec![second], &vec![robot(0)], &vec![Some(0)]);
+        assert_eq!(busy, Err(SkateError::Busy(Fr::from(0))));
+        ledger.complete(Fr::from(0), true, 2).unwrap();
+        assert_eq!(state(&ledger, first), TaskState::Done);
+        give(&mut ledger, second, 0, 3);
+    }
+
+    #[test]
+    fn expired_tasks_fail() {
+        let mut ledger = TaskLedger::new(10, 2);
+        let id = ledger
+            .add(create_prioritized_task(Fr::from(0), Position::default(), Fr::from(0), Fr::from(4)).unwrap(), 0)
+            .unwrap();
+        ledger.requeue_abandoned(4).unwrap();
+        assert_eq!(state(&ledger, id), TaskState::Pending);
+        ledger.requeue_abandoned(5).unwrap();
+        assert_eq!(state(&ledger, id), TaskState::Failed);
+    }
+
+    #[test]
+    fn tasks_wait_for_their_prerequisites() {
+        let mut ledger = TaskLedger::new(10, 1);
+        let (a, b) = (ledger.add(task(), 0).unwrap(), ledger.add(task(), 0).unwrap());
+        let after = |ledger: &mut TaskLedger, prerequisite: usize| {
+            let prerequisites = [Some(Fr::from(prerequisite as u64)), None];
+            let task = create_task_with_prerequisites(Fr::from(10), Position::default(), prerequisites).unwrap();
+            ledger.add(task, 0).unwrap()
+        };
+        let (after_a, after_b) = (after(&mut ledger, a), after(&mut ledger, b));
+        assert_eq!(ledger.pending(6), vec![a, b]);
+
+        give(&mut ledger, a, 0, 1);
+        ledger.complete(Fr::from(0), true, 2).unwrap();
+        assert_eq!(ledger.pending(6), vec![b, after_a]);
+        // A single attempt: b fails, and the task waiting for it with it
+        give(&mut ledger, b, 1, 3);
+        ledger.complete(Fr::from(1), false, 4).unwrap();
+        ledger.requeue_abandoned(5).unwrap();
+        assert_eq!(state(&ledger, after_b), TaskState::Failed);
+        assert_eq!(ledger.pending(6), vec![after_a]);
+    }
+
+    #[test]
+    fn finished_tasks_free_their_slot() {
+        let slots = 1 << TASK_STATE_DEPTH;
+        let mut ledger = TaskLedger::new(10, 1);
+        for id in 0..slots {
+            assert_eq!(ledger.add(task(), 0).unwrap(), id);
+        }
+        assert_eq!(ledger.add(task(), 0), Err(SkateError::LedgerFull(slots)));
+
+        // Task 3 is done, the next id of its slot takes its place
+        give(&mut ledger, 3, 0, 1);
+        ledger.complete(Fr::from(0), true, 2).unwrap();
+        assert_eq!(ledger.add(task(), 2).unwrap(), slots + 3);
+        assert!(ledger.entry(3).is_none());
+        assert_eq!(ledger.entry(slots + 3).unwrap().task.task_id, Fr::from((slots + 3) as u64));
+        assert_eq!(ledger.states().len(), slots);
+        assert!(ledger.root().is_ok());
+        assert_eq!(ledger.add(task(), 2), Err(SkateError::LedgerFull(slots)));
+    }
+
+    #[test]
+    fn prerequisites_keep_their_slot() {
+        let slots = 1 << TASK_STATE_DEPTH;
+        let mut ledger = TaskLedger::new(10, 1);
+        let done = ledger.add(task(), 0).unwrap();
+        give(&mut ledger, done, 0, 1);
+        ledger.complete(Fr::from(0), true, 2).unwrap();
+        let prerequisites = [Some(Fr::from(done as u64)), None];
+        let waiting = create_task_with_prerequisites(Fr::from(10), Position::default(), prerequisites).unwrap();
+        let waiting = ledger.add(waiting, 2).unwrap();
+        for _ in 2..slots {
+            ledger.add(task(), 2).unwrap();
+        }
+        // The done task is still a prerequisite: no slot is free
+        assert_eq!(ledger.add(task(), 3), Err(SkateError::LedgerFull(slots)));
+        ledger.requeue_abandoned(3).unwrap();
+        assert_eq!(state(&ledger, waiting), TaskState::Pending);
+        assert!(ledger.pending(slots).contains(&waiting));
+    }
+
+    #[test]
+    fn task_state_changes_lead_to_the_next_root() {
+        let mut ledger = TaskLedger::new(10, 1);
+        let first = ledger.add(task(), 0).unwrap();
+        ledger.add(task(), 0).unwrap();
+        let from = ledger.states();
+        give(&mut ledger, first, 0, 1);
+        ledger.complete(Fr::from(0), true, 2).unwrap();
+        ledger.add(task(), 2).unwrap();
+
+        let changes = task_state_changes(&from, &ledger.states()).unwrap();
+        assert_eq!(changes.iter().map(|change| change.slot).collect::<Vec<_>>(), vec![0, 2]);
+        let mut root = task_state_root(&from).unwrap().0;
+        for change in &changes {
+            root = replace_task_state(root, change.slot, change.old, change.new, &change.path).unwrap().unwrap();
+        }
+        assert_eq!(root, ledger.root().unwrap());
+
+        // A change of a leaf which is not the one under the root
+        let forged = &changes[0];
+        let old = (forged.old.0, TaskState::Assigned.value());
+        let start = task_state_root(&from).unwrap().0;
+        assert_eq!(replace_task_state(start, forged.slot, old, forged.new, &forged.path).unwrap(), None);
+    }
+}
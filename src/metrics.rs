@@ -0,0 +1,378 @@
+use crate::error::Result;
+use crate::graph::DistanceTable;
+use crate::Skate::{check_length, distance, is_feasible, team_size, Robot, SkateParameters, Task};
+use ark_ff::PrimeField;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+/* Quality of the assignment of one round:
+    - the travel costs (distance metric of the round, without the priority term) of the assigned pairs
+    - the tasks and robots left without a pair
+    - the optimum of the Hungarian algorithm on the same robots, tasks and compatibilities, a coalition
+      task being one column per robot of its team (partial teams are allowed there)
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RoundMetrics {
+    pub round: u64,
+    pub robots: usize,
+    pub tasks: usize,
+    pub total_cost: u64,
+    pub max_cost: u64,
+    pub unassigned_tasks: usize,
+    pub idle_robots: usize,
+    pub optimal_cost: u64,
+    pub optimal_assigned: usize, // pairs of the optimum, it assigns as many compatible pairs as possible
+}
+
+impl RoundMetrics {
+    // Robots with a task, coalition tasks count once per robot
+    pub fn assigned(&self) -> usize {
+        self.robots - self.idle_robots
+    }
+
+    // Relative gap to the optimum, none when the optimum assigns more pairs: the costs are then not
+    // comparable
+    pub fn gap(&self) -> Option<f64> {
+        if self.assigned() != self.optimal_assigned {
+            None
+        } else if self.optimal_cost == 0 {
+            Some(0f64)
+        } else {
+            Some(self.total_cost as f64 / self.optimal_cost as f64 - 1f64)
+        }
+    }
+}
+
+fn format_gap(gap: Option<f64>) -> String {
+    match gap {
+        Some(gap) => format!("{:+.2}%", gap * 100f64),
+        None => "n/a".to_string(),
+    }
+}
+
+impl Display for RoundMetrics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "round {}: {}/{} tasks assigned to {} robots, {} idle robots, {} unassigned tasks, total cost {} (max {}), optimum {} for {} pairs (gap {})",
+            self.round,
+            self.tasks - self.unassigned_tasks,
+            self.tasks,
+            self.assigned(),
+            self.idle_robots,
+            self.unassigned_tasks,
+            self.total_cost,
+            self.max_cost,
+            self.optimal_cost,
+            self.optimal_assigned,
+            format_gap(self.gap())
+        )
+    }
+}
+
+// Totals over several rounds, to compare allocation algorithms on a whole workload
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Summary {
+    pub rounds: usize,
+    pub total_cost: u64,
+    pub max_cost: u64,
+    pub unassigned_tasks: usize,
+    pub idle_robots: usize,
+    pub optimal_cost: u64,
+    pub compared_rounds: usize, // rounds whose gap is defined, the gap of the summary is over them
+    pub compared_cost: u64,
+    pub compared_optimal_cost: u64,
+}
+
+impl Summary {
+    pub fn new(rounds: &[RoundMetrics]) -> Summary {
+        let mut summary = Summary::default();
+        for round in rounds {
+            summary.rounds += 1;
+            summary.total_cost += round.total_cost;
+            summary.max_cost = summary.max_cost.max(round.max_cost);
+            summary.unassigned_tasks += round.unassigned_tasks;
+            summary.idle_robots += round.idle_robots;
+            summary.optimal_cost += round.optimal_cost;
+            if round.gap().is_some() {
+                summary.compared_rounds += 1;
+                summary.compared_cost += round.total_cost;
+                summary.compared_optimal_cost += round.optimal_cost;
+            }
+        }
+        summary
+    }
+
+    pub fn gap(&self) -> Option<f64> {
+        if self.compared_rounds == 0 {
+            None
+        } else if self.compared_optimal_cost == 0 {
+            Some(0f64)
+        } else {
+            Some(self.compared_cost as f64 / self.compared_optimal_cost as f64 - 1f64)
+        }
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} rounds: total cost {} (max {}), {} unassigned tasks, {} idle robots, optimum {} (gap {} over {} rounds)",
+            self.rounds,
+            self.total_cost,
+            self.max_cost,
+            self.unassigned_tasks,
+            self.idle_robots,
+            self.optimal_cost,
+            format_gap(self.gap()),
+            self.compared_rounds
+        )
+    }
+}
+
+// `assignments` are the task indexes returned by Skate::skate_assignments
+pub fn evaluate(
+    round: u64,
+    robots: &Vec<Robot>,
+    tasks: &Vec<Task>,
+    assignments: &Vec<Option<usize>>,
+    parameters: &SkateParameters,
+    road_map: Option<&DistanceTable>,
+) -> Result<RoundMetrics> {
+    check_length(assignments.len(), robots.len())?;
+
+    // None for an infeasible pair
+    let mut cost = vec![vec![None; tasks.len()]; robots.len()];
+    for i in 0..robots.len() {
+        for j in 0..tasks.len() {
+            if is_feasible(&tasks[j], &robots[i], parameters.metric, road_map)?
+                && !parameters.is_capped(&robots[i])
+            {
+                let d = distance(&tasks[j], &robots[i], parameters.metric, road_map)?;
+                cost[i][j] = Some(d.into_bigint().0[0]);
+            }
+        }
+    }
+
+    let mut metrics = RoundMetrics {
+        round,
+        robots: robots.len(),
+        tasks: tasks.len(),
+        total_cost: 0,
+        max_cost: 0,
+        unassigned_tasks: tasks.len(),
+        idle_robots: 0,
+        optimal_cost: 0,
+        optimal_assigned: 0,
+    };
+    let mut assigned = vec![false; tasks.len()];
+    for i in 0..robots.len() {
+        match assignments[i].and_then(|j| cost[i].get(j).copied().flatten().map(|c| (j, c))) {
+            Some((j, c)) => {
+                metrics.total_cost += c;
+                metrics.max_cost = metrics.max_cost.max(c);
+                assigned[j] = true;
+            }
+            None => metrics.idle_robots += 1,
+        }
+    }
+    metrics.unassigned_tasks = assigned.iter().filter(|a| !**a).count();
+
+    // One column per robot a task needs
+    let columns: Vec<usize> = (0..tasks.len())
+        .flat_map(|j| std::iter::repeat(j).take(team_size(&tasks[j])))
+        .collect();
+    let slots: Vec<Vec<Option<u64>>> = cost
+        .iter()
+        .map(|row| columns.iter().map(|j| row[*j]).collect())
+        .collect();
+    for (i, j) in hungarian(&slots) {
+        if let Some(c) = slots[i][j] {
+            metrics.optimal_cost += c;
+            metrics.optimal_assigned += 1;
+        }
+    }
+
+    Ok(metrics)
+}
+
+/* Minimal cost assignment (Hungarian algorithm with potentials, O(n^2 m)), returns the (robot, task)
+   pairs. Incompatible pairs cost more than all compatible pairs together, so that the optimum first
+   maximizes the number of compatible pairs; the caller drops the incompatible ones */
+fn hungarian(cost: &Vec<Vec<Option<u64>>>) -> Vec<(usize, usize)> {
+    if cost.is_empty() || cost[0].is_empty() {
+        return vec![];
+    }
+    let penalty = 1 + cost.iter().flatten().flatten().sum::<u64>() as i64;
+    let value = |c: Option<u64>| c.map(|c| c as i64).unwrap_or(penalty);
+
+    // Rows must be the smallest dimension
+    let transposed = cost.len() > cost[0].len();
+    let matrix: Vec<Vec<i64>> = if transposed {
+        (0..cost[0].len())
+            .map(|j| (0..cost.len()).map(|i| value(cost[i][j])).collect())
+            .collect()
+    } else {
+        cost.iter()
+            .map(|row| row.iter().map(|c| value(*c)).collect())
+            .collect()
+    };
+
+    let n = matrix.len();
+    let m = matrix[0].len();
+    let mut u = vec![0i64; n + 1];
+    let mut v = vec![0i64; m + 1];
+    let mut p = vec![0usize; m + 1]; // row matched with each column, 0 for none (1-indexed)
+    let mut way = vec![0usize; m + 1];
+    for i in 1..=n {
+        p[0] = i;
+        let mut j0 = 0;
+        let mut minv = vec![i64::MAX; m + 1];
+        let mut used = vec![false; m + 1];
+        loop {
+            used[j0] = true;
+            let i0 = p[j0];
+            let mut delta = i64::MAX;
+            let mut j1 = 0;
+            for j in 1..=m {
+                if !used[j] {
+                    let current = matrix[i0 - 1][j - 1] - u[i0] - v[j];
+                    if current < minv[j] {
+                        minv[j] = current;
+                        way[j] = j0;
+                    }
+                    if minv[j] < delta {
+                        delta = minv[j];
+                        j1 = j;
+                    }
+                }
+            }
+            for j in 0..=m {
+                if used[j] {
+                    u[p[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    minv[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if p[j0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let j1 = way[j0];
+            p[j0] = p[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    (1..=m)
+        .filter(|j| p[*j] != 0)
+        .map(|j| {
+            if transposed {
+                (j - 1, p[j] - 1)
+            } else {
+                (p[j] - 1, j - 1)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::rand::rngs::StdRng;
+    use ark_std::rand::{Rng, SeedableRng};
+
+    // Best (pairs, cost) over every assignment of the rows to distinct columns or to none
+    fn brute_force(cost: &Vec<Vec<Option<u64>>>, row: usize, used: &mut Vec<bool>) -> (usize, u64) {
+        if row == cost.len() {
+            return (0, 0);
+        }
+        let mut best = brute_force(cost, row + 1, used);
+        for j in 0..cost[row].len() {
+            let Some(c) = cost[row][j] else {
+                continue;
+            };
+            if used[j] {
+                continue;
+            }
+            used[j] = true;
+            let (pairs, total) = brute_force(cost, row + 1, used);
+            used[j] = false;
+            if pairs + 1 > best.0 || (pairs + 1 == best.0 && total + c < best.1) {
+                best = (pairs + 1, total + c);
+            }
+        }
+        best
+    }
+
+    // Compatible pairs and their cost, as evaluate keeps them
+    fn optimum(cost: &Vec<Vec<Option<u64>>>) -> (usize, u64) {
+        let pairs = hungarian(cost);
+        let mut rows = vec![false; cost.len()];
+        let mut columns = vec![false; cost[0].len()];
+        for (i, j) in &pairs {
+            assert!(!rows[*i] && !columns[*j], "row or column matched twice");
+            rows[*i] = true;
+            columns[*j] = true;
+        }
+        pairs
+            .iter()
+            .filter_map(|(i, j)| cost[*i][*j])
+            .fold((0, 0), |(pairs, total), c| (pairs + 1, total + c))
+    }
+
+    #[test]
+    fn hungarian_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..500 {
+            let (n, m) = (rng.gen_range(1..=5), rng.gen_range(1..=5));
+            let cost: Vec<Vec<Option<u64>>> = (0..n)
+                .map(|_| {
+                    (0..m)
+                        .map(|_| rng.gen_bool(0.7).then(|| rng.gen_range(0..20)))
+                        .collect()
+                })
+                .collect();
+            assert_eq!(optimum(&cost), brute_force(&cost, 0, &mut vec![false; m]), "{:?}", cost);
+        }
+    }
+
+    #[test]
+    fn hungarian_prefers_more_pairs() {
+        // The cheapest pair (0, 0) would leave robot 1 without a compatible task
+        let cost = vec![vec![Some(0), Some(100)], vec![Some(1), None]];
+        assert_eq!(optimum(&cost), (2, 101));
+    }
+
+    fn metrics(assigned: usize, optimal_assigned: usize) -> RoundMetrics {
+        RoundMetrics {
+            round: 1,
+            robots: 3,
+            tasks: 3,
+            total_cost: 12,
+            max_cost: 6,
+            unassigned_tasks: 3 - assigned,
+            idle_robots: 3 - assigned,
+            optimal_cost: 10,
+            optimal_assigned,
+        }
+    }
+
+    #[test]
+    fn gap_needs_as_many_pairs() {
+        assert!((metrics(2, 2).gap().unwrap() - 0.2).abs() < 1e-9);
+        assert_eq!(metrics(2, 3).gap(), None);
+
+        let summary = Summary::new(&[metrics(2, 2), metrics(2, 3)]);
+        assert_eq!(summary.compared_rounds, 1);
+        assert!((summary.gap().unwrap() - 0.2).abs() < 1e-9);
+        assert_eq!(Summary::new(&[metrics(1, 3)]).gap(), None);
+    }
+}
@@ -0,0 +1,796 @@
+use crate::anemoi_2_to_1::hash_2_to_1;
+use crate::error::{Result, SkateError};
+use crate::verifying_key::to_g1;
+use ark_bn254::{Bn254, Fq, Fr, G1Affine, G1Projective, G2Affine};
+use ark_ec::pairing::Pairing;
+use ark_ec::scalar_mul::fixed_base::FixedBase;
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{batch_inversion, BigInteger, FftField, Field, One, PrimeField, Zero};
+use ark_poly::{EvaluationDomain, Radix2EvaluationDomain};
+use ark_relations::r1cs::ConstraintMatrices;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{CryptoRng, RngCore};
+use ark_std::UniformRand;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+/* PLONK over BN254 with KZG commitments, the universal-setup backend (see proof_system::Backend).
+   The reference string (powers of a secret tau) is generated once for circuits up to a size and shared
+   by all of them: the keys of a circuit are computed from it and from the constraints only, with no
+   secret, so a new number of robots or tasks or a new allocator only needs a new preprocessing.
+   The R1CS constraints are compiled into gates ql a + qr b + qo c + qm a b + qc = 0: a linear
+   combination of k variables costs k - 1 addition gates, each constraint one multiplication gate, and
+   each public input one gate at the start. The prover follows the paper (Gabizon, Williamson and
+   Ciobotaru, 2019) except for the last round, where the selectors are opened at zeta instead of being
+   linearized: the proof holds 9 points and 16 evaluations (1.1 kB, 256 B for Groth16), the
+   verification still takes 2 pairings. The challenges are derived with the Anemoi hash of the circuits.
+   This implementation has not been audited: Groth16 (ark-groth16) stays the default backend, PLONK is only
+   used by the robots which accept it (see proof_system::Backend) */
+
+// Identities of the b and c wires, k1 H and k2 H are cosets distinct from H
+const K1: Fr = Fr::GENERATOR;
+
+fn k2() -> Fr {
+    K1.square()
+}
+
+// Openings at zeta, in the order of the proof: the wires, the selectors, the permutation, the
+// accumulator and the quotient
+const EVALUATIONS: usize = 15;
+
+// Extra coefficients of the committed polynomials, due to the blinding of the quotient
+const EXTRA_POWERS: usize = 6;
+
+fn invalid(message: &str) -> SkateError {
+    SkateError::Proof(message.to_string())
+}
+
+// Structured reference string: [tau^i]_1 for the commitments, [1]_2 and [tau]_2 for the openings
+#[derive(Debug, Clone, PartialEq)]
+pub struct Srs {
+    pub powers: Vec<G1Affine>,
+    pub h: G2Affine,
+    pub tau_h: G2Affine,
+}
+
+impl Srs {
+    // Local generation for circuits of up to `gates` gates, tau is dropped once the powers are computed
+    pub fn setup<R: RngCore + CryptoRng>(gates: usize, rng: &mut R) -> Srs {
+        let size = gates.max(8).next_power_of_two() + EXTRA_POWERS;
+        let tau = Fr::rand(rng);
+        let mut scalars = Vec::with_capacity(size);
+        let mut power = Fr::one();
+        for _ in 0..size {
+            scalars.push(power);
+            power *= tau;
+        }
+        let window = FixedBase::get_mul_window_size(size);
+        let bits = Fr::MODULUS_BIT_SIZE as usize;
+        let table = FixedBase::get_window_table(bits, window, G1Affine::generator().into_group());
+        let powers = FixedBase::msm::<G1Projective>(bits, window, &table, &scalars);
+        Srs {
+            powers: G1Projective::normalize_batch(&powers),
+            h: G2Affine::generator(),
+            tau_h: (G2Affine::generator() * tau).into_affine(),
+        }
+    }
+
+    // Largest number of gates of the circuits the string can prove
+    pub fn max_gates(&self) -> usize {
+        match self.powers.len().checked_sub(EXTRA_POWERS) {
+            Some(size) if size >= 8 => 1 << size.ilog2(),
+            _ => 0,
+        }
+    }
+
+    // The powers are successive powers of the tau of [tau]_2 (checked on a random combination)
+    pub fn check<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Result<()> {
+        if self.powers.len() < 2 || self.powers[0].is_zero() || self.h.is_zero() {
+            return Err(SkateError::Artifact("the reference string is empty".to_string()));
+        }
+        let scalars: Vec<Fr> = (1..self.powers.len()).map(|_| Fr::rand(rng)).collect();
+        let lower = G1Projective::msm_unchecked(&self.powers[..self.powers.len() - 1], &scalars);
+        let upper = G1Projective::msm_unchecked(&self.powers[1..], &scalars);
+        if Bn254::pairing(upper, self.h) != Bn254::pairing(lower, self.tau_h) {
+            return Err(SkateError::Artifact("the powers of the reference string are inconsistent".to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Srs> {
+        let error = |e: &dyn std::fmt::Display| SkateError::Artifact(format!("{}: {}", path.display(), e));
+        let mut reader = BufReader::new(File::open(path).map_err(|e| error(&e))?);
+        Ok(Srs {
+            powers: Vec::deserialize_compressed(&mut reader).map_err(|e| error(&e))?,
+            h: G2Affine::deserialize_compressed(&mut reader).map_err(|e| error(&e))?,
+            tau_h: G2Affine::deserialize_compressed(&mut reader).map_err(|e| error(&e))?,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let error = |e: &dyn std::fmt::Display| SkateError::Artifact(format!("{}: {}", path.display(), e));
+        let mut writer = BufWriter::new(File::create(path).map_err(|e| error(&e))?);
+        self.powers.serialize_compressed(&mut writer).map_err(|e| error(&e))?;
+        self.h.serialize_compressed(&mut writer).map_err(|e| error(&e))?;
+        self.tau_h.serialize_compressed(&mut writer).map_err(|e| error(&e))
+    }
+
+    fn commit(&self, coefficients: &[Fr]) -> G1Affine {
+        G1Projective::msm_unchecked(&self.powers[..coefficients.len()], coefficients).into_affine()
+    }
+}
+
+// Gates of a constraint system: the variables of the R1CS come first (the constant 1 then the public
+// inputs), then the partial sums of the linear combinations. A combination is summed in the order of
+// its variables, scaled so that its first coefficient is 1, and the partial sums are shared: the
+// combinations repeated in several constraints (or sharing their first terms) cost their gates once
+#[derive(Debug, Default)]
+struct Compiler {
+    rows: Vec<[usize; 3]>,
+    selectors: Vec<[Fr; 5]>, // ql, qr, qo, qm, qc
+    additions: Vec<usize>,   // Rows computing a new variable from the two others
+    sums: HashMap<(usize, Fr, usize), usize>, // Variable of sum + coefficient * variable
+    variables: usize,
+}
+
+// Value scale * variable + constant of a linear combination
+struct Term {
+    variable: usize,
+    scale: Fr,
+    constant: Fr,
+}
+
+impl Compiler {
+    fn gate(&mut self, row: [usize; 3], selectors: [Fr; 5]) {
+        self.rows.push(row);
+        self.selectors.push(selectors);
+    }
+
+    fn term(&mut self, combination: &[(Fr, usize)]) -> Term {
+        let constant = combination.iter().filter(|(_, v)| *v == 0).map(|(c, _)| *c).sum();
+        let terms: Vec<(Fr, usize)> = combination.iter().filter(|(c, v)| *v != 0 && !c.is_zero()).copied().collect();
+        let Some(((scale, first), rest)) = terms.split_first() else {
+            return Term { variable: 0, scale: Fr::zero(), constant };
+        };
+        let inverse = scale.inverse().unwrap();
+        let mut variable = *first;
+        for (coefficient, next) in rest {
+            let coefficient = *coefficient * inverse;
+            variable = match self.sums.get(&(variable, coefficient, *next)) {
+                Some(sum) => *sum,
+                None => {
+                    let sum = self.variables;
+                    self.additions.push(self.rows.len());
+                    self.gate([variable, *next, sum], [Fr::one(), coefficient, -Fr::one(), Fr::zero(), Fr::zero()]);
+                    self.sums.insert((variable, coefficient, *next), sum);
+                    self.variables += 1;
+                    sum
+                }
+            };
+        }
+        Term { variable, scale: *scale, constant }
+    }
+
+    // (sa a + ka)(sb b + kb) = sc c + kc
+    fn constraint(&mut self, a: &[(Fr, usize)], b: &[(Fr, usize)], c: &[(Fr, usize)]) {
+        let (a, b, c) = (self.term(a), self.term(b), self.term(c));
+        self.gate(
+            [a.variable, b.variable, c.variable],
+            [
+                a.scale * b.constant,
+                a.constant * b.scale,
+                -c.scale,
+                a.scale * b.scale,
+                a.constant * b.constant - c.constant,
+            ],
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyingKey {
+    pub size: usize, // Rows of the circuit, a power of 2
+    pub public_inputs: usize,
+    pub selectors: [G1Affine; 5],
+    pub permutation: [G1Affine; 3],
+    pub g: G1Affine,
+    pub h: G2Affine,
+    pub tau_h: G2Affine,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProvingKey {
+    pub vk: VerifyingKey,
+    powers: Vec<G1Affine>,
+    variables: usize, // Of the R1CS
+    gates: usize,
+    rows: Vec<[usize; 3]>,
+    additions: Vec<(usize, [Fr; 2])>,
+    wires: usize,
+    selectors: [Vec<Fr>; 5], // Coefficients
+    permutation: [Vec<Fr>; 3],
+    permutation_values: [Vec<Fr>; 3], // On the domain
+    selectors_coset: [Vec<Fr>; 5],    // On the coset of the quotient
+    permutation_coset: [Vec<Fr>; 3],
+}
+
+impl ProvingKey {
+    pub fn gates(&self) -> usize {
+        self.gates
+    }
+}
+
+fn domain(size: usize) -> Radix2EvaluationDomain<Fr> {
+    Radix2EvaluationDomain::new(size).unwrap()
+}
+
+// Domain of 4n points on which the quotient is computed, off the roots of unity
+fn quotient_domain(size: usize) -> Radix2EvaluationDomain<Fr> {
+    domain(4 * size).get_coset(Fr::GENERATOR).unwrap()
+}
+
+fn compile(matrices: &ConstraintMatrices<Fr>) -> Compiler {
+    let mut compiler = Compiler {
+        variables: matrices.num_instance_variables + matrices.num_witness_variables,
+        ..Default::default()
+    };
+    for input in 1..matrices.num_instance_variables {
+        compiler.gate([input, 0, 0], [Fr::one(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero()]);
+    }
+    for ((a, b), c) in matrices.a.iter().zip(&matrices.b).zip(&matrices.c) {
+        compiler.constraint(a, b, c);
+    }
+    compiler
+}
+
+// Gates of the circuit of the matrices, the reference string must be set up for as many
+pub fn gates(matrices: &ConstraintMatrices<Fr>) -> usize {
+    compile(matrices).rows.len()
+}
+
+// Keys of the circuit of the matrices, for the reference string
+pub fn preprocess(srs: &Srs, matrices: &ConstraintMatrices<Fr>) -> Result<ProvingKey> {
+    let public_inputs = matrices.num_instance_variables - 1;
+    let compiler = compile(matrices);
+    let gates = compiler.rows.len();
+    if gates > srs.max_gates() {
+        return Err(SkateError::Artifact(format!(
+            "the reference string is for {} gates, the circuit has {}",
+            srs.max_gates(),
+            gates
+        )));
+    }
+    // The padding rows are blank, on the constant wire
+    let n = gates.max(8).next_power_of_two();
+    let mut rows = compiler.rows;
+    rows.resize(n, [0; 3]);
+    let mut selector_values = compiler.selectors;
+    selector_values.resize(n, [Fr::zero(); 5]);
+
+    // Copy constraints: the positions of each variable are a cycle, position j n + i is k_j w^i
+    let domain = domain(n);
+    let elements: Vec<Fr> = domain.elements().collect();
+    let identity = |position: usize| [Fr::one(), K1, k2()][position / n] * elements[position % n];
+    let mut sigma: Vec<usize> = (0..3 * n).collect();
+    let mut first = vec![usize::MAX; compiler.variables];
+    let mut last = vec![usize::MAX; compiler.variables];
+    for position in 0..3 * n {
+        let variable = rows[position % n][position / n];
+        if last[variable] == usize::MAX {
+            first[variable] = position;
+        } else {
+            sigma[last[variable]] = position;
+        }
+        last[variable] = position;
+    }
+    for variable in 0..compiler.variables {
+        if last[variable] != usize::MAX {
+            sigma[last[variable]] = first[variable];
+        }
+    }
+    let permutation_values: [Vec<Fr>; 3] =
+        std::array::from_fn(|column| (0..n).map(|i| identity(sigma[column * n + i])).collect());
+    let selectors: [Vec<Fr>; 5] =
+        std::array::from_fn(|s| domain.ifft(&selector_values.iter().map(|row| row[s]).collect::<Vec<_>>()));
+    let permutation: [Vec<Fr>; 3] = std::array::from_fn(|column| domain.ifft(&permutation_values[column]));
+
+    let coset = quotient_domain(n);
+    let additions = compiler.additions.iter().map(|&row| (row, [selector_values[row][0], selector_values[row][1]]));
+    Ok(ProvingKey {
+        vk: VerifyingKey {
+            size: n,
+            public_inputs,
+            selectors: std::array::from_fn(|s| srs.commit(&selectors[s])),
+            permutation: std::array::from_fn(|column| srs.commit(&permutation[column])),
+            g: srs.powers[0],
+            h: srs.h,
+            tau_h: srs.tau_h,
+        },
+        powers: srs.powers[..n + EXTRA_POWERS].to_vec(),
+        variables: matrices.num_instance_variables + matrices.num_witness_variables,
+        gates,
+        additions: additions.collect(),
+        wires: compiler.variables,
+        selectors_coset: std::array::from_fn(|s| coset.fft(&selectors[s])),
+        permutation_coset: std::array::from_fn(|column| coset.fft(&permutation[column])),
+        rows,
+        selectors,
+        permutation,
+        permutation_values,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proof {
+    pub wires: [G1Affine; 3],
+    pub z: G1Affine, // Permutation accumulator
+    pub quotient: [G1Affine; 3],
+    pub evaluations: [Fr; EVALUATIONS],
+    pub z_shifted: Fr, // z(zeta w)
+    pub opening: G1Affine,
+    pub opening_shifted: G1Affine,
+}
+
+impl Proof {
+    fn points(&self) -> [&G1Affine; 9] {
+        let [a, b, c] = &self.wires;
+        let [lo, mid, hi] = &self.quotient;
+        [a, b, c, &self.z, lo, mid, hi, &self.opening, &self.opening_shifted]
+    }
+
+    // Decimal coordinates of the points, then the evaluations, as the Groth16 proofs are sent
+    pub fn to_strings(&self) -> Vec<String> {
+        let mut strings = vec![];
+        for point in self.points() {
+            strings.push(point.x.into_bigint().to_string());
+            strings.push(point.y.into_bigint().to_string());
+        }
+        strings.extend(self.evaluations.iter().map(|e| e.into_bigint().to_string()));
+        strings.push(self.z_shifted.into_bigint().to_string());
+        strings
+    }
+
+    pub fn from_strings(strings: &[String]) -> Result<Proof> {
+        if strings.len() != 18 + EVALUATIONS + 1 {
+            return Err(SkateError::InvalidLength { expected: 18 + EVALUATIONS + 1, found: strings.len() });
+        }
+        let points = strings[..18]
+            .chunks(2)
+            .map(|coordinates| to_g1(coordinates).filter(|point| point.is_on_curve()))
+            .collect::<Option<Vec<G1Affine>>>()
+            .ok_or_else(|| invalid("invalid point"))?;
+        let scalars = strings[18..]
+            .iter()
+            .map(|s| s.parse::<Fr>().ok())
+            .collect::<Option<Vec<Fr>>>()
+            .ok_or_else(|| invalid("invalid evaluation"))?;
+        Ok(Proof {
+            wires: [points[0], points[1], points[2]],
+            z: points[3],
+            quotient: [points[4], points[5], points[6]],
+            evaluations: std::array::from_fn(|i| scalars[i]),
+            z_shifted: scalars[EVALUATIONS],
+            opening: points[7],
+            opening_shifted: points[8],
+        })
+    }
+}
+
+// Fiat-Shamir transcript, bound to the circuit and its public inputs
+struct Transcript {
+    state: Fr,
+}
+
+impl Transcript {
+    fn new(vk: &VerifyingKey, public_inputs: &[Fr]) -> Transcript {
+        let mut transcript = Transcript { state: Fr::from(vk.size as u64) };
+        transcript.absorb(Fr::from(vk.public_inputs as u64));
+        vk.selectors.iter().chain(&vk.permutation).for_each(|point| transcript.absorb_point(point));
+        public_inputs.iter().for_each(|input| transcript.absorb(*input));
+        transcript
+    }
+
+    fn absorb(&mut self, value: Fr) {
+        self.state = hash_2_to_1(self.state, value);
+    }
+
+    // Coordinates in two halves, below the scalar modulus
+    fn absorb_point(&mut self, point: &G1Affine) {
+        for coordinate in [point.x, point.y] {
+            let bytes = Fq::into_bigint(coordinate).to_bytes_le();
+            self.absorb(Fr::from_le_bytes_mod_order(&bytes[..16]));
+            self.absorb(Fr::from_le_bytes_mod_order(&bytes[16..]));
+        }
+    }
+
+    fn challenge(&mut self) -> Fr {
+        self.absorb(Fr::zero());
+        self.state
+    }
+}
+
+fn evaluate(coefficients: &[Fr], point: Fr) -> Fr {
+    coefficients.iter().rev().fold(Fr::zero(), |value, c| value * point + c)
+}
+
+// Quotient of p(X) - p(point) by X - point
+fn divide_linear(coefficients: &[Fr], point: Fr) -> Vec<Fr> {
+    let mut quotient = vec![Fr::zero(); coefficients.len().saturating_sub(1)];
+    let mut carry = Fr::zero();
+    for i in (1..coefficients.len()).rev() {
+        carry = coefficients[i] + carry * point;
+        quotient[i - 1] = carry;
+    }
+    quotient
+}
+
+// Polynomial interpolating the values on H plus (b_0 + b_1 X + ...) Z_H(X), which hides it
+fn blinded<R: RngCore + CryptoRng>(domain: &Radix2EvaluationDomain<Fr>, values: &[Fr], blinding: usize, rng: &mut R) -> Vec<Fr> {
+    let n = domain.size();
+    let mut coefficients = domain.ifft(values);
+    coefficients.resize(n + blinding, Fr::zero());
+    for i in 0..blinding {
+        let b = Fr::rand(rng);
+        coefficients[i] -= b;
+        coefficients[n + i] += b;
+    }
+    coefficients
+}
+
+fn add_scaled(sum: &mut Vec<Fr>, coefficients: &[Fr], scale: Fr) {
+    if sum.len() < coefficients.len() {
+        sum.resize(coefficients.len(), Fr::zero());
+    }
+    sum.iter_mut().zip(coefficients).for_each(|(s, c)| *s += scale * c);
+}
+
+// Proof of the assignment of the R1CS variables: the constant 1, the public inputs, then the witness
+pub fn prove<R: RngCore + CryptoRng>(pk: &ProvingKey, assignment: &[Fr], rng: &mut R) -> Result<Proof> {
+    if assignment.len() != pk.variables {
+        return Err(SkateError::InvalidLength { expected: pk.variables, found: assignment.len() });
+    }
+    let n = pk.vk.size;
+    let domain = domain(n);
+    let omega = domain.group_gen();
+    let public_inputs = &assignment[1..=pk.vk.public_inputs];
+    let mut transcript = Transcript::new(&pk.vk, public_inputs);
+    let commit = |coefficients: &[Fr]| {
+        G1Projective::msm_unchecked(&pk.powers[..coefficients.len()], coefficients).into_affine()
+    };
+
+    // Round 1: the wires
+    let mut values = assignment.to_vec();
+    values.resize(pk.wires, Fr::zero());
+    for (row, [left, right]) in &pk.additions {
+        let [a, b, c] = pk.rows[*row];
+        values[c] = *left * values[a] + *right * values[b];
+    }
+    let wire_values: [Vec<Fr>; 3] = std::array::from_fn(|column| pk.rows.iter().map(|row| values[row[column]]).collect());
+    let wires: [Vec<Fr>; 3] = std::array::from_fn(|column| blinded(&domain, &wire_values[column], 2, rng));
+    let wire_commitments = wires.each_ref().map(|wire| commit(wire));
+    wire_commitments.iter().for_each(|point| transcript.absorb_point(point));
+
+    // Round 2: the accumulator of the copy constraints
+    let beta = transcript.challenge();
+    let gamma = transcript.challenge();
+    let identities = [Fr::one(), K1, k2()];
+    let mut numerators = vec![Fr::one(); n];
+    let mut denominators = vec![Fr::one(); n];
+    for (i, x) in domain.elements().enumerate() {
+        for column in 0..3 {
+            numerators[i] *= wire_values[column][i] + beta * identities[column] * x + gamma;
+            denominators[i] *= wire_values[column][i] + beta * pk.permutation_values[column][i] + gamma;
+        }
+    }
+    batch_inversion(&mut denominators);
+    let mut z_values = vec![Fr::one(); n];
+    for i in 0..n - 1 {
+        z_values[i + 1] = z_values[i] * numerators[i] * denominators[i];
+    }
+    let z = blinded(&domain, &z_values, 3, rng);
+    let z_commitment = commit(&z);
+    transcript.absorb_point(&z_commitment);
+
+    // Round 3: the quotient, on the coset of 4n points
+    let alpha = transcript.challenge();
+    let coset = quotient_domain(n);
+    let points: Vec<Fr> = coset.elements().collect();
+    let [a, b, c] = wires.each_ref().map(|wire| coset.fft(wire));
+    let z_coset = coset.fft(&z);
+    let mut pi_values = vec![Fr::zero(); n];
+    pi_values.iter_mut().zip(public_inputs).for_each(|(pi, input)| *pi = -*input);
+    let pi = coset.fft(&domain.ifft(&pi_values));
+    // Z_H takes 4 values on the coset
+    let mut vanishing: Vec<Fr> = (0..4).map(|j| points[j].pow([n as u64]) - Fr::one()).collect();
+    let mut lagrange: Vec<Fr> = points.iter().map(|x| Fr::from(n as u64) * (*x - Fr::one())).collect();
+    batch_inversion(&mut lagrange);
+    lagrange.iter_mut().enumerate().for_each(|(j, l)| *l *= vanishing[j % 4]);
+    batch_inversion(&mut vanishing);
+    let [ql, qr, qo, qm, qc] = &pk.selectors_coset;
+    let [s1, s2, s3] = &pk.permutation_coset;
+    let alpha_2 = alpha.square();
+    let quotient_values: Vec<Fr> = (0..4 * n)
+        .map(|j| {
+            let x = points[j];
+            let z_shifted = z_coset[(j + 4) % (4 * n)];
+            let gate = ql[j] * a[j] + qr[j] * b[j] + qo[j] * c[j] + qm[j] * a[j] * b[j] + qc[j] + pi[j];
+            let copies = (a[j] + beta * x + gamma)
+                * (b[j] + beta * K1 * x + gamma)
+                * (c[j] + beta * identities[2] * x + gamma)
+                * z_coset[j]
+                - (a[j] + beta * s1[j] + gamma) * (b[j] + beta * s2[j] + gamma) * (c[j] + beta * s3[j] + gamma) * z_shifted;
+            let start = (z_coset[j] - Fr::one()) * lagrange[j];
+            (gate + alpha * copies + alpha_2 * start) * vanishing[j % 4]
+        })
+        .collect();
+    let mut quotient = coset.ifft(&quotient_values);
+    // Without a satisfying assignment the numerator is not divisible by Z_H
+    if quotient[3 * n + EXTRA_POWERS..].iter().any(|c| !c.is_zero()) {
+        return Err(invalid("the assignment does not satisfy the circuit"));
+    }
+    quotient.truncate(3 * n + EXTRA_POWERS);
+    let quotient = [&quotient[..n], &quotient[n..2 * n], &quotient[2 * n..]];
+    let quotient_commitments = quotient.map(|q| commit(q));
+    quotient_commitments.iter().for_each(|point| transcript.absorb_point(point));
+
+    // Round 4: the evaluations at zeta
+    let zeta = transcript.challenge();
+    let polynomials: [&[Fr]; EVALUATIONS] = [
+        &wires[0], &wires[1], &wires[2],
+        &pk.selectors[0], &pk.selectors[1], &pk.selectors[2], &pk.selectors[3], &pk.selectors[4],
+        &pk.permutation[0], &pk.permutation[1], &pk.permutation[2],
+        &z, quotient[0], quotient[1], quotient[2],
+    ];
+    let evaluations = polynomials.map(|p| evaluate(p, zeta));
+    let z_shifted = evaluate(&z, zeta * omega);
+    evaluations.iter().for_each(|e| transcript.absorb(*e));
+    transcript.absorb(z_shifted);
+
+    // Round 5: the openings, all the polynomials at zeta and z at zeta w
+    let v = transcript.challenge();
+    let mut combination = vec![];
+    let mut power = Fr::one();
+    for polynomial in polynomials {
+        add_scaled(&mut combination, polynomial, power);
+        power *= v;
+    }
+    Ok(Proof {
+        wires: wire_commitments,
+        z: z_commitment,
+        quotient: quotient_commitments,
+        evaluations,
+        z_shifted,
+        opening: commit(&divide_linear(&combination, zeta)),
+        opening_shifted: commit(&divide_linear(&z, zeta * omega)),
+    })
+}
+
+pub fn verify(vk: &VerifyingKey, public_inputs: &[Fr], proof: &Proof) -> Result<bool> {
+    if public_inputs.len() != vk.public_inputs {
+        return Err(SkateError::InvalidLength { expected: vk.public_inputs, found: public_inputs.len() });
+    }
+    let n = vk.size;
+    let domain = domain(n);
+    let mut transcript = Transcript::new(vk, public_inputs);
+    proof.wires.iter().for_each(|point| transcript.absorb_point(point));
+    let beta = transcript.challenge();
+    let gamma = transcript.challenge();
+    transcript.absorb_point(&proof.z);
+    let alpha = transcript.challenge();
+    proof.quotient.iter().for_each(|point| transcript.absorb_point(point));
+    let zeta = transcript.challenge();
+    proof.evaluations.iter().for_each(|e| transcript.absorb(*e));
+    transcript.absorb(proof.z_shifted);
+    let v = transcript.challenge();
+    proof.points()[7..].iter().for_each(|point| transcript.absorb_point(point));
+    let u = transcript.challenge();
+
+    // The identity of the quotient at zeta
+    let zeta_n = zeta.pow([n as u64]);
+    let vanishing = zeta_n - Fr::one();
+    if vanishing.is_zero() {
+        return Ok(false);
+    }
+    let mut lagrange: Vec<Fr> = domain
+        .elements()
+        .take(vk.public_inputs.max(1))
+        .map(|w| Fr::from(n as u64) * (zeta - w))
+        .collect();
+    batch_inversion(&mut lagrange);
+    lagrange
+        .iter_mut()
+        .zip(domain.elements())
+        .for_each(|(l, w)| *l *= w * vanishing);
+    let pi: Fr = -public_inputs.iter().zip(&lagrange).map(|(input, l)| *input * l).sum::<Fr>();
+    let [a, b, c, ql, qr, qo, qm, qc, s1, s2, s3, z, lo, mid, hi] = proof.evaluations;
+    let gate = ql * a + qr * b + qo * c + qm * a * b + qc + pi;
+    let copies = (a + beta * zeta + gamma) * (b + beta * K1 * zeta + gamma) * (c + beta * k2() * zeta + gamma) * z
+        - (a + beta * s1 + gamma) * (b + beta * s2 + gamma) * (c + beta * s3 + gamma) * proof.z_shifted;
+    let start = (z - Fr::one()) * lagrange[0];
+    let quotient = lo + zeta_n * mid + zeta_n.square() * hi;
+    if gate + alpha * copies + alpha.square() * start != quotient * vanishing {
+        return Ok(false);
+    }
+
+    // The openings, in one pairing check
+    let [wa, wb, wc] = proof.wires;
+    let [q1, q2, q3, q4, q5] = vk.selectors;
+    let [p1, p2, p3] = vk.permutation;
+    let [t1, t2, t3] = proof.quotient;
+    let commitments = [wa, wb, wc, q1, q2, q3, q4, q5, p1, p2, p3, proof.z, t1, t2, t3];
+    let mut powers = Vec::with_capacity(EVALUATIONS);
+    let mut power = Fr::one();
+    for _ in 0..EVALUATIONS {
+        powers.push(power);
+        power *= v;
+    }
+    let value: Fr = proof.evaluations.iter().zip(&powers).map(|(e, p)| *e * p).sum::<Fr>() + u * proof.z_shifted;
+    let combined = G1Projective::msm_unchecked(&commitments, &powers) + proof.z * u;
+    let shifted = zeta * domain.group_gen();
+    let left = proof.opening_shifted * u + proof.opening;
+    let right = proof.opening * zeta + proof.opening_shifted * (u * shifted) + combined - vk.g * value;
+    Ok(Bn254::pairing(left, vk.tau_h) == Bn254::pairing(right, vk.h))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::TaskState;
+    use crate::position::Position;
+    use crate::proof_system::{plonk_matrices, PlonkBackend, ProofSystem};
+    use crate::rollup::RollupCircuit;
+    use crate::Skate::{create_robot, create_task, SkateParameters, IDLE_TASK_ID};
+    use ark_std::rand::rngs::StdRng;
+    use ark_std::rand::SeedableRng;
+
+    #[test]
+    fn rounds_are_proven_and_tampering_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let position = Position::new(3, 4, 0).unwrap();
+        let robot = create_robot(Fr::zero(), [Fr::from(IDLE_TASK_ID); 2], [Position::default(), position]).unwrap();
+        let robots = vec![robot];
+        let tasks = vec![create_task(Fr::zero(), Position::new(1, 1, 0).unwrap()).unwrap()];
+        let states = vec![(Fr::zero(), TaskState::Pending.value())];
+        let circuit = RollupCircuit::new(&robots, &tasks, &SkateParameters::default(), Fr::one(), &states).unwrap();
+
+        let srs = Srs::setup(gates(&plonk_matrices(circuit.clone()).unwrap()), &mut rng);
+        let backend = PlonkBackend::new(srs);
+        let (pk, vk) = backend.keys(circuit.clone(), &mut rng).unwrap();
+        let proof = backend.prove(&pk, circuit.clone(), &mut rng).unwrap();
+        let public_inputs = circuit.public_inputs();
+        assert!(verify(&vk, &public_inputs, &proof).unwrap());
+
+        // The proof as sent in the updates
+        let decoded = Proof::from_strings(&proof.to_strings()).unwrap();
+        assert_eq!(decoded, proof);
+        assert!(verify(&vk, &public_inputs, &decoded).unwrap());
+
+        for i in 0..public_inputs.len() {
+            let mut inputs = public_inputs.clone();
+            inputs[i] += Fr::one();
+            assert!(!verify(&vk, &inputs, &proof).unwrap(), "public input {}", i);
+        }
+        assert!(verify(&vk, &public_inputs[1..], &proof).is_err());
+
+        for i in 0..EVALUATIONS {
+            let mut tampered = proof.clone();
+            tampered.evaluations[i] += Fr::one();
+            assert!(!verify(&vk, &public_inputs, &tampered).unwrap(), "evaluation {}", i);
+        }
+        let mut tampered = proof.clone();
+        tampered.z_shifted += Fr::one();
+        assert!(!verify(&vk, &public_inputs, &tampered).unwrap());
+
+        // Each commitment moved by the generator, still on the curve
+        let moved = |point: &G1Affine| (*point + G1Affine::generator()).into_affine();
+        for i in 0..9 {
+            let mut tampered = proof.clone();
+            match i {
+                0..=2 => tampered.wires[i] = moved(&proof.wires[i]),
+                3 => tampered.z = moved(&proof.z),
+                4..=6 => tampered.quotient[i - 4] = moved(&proof.quotient[i - 4]),
+                7 => tampered.opening = moved(&proof.opening),
+                _ => tampered.opening_shifted = moved(&proof.opening_shifted),
+            }
+            assert!(!verify(&vk, &public_inputs, &tampered).unwrap(), "commitment {}", i);
+        }
+    }
+
+    // Matrices over the variables 1, the public inputs, then the witness: each constraint is a b = c
+    fn matrices(
+        public_inputs: usize,
+        witness: usize,
+        constraints: Vec<[Vec<(Fr, usize)>; 3]>,
+    ) -> ConstraintMatrices<Fr> {
+        let column = |k: usize| constraints.iter().map(|constraint| constraint[k].clone()).collect::<Vec<_>>();
+        let (a, b, c) = (column(0), column(1), column(2));
+        ConstraintMatrices {
+            num_instance_variables: public_inputs + 1,
+            num_witness_variables: witness,
+            num_constraints: constraints.len(),
+            a_num_non_zero: a.iter().map(Vec::len).sum(),
+            b_num_non_zero: b.iter().map(Vec::len).sum(),
+            c_num_non_zero: c.iter().map(Vec::len).sum(),
+            a,
+            b,
+            c,
+        }
+    }
+
+    // x y = z repeated, z public (variable 1), x and y the witness (variables 2 and 3)
+    fn products(constraints: usize) -> ConstraintMatrices<Fr> {
+        let one = Fr::one();
+        matrices(1, 2, vec![[vec![(one, 2)], vec![(one, 3)], vec![(one, 1)]]; constraints])
+    }
+
+    fn proven(matrices: &ConstraintMatrices<Fr>, assignment: &[Fr]) -> Result<(VerifyingKey, Proof)> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let srs = Srs::setup(gates(matrices), &mut rng);
+        let pk = preprocess(&srs, matrices)?;
+        Ok((pk.vk.clone(), prove(&pk, assignment, &mut rng)?))
+    }
+
+    #[test]
+    fn unsatisfying_witnesses_are_not_proven() {
+        let one = Fr::one();
+        let (vk, proof) = proven(&products(1), &[one, Fr::from(6), Fr::from(2), Fr::from(3)]).unwrap();
+        assert!(verify(&vk, &[Fr::from(6)], &proof).unwrap());
+        assert!(proven(&products(1), &[one, Fr::from(7), Fr::from(2), Fr::from(3)]).is_err());
+
+        // (x + y) 1 = z, broken through the addition gate of x + y
+        let sum = matrices(1, 2, vec![[vec![(one, 2), (one, 3)], vec![(one, 0)], vec![(one, 1)]]]);
+        assert!(proven(&sum, &[one, Fr::from(5), Fr::from(2), Fr::from(3)]).is_ok());
+        assert!(proven(&sum, &[one, Fr::from(6), Fr::from(2), Fr::from(3)]).is_err());
+    }
+
+    #[test]
+    fn wrong_public_inputs_are_rejected() {
+        let (vk, proof) = proven(&products(1), &[Fr::one(), Fr::from(6), Fr::from(2), Fr::from(3)]).unwrap();
+        assert!(verify(&vk, &[Fr::from(6)], &proof).unwrap());
+        assert!(!verify(&vk, &[Fr::from(7)], &proof).unwrap());
+        assert!(!verify(&vk, &[-Fr::from(6)], &proof).unwrap());
+        assert!(verify(&vk, &[], &proof).is_err());
+        assert!(verify(&vk, &[Fr::from(6), Fr::from(6)], &proof).is_err());
+    }
+
+    #[test]
+    fn single_constraint_circuits_are_proven() {
+        // x x = x, without public input
+        let one = Fr::one();
+        let square = matrices(0, 1, vec![[vec![(one, 1)], vec![(one, 1)], vec![(one, 1)]]]);
+        assert_eq!(gates(&square), 1);
+        let (vk, proof) = proven(&square, &[one, one]).unwrap();
+        assert!(verify(&vk, &[], &proof).unwrap());
+        assert!(proven(&square, &[one, Fr::from(2)]).is_err());
+    }
+
+    #[test]
+    fn public_input_only_circuits_are_proven() {
+        let inputs = [Fr::from(4), Fr::from(5), Fr::from(6)];
+        let assignment = [Fr::one(), inputs[0], inputs[1], inputs[2]];
+        let (vk, proof) = proven(&matrices(3, 0, vec![]), &assignment).unwrap();
+        assert_eq!(vk.public_inputs, 3);
+        assert!(verify(&vk, &inputs, &proof).unwrap());
+        let mut wrong = inputs;
+        wrong[2] = Fr::from(7);
+        assert!(!verify(&vk, &wrong, &proof).unwrap());
+    }
+
+    #[test]
+    fn circuits_up_to_the_size_of_the_reference_string_are_proven() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let srs = Srs::setup(16, &mut rng);
+        assert_eq!(srs.max_gates(), 16);
+        let assignment = [Fr::one(), Fr::from(6), Fr::from(2), Fr::from(3)];
+
+        // The public input gate and 15 constraints fill the 16 rows, without padding
+        let pk = preprocess(&srs, &products(15)).unwrap();
+        assert_eq!((pk.gates(), pk.vk.size), (16, 16));
+        let proof = prove(&pk, &assignment, &mut rng).unwrap();
+        assert!(verify(&pk.vk, &[Fr::from(6)], &proof).unwrap());
+
+        assert!(preprocess(&srs, &products(16)).is_err());
+    }
+}
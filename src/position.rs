@@ -0,0 +1,228 @@
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use serde::{Deserialize, Serialize};
+
+// Coordinates are integers between 0 and GRID_SIZE (excluded), on 14 bits in the hashes
+// (see hashes::hash_task)
+pub const GRID_SIZE: u64 = 10000;
+
+/* A position on the grid committed by the circuits, each coordinate is a field element
+   between 0 and GRID_SIZE (excluded). On the wire it is an array of three integers,
+   range-checked when deserialized */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "[u64; 3]", into = "[u64; 3]")]
+pub struct Position {
+    x: Fr,
+    y: Fr,
+    z: Fr,
+}
+
+impl Position {
+    pub fn new(x: u64, y: u64, z: u64) -> Option<Position> {
+        if x < GRID_SIZE && y < GRID_SIZE && z < GRID_SIZE {
+            Some(Position {
+                x: Fr::from(x),
+                y: Fr::from(y),
+                z: Fr::from(z),
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn from_coordinates(coordinates: [Fr; 3]) -> Option<Position> {
+        let bound = Fr::from(GRID_SIZE);
+        if coordinates.iter().all(|c| *c < bound) {
+            Some(Position {
+                x: coordinates[0],
+                y: coordinates[1],
+                z: coordinates[2],
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn x(&self) -> Fr {
+        self.x
+    }
+
+    pub fn y(&self) -> Fr {
+        self.y
+    }
+
+    pub fn z(&self) -> Fr {
+        self.z
+    }
+
+    pub fn coordinates(&self) -> [Fr; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    pub fn grid(&self) -> [u64; 3] {
+        [
+            self.x.into_bigint().0[0],
+            self.y.into_bigint().0[0],
+            self.z.into_bigint().0[0],
+        ]
+    }
+}
+
+impl TryFrom<[u64; 3]> for Position {
+    type Error = &'static str;
+
+    fn try_from(grid: [u64; 3]) -> Result<Self, Self::Error> {
+        Position::new(grid[0], grid[1], grid[2]).ok_or("Position out of the grid")
+    }
+}
+
+impl From<Position> for [u64; 3] {
+    fn from(position: Position) -> Self {
+        position.grid()
+    }
+}
+
+/* Conversion between grid positions and real-world coordinates:
+    - origin: coordinates (meters) of the grid position (0, 0, 0)
+    - resolution: meters per grid unit
+    - bounds: grid positions must be below them on each axis (at most GRID_SIZE)
+   Deserialized through Frame::new, so a configuration cannot bypass its checks.
+   The `scale` of the former server configurations mapped a grid coordinate c to c / 10000 * scale - 2
+   meters, the same frame as origin -2 and resolution scale / 10000 (see Frame::from_scale)
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "FrameConfig")]
+pub struct Frame {
+    origin: [f64; 3],
+    resolution: f64,
+    bounds: [u64; 3],
+}
+
+#[derive(Deserialize)]
+struct FrameConfig {
+    origin: [f64; 3],
+    resolution: f64,
+    #[serde(default = "default_bounds")]
+    bounds: [u64; 3],
+}
+
+fn default_bounds() -> [u64; 3] {
+    [GRID_SIZE; 3]
+}
+
+impl TryFrom<FrameConfig> for Frame {
+    type Error = &'static str;
+
+    fn try_from(config: FrameConfig) -> Result<Self, Self::Error> {
+        Frame::new(config.origin, config.resolution, config.bounds)
+    }
+}
+
+impl Default for Frame {
+    // One grid unit per meter starting at 0, grid positions and meters are the same
+    fn default() -> Self {
+        Frame {
+            origin: [0f64; 3],
+            resolution: 1f64,
+            bounds: [GRID_SIZE; 3],
+        }
+    }
+}
+
+impl Frame {
+    pub fn new(origin: [f64; 3], resolution: f64, bounds: [u64; 3]) -> Result<Frame, &'static str> {
+        if !(resolution > 0f64 && resolution.is_finite()) {
+            return Err("The resolution must be a positive number of meters");
+        }
+        if !origin.iter().all(|o| o.is_finite()) {
+            return Err("The origin must be finite");
+        }
+        if !bounds.iter().all(|b| *b <= GRID_SIZE) {
+            return Err("The bounds must be at most the grid size");
+        }
+        Ok(Frame {
+            origin,
+            resolution,
+            bounds,
+        })
+    }
+
+    // Frame of the former `scale` setting of the servers (meters spanned by the GRID_SIZE units, from -2)
+    pub fn from_scale(scale: f64) -> Result<Frame, &'static str> {
+        Frame::new([-2f64; 3], scale / GRID_SIZE as f64, [GRID_SIZE; 3])
+    }
+
+    pub fn origin(&self) -> [f64; 3] {
+        self.origin
+    }
+
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    pub fn bounds(&self) -> [u64; 3] {
+        self.bounds
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        let grid = position.grid();
+        (0..3).all(|i| grid[i] < self.bounds[i])
+    }
+
+    // Range-checked against the bounds of the frame
+    pub fn position(&self, x: u64, y: u64, z: u64) -> Option<Position> {
+        Position::new(x, y, z).filter(|position| self.contains(position))
+    }
+
+    pub fn to_meters(&self, position: &Position) -> [f64; 3] {
+        let grid = position.grid();
+        [
+            self.origin[0] + grid[0] as f64 * self.resolution,
+            self.origin[1] + grid[1] as f64 * self.resolution,
+            self.origin[2] + grid[2] as f64 * self.resolution,
+        ]
+    }
+
+    // Rounded to the closest grid position, None outside of the bounds
+    pub fn from_meters(&self, meters: [f64; 3]) -> Option<Position> {
+        let mut grid = [0u64; 3];
+        for i in 0..3 {
+            let units = ((meters[i] - self.origin[i]) / self.resolution).round();
+            if !(units >= 0f64 && units < self.bounds[i] as f64) {
+                return None;
+            }
+            grid[i] = units as u64;
+        }
+        self.position(grid[0], grid[1], grid[2])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_checks_its_configuration() {
+        let frame: Frame = serde_json::from_str(r#"{"origin": [-2.0, -2.0, -2.0], "resolution": 0.001}"#).unwrap();
+        assert_eq!(frame.bounds(), [GRID_SIZE; 3]);
+        for invalid in [
+            r#"{"origin": [0.0, 0.0, 0.0], "resolution": 0.0}"#,
+            r#"{"origin": [0.0, 0.0, 0.0], "resolution": -1.0}"#,
+            r#"{"origin": [0.0, 0.0, 0.0], "resolution": 1.0, "bounds": [10001, 1, 1]}"#,
+        ] {
+            assert!(serde_json::from_str::<Frame>(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn scale_keeps_its_conversion() {
+        let frame = Frame::from_scale(10f64).unwrap();
+        let position = Position::new(5000, 0, 9999).unwrap();
+        // c / 10000 * scale - 2
+        let expected = [5000f64, 0f64, 9999f64].map(|c| c / 10000f64 * 10f64 - 2f64);
+        for (meters, expected) in frame.to_meters(&position).iter().zip(expected) {
+            assert!((meters - expected).abs() < 1e-9);
+        }
+        assert_eq!(frame.from_meters(expected), Some(position));
+    }
+}
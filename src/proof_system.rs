@@ -0,0 +1,229 @@
+use crate::error::{Result, SkateError};
+use crate::plonk::{self, Srs};
+use crate::setup::generate_parameters;
+use crate::verifying_key::{to_g1, to_g2};
+use ark_bn254::{Bn254, Fr};
+use ark_circom::CircomReduction;
+use ark_crypto_primitives::snark::SNARK;
+use ark_ff::{Field, PrimeField};
+use ark_groth16::{prepare_verifying_key, Groth16, PreparedVerifyingKey, Proof, ProvingKey};
+use ark_relations::r1cs::{
+    ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, OptimizationGoal,
+    SynthesisMode,
+};
+use ark_std::rand::{CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/* Proving backends of the rollup circuits behind one interface: Groth16, whose keys come from a trusted
+   setup per circuit (see setup), and PLONK, whose keys are computed from a universal reference string
+   (see plonk). The coordinator proves a round with the first backend of its preferences that all its
+   agents accept (see Backend::negotiate), the update names the backend so that the agent verifies with
+   the matching keys */
+
+type GrothBn = Groth16<Bn254, CircomReduction>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Groth16,
+    Plonk,
+}
+
+impl Backend {
+    // First backend of the preferences accepted by every agent
+    pub fn negotiate(preferences: &[Backend], accepted: &[Vec<Backend>]) -> Option<Backend> {
+        preferences
+            .iter()
+            .copied()
+            .find(|backend| accepted.iter().all(|backends| backends.contains(backend)))
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Backend::Groth16 => write!(f, "groth16"),
+            Backend::Plonk => write!(f, "plonk"),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = SkateError;
+
+    fn from_str(s: &str) -> Result<Backend> {
+        match s {
+            "groth16" => Ok(Backend::Groth16),
+            "plonk" => Ok(Backend::Plonk),
+            _ => Err(SkateError::Proof(format!("unknown backend {}", s))),
+        }
+    }
+}
+
+pub trait ProofSystem {
+    type ProvingKey;
+    type VerifyingKey;
+    type Proof;
+
+    fn backend(&self) -> Backend;
+
+    // Keys of a circuit, synthesized without its witness
+    fn keys<C, R>(&self, circuit: C, rng: &mut R) -> Result<(Self::ProvingKey, Self::VerifyingKey)>
+    where
+        C: ConstraintSynthesizer<Fr> + Clone,
+        R: RngCore + CryptoRng;
+
+    fn prove<C, R>(&self, pk: &Self::ProvingKey, circuit: C, rng: &mut R) -> Result<Self::Proof>
+    where
+        C: ConstraintSynthesizer<Fr>,
+        R: RngCore + CryptoRng;
+
+    fn verify(&self, vk: &Self::VerifyingKey, public_inputs: &[Fr], proof: &Self::Proof) -> Result<bool>;
+
+    // Proof as the decimal strings of an update
+    fn encode(&self, proof: &Self::Proof) -> Vec<String>;
+
+    fn decode(&self, proof: &[String]) -> Result<Self::Proof>;
+}
+
+// Synthesis for PLONK, which pays a gate per term of the linear combinations: the combinations used
+// several times become variables instead of being inlined in each constraint (see plonk_matrices)
+fn synthesize<C: ConstraintSynthesizer<Fr>>(circuit: C, mode: SynthesisMode) -> Result<ConstraintSystemRef<Fr>> {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Weight);
+    cs.set_mode(mode);
+    circuit
+        .generate_constraints(cs.clone())
+        .map_err(|e| SkateError::Proof(e.to_string()))?;
+    cs.finalize();
+    Ok(cs)
+}
+
+// Matrices of a circuit as the PLONK backend compiles it
+pub fn plonk_matrices<C: ConstraintSynthesizer<Fr>>(circuit: C) -> Result<ConstraintMatrices<Fr>> {
+    synthesize(circuit, SynthesisMode::Setup)?
+        .to_matrices()
+        .ok_or_else(|| SkateError::Proof("the constraint system has no matrices".to_string()))
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Groth16Backend;
+
+impl ProofSystem for Groth16Backend {
+    type ProvingKey = ProvingKey<Bn254>;
+    type VerifyingKey = PreparedVerifyingKey<Bn254>;
+    type Proof = Proof<Bn254>;
+
+    fn backend(&self) -> Backend {
+        Backend::Groth16
+    }
+
+    fn keys<C, R>(&self, circuit: C, rng: &mut R) -> Result<(ProvingKey<Bn254>, PreparedVerifyingKey<Bn254>)>
+    where
+        C: ConstraintSynthesizer<Fr> + Clone,
+        R: RngCore + CryptoRng,
+    {
+        let (pk, _) = generate_parameters(circuit, rng)?;
+        let vk = prepare_verifying_key(&pk.vk);
+        Ok((pk, vk))
+    }
+
+    fn prove<C, R>(&self, pk: &ProvingKey<Bn254>, circuit: C, rng: &mut R) -> Result<Proof<Bn254>>
+    where
+        C: ConstraintSynthesizer<Fr>,
+        R: RngCore + CryptoRng,
+    {
+        GrothBn::prove(pk, circuit, rng).map_err(|e| SkateError::Proof(e.to_string()))
+    }
+
+    fn verify(&self, vk: &PreparedVerifyingKey<Bn254>, public_inputs: &[Fr], proof: &Proof<Bn254>) -> Result<bool> {
+        GrothBn::verify_with_processed_vk(vk, public_inputs, proof).map_err(|e| SkateError::Proof(e.to_string()))
+    }
+
+    // A, B and C in affine coordinates, B as x.c0, x.c1, y.c0, y.c1
+    fn encode(&self, proof: &Proof<Bn254>) -> Vec<String> {
+        let mut strings = vec![proof.a.x.into_bigint().to_string(), proof.a.y.into_bigint().to_string()];
+        for coordinate in [proof.b.x, proof.b.y] {
+            strings.extend(coordinate.to_base_prime_field_elements().map(|e| e.into_bigint().to_string()));
+        }
+        strings.push(proof.c.x.into_bigint().to_string());
+        strings.push(proof.c.y.into_bigint().to_string());
+        strings
+    }
+
+    fn decode(&self, proof: &[String]) -> Result<Proof<Bn254>> {
+        if proof.len() != 8 {
+            return Err(SkateError::InvalidLength { expected: 8, found: proof.len() });
+        }
+        let invalid = || SkateError::Proof("invalid point".to_string());
+        Ok(Proof {
+            a: to_g1(&proof[0..2]).filter(|a| a.is_on_curve()).ok_or_else(invalid)?,
+            b: to_g2(&[proof[2..4].to_vec(), proof[4..6].to_vec()])
+                .filter(|b| b.is_on_curve() && b.is_in_correct_subgroup_assuming_on_curve())
+                .ok_or_else(invalid)?,
+            c: to_g1(&proof[6..8]).filter(|c| c.is_on_curve()).ok_or_else(invalid)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlonkBackend {
+    srs: Srs,
+}
+
+impl PlonkBackend {
+    pub fn new(srs: Srs) -> PlonkBackend {
+        PlonkBackend { srs }
+    }
+
+    pub fn srs(&self) -> &Srs {
+        &self.srs
+    }
+}
+
+impl ProofSystem for PlonkBackend {
+    type ProvingKey = plonk::ProvingKey;
+    type VerifyingKey = plonk::VerifyingKey;
+    type Proof = plonk::Proof;
+
+    fn backend(&self) -> Backend {
+        Backend::Plonk
+    }
+
+    // Deterministic, the randomness is the one of the reference string
+    fn keys<C, R>(&self, circuit: C, _rng: &mut R) -> Result<(plonk::ProvingKey, plonk::VerifyingKey)>
+    where
+        C: ConstraintSynthesizer<Fr> + Clone,
+        R: RngCore + CryptoRng,
+    {
+        let pk = plonk::preprocess(&self.srs, &plonk_matrices(circuit)?)?;
+        let vk = pk.vk.clone();
+        Ok((pk, vk))
+    }
+
+    fn prove<C, R>(&self, pk: &plonk::ProvingKey, circuit: C, rng: &mut R) -> Result<plonk::Proof>
+    where
+        C: ConstraintSynthesizer<Fr>,
+        R: RngCore + CryptoRng,
+    {
+        // The outlined combinations get their variables only along with the matrices
+        let cs = synthesize(circuit, SynthesisMode::Prove { construct_matrices: true })?;
+        let cs = cs.borrow().unwrap();
+        plonk::prove(pk, &[cs.instance_assignment.as_slice(), cs.witness_assignment.as_slice()].concat(), rng)
+    }
+
+    fn verify(&self, vk: &plonk::VerifyingKey, public_inputs: &[Fr], proof: &plonk::Proof) -> Result<bool> {
+        plonk::verify(vk, public_inputs, proof)
+    }
+
+    fn encode(&self, proof: &plonk::Proof) -> Vec<String> {
+        proof.to_strings()
+    }
+
+    fn decode(&self, proof: &[String]) -> Result<plonk::Proof> {
+        plonk::Proof::from_strings(proof)
+    }
+}
@@ -0,0 +1,355 @@
+use crate::error::{Result, SkateError};
+use crate::graph::{road_map_witness, DistanceTable};
+use crate::hashes::{distance_table_root, robot_root, task_root};
+use crate::inputs::{build_rollup_inputs, RollupInputs};
+use crate::lifecycle::TaskStateWitness;
+use crate::plonk::{self, Srs};
+use crate::proof_system::{Backend, Groth16Backend, ProofSystem};
+use crate::registry::{Artifact, CircuitArtifacts, CircuitKey};
+use crate::rollup::RollupCircuit;
+use crate::setup::outlined_matrices;
+use crate::simulator::RoundProver;
+use crate::Skate::{Robot, SkateParameters, Task};
+use ark_bn254::{Bn254, Fr};
+use ark_circom::{read_zkey, CircomReduction};
+use ark_crypto_primitives::snark::SNARK;
+use ark_groth16::{prepare_verifying_key, Groth16, PreparedVerifyingKey, Proof, ProvingKey};
+use ark_relations::r1cs::ConstraintMatrices;
+use ark_std::rand::{thread_rng, CryptoRng, RngCore};
+use ark_std::UniformRand;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+
+/* Long-lived Groth16 prover of the registered circuits. The proving key and the constraint matrices of
+   the zkey, and the prepared verifying key are loaded once per circuit; a proof then only computes the
+   witness with rollup::RollupCircuit and runs the prover on the cached matrices. Nothing is mutable once
+   loaded, so a prover can be shared between threads behind an Arc and its proofs run in parallel.
+   With a reference string, the circuits also get PLONK keys, preprocessed from the matrices of the
+   native circuit, and the rounds can be proven with either backend (see proof_system::Backend) */
+
+type GrothBn = Groth16<Bn254, CircomReduction>;
+
+// Full matrices of a registered circuit, with the C matrix the zkey lacks, from the native circuit of
+// its size
+pub fn circuit_matrices(circuit: &CircuitArtifacts) -> Result<ConstraintMatrices<Fr>> {
+    outlined_matrices(RollupCircuit::blank(
+        circuit.robots,
+        circuit.tasks,
+        &circuit.parameters(),
+        circuit.road_map_depth(),
+    )?)
+}
+
+#[derive(Debug)]
+pub struct CircuitProver {
+    key: CircuitKey,
+    road_map: Option<usize>, // depth of the distance table
+    parameters: SkateParameters, // mode and metric of the setup
+    proving_key: ProvingKey<Bn254>,
+    matrices: ConstraintMatrices<Fr>,
+    verifying_key: PreparedVerifyingKey<Bn254>,
+    plonk: Option<plonk::ProvingKey>,
+}
+
+impl CircuitProver {
+    pub fn load(circuit: &CircuitArtifacts) -> Result<CircuitProver> {
+        let zkey = circuit.path(Artifact::Zkey)?;
+        let file = File::open(zkey).map_err(|e| SkateError::Artifact(format!("{}: {}", zkey.display(), e)))?;
+        let (proving_key, matrices) = read_zkey(&mut BufReader::new(file))
+            .map_err(|e| SkateError::Artifact(format!("{}: {}", zkey.display(), e)))?;
+        if matrices.num_instance_variables != circuit.public_inputs() + 1 {
+            return Err(SkateError::Artifact(format!(
+                "{}: {} public inputs, circuit {} has {}",
+                zkey.display(),
+                matrices.num_instance_variables - 1,
+                circuit.key(),
+                circuit.public_inputs()
+            )));
+        }
+        Ok(CircuitProver {
+            key: circuit.key(),
+            road_map: circuit.road_map_depth(),
+            parameters: circuit.parameters(),
+            verifying_key: prepare_verifying_key(&proving_key.vk),
+            proving_key,
+            matrices,
+            plonk: None,
+        })
+    }
+
+    // Loads the PLONK keys of the circuit as well, the zkey matrices lack the C matrix they need
+    pub fn load_with_srs(circuit: &CircuitArtifacts, srs: &Srs) -> Result<CircuitProver> {
+        let mut prover = CircuitProver::load(circuit)?;
+        prover.plonk = Some(plonk::preprocess(srs, &circuit_matrices(circuit)?)?);
+        Ok(prover)
+    }
+
+    pub fn key(&self) -> &CircuitKey {
+        &self.key
+    }
+
+    pub fn proving_key(&self) -> &ProvingKey<Bn254> {
+        &self.proving_key
+    }
+
+    pub fn verifying_key(&self) -> &PreparedVerifyingKey<Bn254> {
+        &self.verifying_key
+    }
+
+    // Full assignment of the circuit: the constant 1, the public inputs, then the private wires
+    pub fn witness(&self, inputs: &RollupInputs) -> Result<Vec<Fr>> {
+        inputs.check(self.key.robots, self.key.tasks, self.road_map)?;
+        RollupCircuit::from_inputs(inputs, self.key.robots, self.key.tasks, &self.parameters, self.road_map)?
+            .assignment()
+    }
+
+    pub fn prove_witness<R: RngCore + CryptoRng>(&self, witness: &[Fr], rng: &mut R) -> Result<Proof<Bn254>> {
+        GrothBn::create_proof_with_reduction_and_matrices(
+            &self.proving_key,
+            Fr::rand(rng),
+            Fr::rand(rng),
+            &self.matrices,
+            self.matrices.num_instance_variables,
+            self.matrices.num_constraints,
+            witness,
+        )
+        .map_err(|e| SkateError::Proof(e.to_string()))
+    }
+
+    // Proof of a round with its public inputs, in the order of the circuit
+    pub fn prove<R: RngCore + CryptoRng>(
+        &self,
+        inputs: &RollupInputs,
+        rng: &mut R,
+    ) -> Result<(Proof<Bn254>, Vec<Fr>)> {
+        let witness = self.witness(inputs)?;
+        let proof = self.prove_witness(&witness, rng)?;
+        Ok((proof, witness[1..self.matrices.num_instance_variables].to_vec()))
+    }
+
+    // Proof of a round with the backend, as sent in the updates, with its public inputs
+    pub fn prove_with<R: RngCore + CryptoRng>(
+        &self,
+        backend: Backend,
+        inputs: &RollupInputs,
+        rng: &mut R,
+    ) -> Result<(Vec<String>, Vec<Fr>)> {
+        let witness = self.witness(inputs)?;
+        let proof = match backend {
+            Backend::Groth16 => Groth16Backend.encode(&self.prove_witness(&witness, rng)?),
+            Backend::Plonk => {
+                let pk = self.plonk.as_ref().ok_or_else(|| {
+                    SkateError::Artifact(format!("circuit {} has no PLONK keys", self.key))
+                })?;
+                plonk::prove(pk, &witness, rng)?.to_strings()
+            }
+        };
+        Ok((proof, witness[1..self.matrices.num_instance_variables].to_vec()))
+    }
+
+    pub fn verify(&self, public_inputs: &[Fr], proof: &Proof<Bn254>) -> Result<bool> {
+        GrothBn::verify_with_processed_vk(&self.verifying_key, public_inputs, proof)
+            .map_err(|e| SkateError::Proof(e.to_string()))
+    }
+}
+
+// The provers of several circuits, by circuit
+#[derive(Debug, Default)]
+pub struct Prover {
+    circuits: HashMap<CircuitKey, CircuitProver>,
+}
+
+impl Prover {
+    pub fn load<'a>(circuits: impl IntoIterator<Item = &'a CircuitArtifacts>) -> Result<Prover> {
+        let mut prover = Prover::default();
+        for circuit in circuits {
+            prover.circuits.insert(circuit.key(), CircuitProver::load(circuit)?);
+        }
+        Ok(prover)
+    }
+
+    pub fn load_with_srs<'a>(
+        circuits: impl IntoIterator<Item = &'a CircuitArtifacts>,
+        srs: &Srs,
+    ) -> Result<Prover> {
+        let mut prover = Prover::default();
+        for circuit in circuits {
+            prover.circuits.insert(circuit.key(), CircuitProver::load_with_srs(circuit, srs)?);
+        }
+        Ok(prover)
+    }
+
+    pub fn get(&self, key: &CircuitKey) -> Result<&CircuitProver> {
+        self.circuits
+            .get(key)
+            .ok_or_else(|| SkateError::Artifact(format!("circuit {} is not loaded", key)))
+    }
+
+    pub fn circuits(&self) -> impl Iterator<Item = &CircuitProver> {
+        self.circuits.values()
+    }
+
+    pub fn prove<R: RngCore + CryptoRng>(
+        &self,
+        key: &CircuitKey,
+        inputs: &RollupInputs,
+        rng: &mut R,
+    ) -> Result<(Proof<Bn254>, Vec<Fr>)> {
+        self.get(key)?.prove(inputs, rng)
+    }
+
+    pub fn prove_with<R: RngCore + CryptoRng>(
+        &self,
+        key: &CircuitKey,
+        backend: Backend,
+        inputs: &RollupInputs,
+        rng: &mut R,
+    ) -> Result<(Vec<String>, Vec<Fr>)> {
+        self.get(key)?.prove_with(backend, inputs, rng)
+    }
+}
+
+// Proves the rounds of the simulator with the loaded circuit of their size and road map, whatever its version
+impl RoundProver for Prover {
+    fn prove_round(
+        &mut self,
+        round: u64,
+        robots: &Vec<Robot>,
+        tasks: &Vec<Task>,
+        new_robots: &Vec<Robot>,
+        parameters: &SkateParameters,
+        task_state: &TaskStateWitness,
+        road_map: Option<&DistanceTable>,
+    ) -> Result<bool> {
+        let circuit = self
+            .circuits
+            .values()
+            .find(|circuit| {
+                circuit.key.robots == robots.len()
+                    && circuit.key.tasks == tasks.len()
+                    && circuit.road_map.is_some() == road_map.is_some()
+            })
+            .ok_or_else(|| {
+                SkateError::Artifact(format!("no circuit for {} robots and {} tasks", robots.len(), tasks.len()))
+            })?;
+        let mut inputs = build_rollup_inputs(robots, tasks, robot_root(robots)?.0, task_root(tasks)?)
+            .with_round(tasks, Fr::from(round), parameters, task_state)?;
+        if let Some(table) = road_map {
+            inputs = inputs.with_road_map(&road_map_witness(table, &distance_table_root(table)?, robots, tasks)?);
+        }
+        let (proof, public_inputs) = circuit.prove(&inputs, &mut thread_rng())?;
+        Ok(public_inputs[0] == robot_root(new_robots)?.0 && circuit.verify(&public_inputs, &proof)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashes::round_seed;
+    use crate::lifecycle::{task_state_witness, TaskState};
+    use crate::position::Position;
+    use crate::setup::matrix_parameters;
+    use crate::zkey::write_zkey;
+    use crate::Skate::{create_robot, create_task, skate_assignments, AssignmentMode, DistanceMetric, IDLE_TASK_ID};
+    use ark_std::rand::rngs::StdRng;
+    use ark_std::rand::SeedableRng;
+    use std::sync::Arc;
+
+    // The 1 x 1 circuit set up as by `setup native`, its zkey in a temporary file the caller removes
+    fn circuit(name: &str) -> CircuitArtifacts {
+        let mut circuit = CircuitArtifacts {
+            robots: 1,
+            tasks: 1,
+            version: "v1".to_string(),
+            road_map: false,
+            depth: 4,
+            zkey: None,
+            verifying_key: None,
+            mode: AssignmentMode::Distance,
+            metric: DistanceMetric::SquaredEuclidean,
+        };
+        let matrices = circuit_matrices(&circuit).unwrap();
+        let pk = matrix_parameters(&matrices, &mut StdRng::seed_from_u64(0)).unwrap();
+        let path = std::env::temp_dir().join(format!("skate_prover_{}_{}.zkey", name, std::process::id()));
+        write_zkey(&path, &pk, &matrices).unwrap();
+        circuit.zkey = Some(path);
+        circuit
+    }
+
+    fn remove(circuit: &CircuitArtifacts) {
+        std::fs::remove_file(circuit.zkey.as_ref().unwrap()).unwrap();
+    }
+
+    // A round of n idle robots and as many pending tasks
+    fn round(n: usize) -> RollupInputs {
+        let robots: Vec<Robot> = (0..n as u64)
+            .map(|i| {
+                let position = Position::new(2 * i, 0, 0).unwrap();
+                create_robot(Fr::from(i), [Fr::from(IDLE_TASK_ID); 2], [Position::default(), position]).unwrap()
+            })
+            .collect();
+        let tasks: Vec<Task> =
+            (0..n as u64).map(|j| create_task(Fr::from(j), Position::new(3 * j + 1, 1, 0).unwrap()).unwrap()).collect();
+        let root = robot_root(&robots).unwrap().0;
+        let parameters = SkateParameters { seed: round_seed(root, Fr::from(1)), ..SkateParameters::default() };
+        let states = (0..n as u64).map(|j| (Fr::from(j), TaskState::Pending.value())).collect();
+        let assignments = skate_assignments(&robots, &tasks, &parameters, None).unwrap();
+        let task_state = task_state_witness(&states, &robots, &tasks, &assignments).unwrap();
+        build_rollup_inputs(&robots, &tasks, root, task_root(&tasks).unwrap())
+            .with_round(&tasks, Fr::from(1), &parameters, &task_state)
+            .unwrap()
+    }
+
+    #[test]
+    fn rounds_are_proven_with_the_loaded_circuit() {
+        let circuit = circuit("loaded");
+        let loaded = CircuitProver::load(&circuit);
+        // The zkey of the circuit for a circuit with the road map, one public input more
+        let road_map = CircuitArtifacts { road_map: true, metric: DistanceMetric::Graph, ..circuit.clone() };
+        let mismatch = CircuitProver::load(&road_map);
+        remove(&circuit);
+        assert!(matches!(mismatch, Err(SkateError::Artifact(e)) if e.contains("public inputs")));
+        assert!(CircuitProver::load(&circuit).is_err());
+
+        let prover = loaded.unwrap();
+        assert_eq!(prover.key().to_string(), "v1/1_1");
+        let mut rng = StdRng::seed_from_u64(1);
+        let inputs = round(1);
+        // The same prover for several proofs
+        for _ in 0..2 {
+            let (proof, public_inputs) = prover.prove(&inputs, &mut rng).unwrap();
+            assert_eq!(public_inputs.len(), circuit.public_inputs());
+            assert!(prover.verify(&public_inputs, &proof).unwrap());
+            let mut wrong = public_inputs.clone();
+            wrong[0] += Fr::from(1);
+            assert!(!prover.verify(&wrong, &proof).unwrap());
+        }
+
+        // A round of another size, and PLONK without its keys
+        assert!(prover.prove(&round(2), &mut rng).is_err());
+        assert!(prover.prove_with(Backend::Plonk, &inputs, &mut rng).is_err());
+    }
+
+    #[test]
+    fn provers_are_shared_between_threads() {
+        let circuit = circuit("shared");
+        let prover = CircuitProver::load(&circuit);
+        remove(&circuit);
+        let prover = Arc::new(prover.unwrap());
+        let inputs = Arc::new(round(1));
+
+        let threads: Vec<_> = (0..2u64)
+            .map(|seed| {
+                let (prover, inputs) = (prover.clone(), inputs.clone());
+                std::thread::spawn(move || prover.prove(&inputs, &mut StdRng::seed_from_u64(seed)).unwrap())
+            })
+            .collect();
+        let proofs: Vec<_> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+        assert_ne!(proofs[0].0, proofs[1].0);
+        for (proof, public_inputs) in &proofs {
+            assert_eq!(public_inputs, &proofs[0].1);
+            assert!(prover.verify(public_inputs, proof).unwrap());
+        }
+    }
+}
@@ -0,0 +1,424 @@
+use crate::error::{Result, SkateError};
+use crate::verifying_key::read_verifier_file;
+use crate::Skate::{AssignmentMode, DistanceMetric, SkateParameters};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/* Circuits known to the coordinator and the robots, configured in a TOML file (circuits.toml):
+
+       [[circuit]]
+       robots = 3
+       tasks = 6
+       version = "v1"
+       road_map = false  # rollup of the road map, with the road map root as last public input
+       depth = 4         # depth of the distance table of the road map, at most 2^depth entries
+       zkey = "skate_6_3.zkey"
+       verifying_key = "verification_key_6_3.json"
+
+   Relative paths are relative to the registry file. Every circuit is the rollup::RollupCircuit of its
+   size: its witness and its constraints are computed by the crate, and its zkey and verifying key come
+   from `setup native`. The coordinator needs the zkeys of its circuits and the robots only the verifying
+   keys, so each binary checks at startup the artifacts it uses (see CircuitRegistry::check).
+   A circuit is set up for its `mode` and `metric` (by default the distance mode and the squared
+   euclidean metric), which must be those of the coordinator. A circuit with the road map has the graph
+   metric */
+
+// Public inputs of the rollup, see rollup::RollupCircuit::public_inputs, and one more with the road map
+pub const PUBLIC_INPUTS: usize = 9;
+
+// A circuit as sent with the proofs, "<version>/<tasks>_<robots>"
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CircuitKey {
+    pub robots: usize,
+    pub tasks: usize,
+    pub version: String,
+}
+
+impl fmt::Display for CircuitKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}_{}", self.version, self.tasks, self.robots)
+    }
+}
+
+impl FromStr for CircuitKey {
+    type Err = SkateError;
+
+    fn from_str(s: &str) -> Result<CircuitKey> {
+        let invalid = || SkateError::Artifact(format!("invalid circuit {}", s));
+        let (version, size) = s.rsplit_once('/').ok_or_else(invalid)?;
+        let (tasks, robots) = size.split_once('_').ok_or_else(invalid)?;
+        Ok(CircuitKey {
+            robots: robots.parse().map_err(|_| invalid())?,
+            tasks: tasks.parse().map_err(|_| invalid())?,
+            version: version.to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Artifact {
+    Zkey,
+    VerifyingKey,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CircuitArtifacts {
+    pub robots: usize,
+    pub tasks: usize,
+    pub version: String,
+    #[serde(default)]
+    pub road_map: bool,
+    #[serde(default = "default_depth")]
+    pub depth: usize,
+    pub zkey: Option<PathBuf>,
+    pub verifying_key: Option<PathBuf>,
+    #[serde(default = "default_mode")]
+    pub mode: AssignmentMode,
+    #[serde(default = "default_metric")]
+    pub metric: DistanceMetric,
+}
+
+// A distance table of at most 16 entries, a road map of 4 nodes
+fn default_depth() -> usize {
+    4
+}
+
+fn default_mode() -> AssignmentMode {
+    AssignmentMode::Distance
+}
+
+fn default_metric() -> DistanceMetric {
+    DistanceMetric::SquaredEuclidean
+}
+
+impl CircuitArtifacts {
+    pub fn key(&self) -> CircuitKey {
+        CircuitKey {
+            robots: self.robots,
+            tasks: self.tasks,
+            version: self.version.clone(),
+        }
+    }
+
+    pub fn public_inputs(&self) -> usize {
+        PUBLIC_INPUTS + self.road_map as usize
+    }
+
+    // Depth of the distance table of a circuit with the road map
+    pub fn road_map_depth(&self) -> Option<usize> {
+        self.road_map.then_some(self.depth)
+    }
+
+    // Parameters the circuit is set up with, the values of the public inputs aside
+    pub fn parameters(&self) -> SkateParameters {
+        SkateParameters {
+            mode: self.mode,
+            metric: self.metric,
+            ..SkateParameters::default()
+        }
+    }
+
+    // Path of an artifact, an error if the registry does not give it
+    pub fn path(&self, artifact: Artifact) -> Result<&Path> {
+        match artifact {
+            Artifact::Zkey => self.zkey.as_deref(),
+            Artifact::VerifyingKey => self.verifying_key.as_deref(),
+        }
+        .ok_or_else(|| SkateError::Artifact(format!("no {:?} for circuit {}", artifact, self.key())))
+    }
+
+    // The artifacts exist and agree with the circuit on the number of public inputs
+    fn check(&self, artifacts: &[Artifact]) -> Result<()> {
+        let expected = self.public_inputs();
+        let mismatch = |path: &Path, found: usize| {
+            SkateError::Artifact(format!(
+                "{}: {} public inputs, circuit {} has {}",
+                path.display(),
+                found,
+                self.key(),
+                expected
+            ))
+        };
+        if self.road_map != (self.metric == DistanceMetric::Graph) {
+            return Err(SkateError::Artifact(format!(
+                "circuit {} uses the road map with the graph metric only",
+                self.key()
+            )));
+        }
+        for artifact in artifacts {
+            let path = self.path(*artifact)?;
+            match artifact {
+                Artifact::Zkey => {
+                    let public = zkey_header(path)?;
+                    if public != expected {
+                        return Err(mismatch(path, public));
+                    }
+                }
+                Artifact::VerifyingKey => {
+                    let json = read_verifier_file(path)?;
+                    if json.nPublic as usize != expected {
+                        return Err(mismatch(path, json.nPublic as usize));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CircuitRegistry {
+    #[serde(rename = "circuit", default)]
+    circuits: Vec<CircuitArtifacts>,
+}
+
+impl CircuitRegistry {
+    pub fn load(path: &Path) -> Result<CircuitRegistry> {
+        let content = std::fs::read_to_string(path).map_err(|e| io_error(path, e))?;
+        let mut registry: CircuitRegistry = toml::from_str(&content)
+            .map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for circuit in &mut registry.circuits {
+            for artifact in [&mut circuit.zkey, &mut circuit.verifying_key] {
+                if let Some(file) = artifact {
+                    *file = directory.join(&*file);
+                }
+            }
+        }
+        Ok(registry)
+    }
+
+    pub fn circuits(&self) -> &Vec<CircuitArtifacts> {
+        &self.circuits
+    }
+
+    pub fn get(&self, key: &CircuitKey) -> Option<&CircuitArtifacts> {
+        self.circuits.iter().find(|circuit| circuit.key() == *key)
+    }
+
+    // The circuit of a round, with the road map or not
+    pub fn find(&self, robots: usize, tasks: usize, version: &str, road_map: bool) -> Result<&CircuitArtifacts> {
+        let key = CircuitKey {
+            robots,
+            tasks,
+            version: version.to_string(),
+        };
+        match self.get(&key) {
+            Some(circuit) if circuit.road_map == road_map => Ok(circuit),
+            Some(_) => Err(SkateError::Artifact(format!(
+                "circuit {} {} the road map",
+                key,
+                if road_map { "does not use" } else { "uses" }
+            ))),
+            None => Err(SkateError::Artifact(format!("no circuit {} in the registry", key))),
+        }
+    }
+
+    // Every circuit is registered once, and the given artifacts of each one exist and fit together
+    pub fn check(&self, artifacts: &[Artifact]) -> Result<()> {
+        let mut keys = HashSet::new();
+        for circuit in &self.circuits {
+            if circuit.robots == 0 || circuit.tasks == 0 {
+                return Err(SkateError::Artifact(format!("circuit {} is empty", circuit.key())));
+            }
+            if !keys.insert(circuit.key()) {
+                return Err(SkateError::Artifact(format!("circuit {} registered twice", circuit.key())));
+            }
+            circuit.check(artifacts)?;
+        }
+        Ok(())
+    }
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> SkateError {
+    SkateError::Artifact(format!("{}: {}", path.display(), e))
+}
+
+fn open(path: &Path) -> Result<BufReader<File>> {
+    Ok(BufReader::new(File::open(path).map_err(|e| io_error(path, e))?))
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// Moves the reader to a section of an iden3 binary file as the zkey: magic, version, number of
+// sections, then each section as its type, its size and its content
+fn seek_section(reader: &mut BufReader<File>, magic: &[u8; 4], section: u32) -> std::io::Result<bool> {
+    let mut found = [0u8; 4];
+    reader.read_exact(&mut found)?;
+    if &found != magic {
+        return Ok(false);
+    }
+    let _version = read_u32(reader)?;
+    let sections = read_u32(reader)?;
+    for _ in 0..sections {
+        let kind = read_u32(reader)?;
+        let size = read_u64(reader)?;
+        if kind == section {
+            return Ok(true);
+        }
+        reader.seek(SeekFrom::Current(size as i64))?;
+    }
+    Ok(false)
+}
+
+// Number of public inputs in the Groth16 header of a zkey file: base and scalar field sizes and primes,
+// then the numbers of variables and of public inputs
+fn zkey_header(path: &Path) -> Result<usize> {
+    let mut reader = open(path)?;
+    let header = (|| -> std::io::Result<Option<usize>> {
+        if !seek_section(&mut reader, b"zkey", 2)? {
+            return Ok(None);
+        }
+        let n8q = read_u32(&mut reader)?;
+        reader.seek(SeekFrom::Current(n8q as i64))?;
+        let n8r = read_u32(&mut reader)?;
+        reader.seek(SeekFrom::Current(n8r as i64))?;
+        let _variables = read_u32(&mut reader)?;
+        let public = read_u32(&mut reader)?;
+        Ok(Some(public as usize))
+    })()
+    .map_err(|e| io_error(path, e))?;
+    header.ok_or_else(|| SkateError::Artifact(format!("{}: not a Groth16 zkey", path.display())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A registry file in its own directory, removed once loaded
+    fn load(name: &str, content: &str) -> Result<CircuitRegistry> {
+        let directory = std::env::temp_dir().join(format!("skate_registry_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("circuits.toml");
+        std::fs::write(&path, content).unwrap();
+        let registry = CircuitRegistry::load(&path);
+        std::fs::remove_dir_all(&directory).unwrap();
+        registry
+    }
+
+    // The fixture keys of snarkjs, of a circuit with one public input (see snarkjs::tests)
+    fn fixture(name: &str) -> String {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/snarkjs").join(name).display().to_string()
+    }
+
+    #[test]
+    fn registries_are_parsed() {
+        let registry = load(
+            "parsed",
+            r#"
+            [[circuit]]
+            robots = 3
+            tasks = 6
+            version = "v1"
+            zkey = "keys/skate_6_3.zkey"
+
+            [[circuit]]
+            robots = 2
+            tasks = 4
+            version = "v2"
+            road_map = true
+            depth = 5
+            mode = "fair"
+            metric = "graph"
+            verifying_key = "verification_key_4_2.json"
+            "#,
+        )
+        .unwrap();
+        let [first, second] = registry.circuits().as_slice() else { panic!("two circuits") };
+        assert_eq!(first.key().to_string(), "v1/6_3");
+        // Relative to the registry file
+        let zkey = first.path(Artifact::Zkey).unwrap();
+        assert!(zkey.starts_with(std::env::temp_dir()) && zkey.ends_with("keys/skate_6_3.zkey"));
+        assert!(first.path(Artifact::VerifyingKey).is_err());
+        assert_eq!((first.road_map, first.road_map_depth()), (false, None));
+        assert_eq!((first.mode, first.metric), (AssignmentMode::Distance, DistanceMetric::SquaredEuclidean));
+        assert_eq!(first.public_inputs(), PUBLIC_INPUTS);
+
+        assert_eq!(second.road_map_depth(), Some(5));
+        let parameters = second.parameters();
+        assert_eq!((parameters.mode, parameters.metric), (AssignmentMode::Fair, DistanceMetric::Graph));
+        assert_eq!(second.public_inputs(), PUBLIC_INPUTS + 1);
+        assert!(registry.check(&[]).is_ok());
+
+        let key = "v2/4_2".parse::<CircuitKey>().unwrap();
+        assert_eq!(registry.get(&key).unwrap().tasks, 4);
+        assert!(registry.find(2, 4, "v2", true).is_ok());
+        assert!(registry.find(2, 4, "v2", false).is_err());
+        assert!(registry.find(3, 6, "v2", false).is_err());
+        assert!("v1/6".parse::<CircuitKey>().is_err());
+        assert!("6_3".parse::<CircuitKey>().is_err());
+
+        // Unknown modes and missing fields
+        assert!(load("mode", "[[circuit]]\nrobots = 3\ntasks = 6\nversion = \"v1\"\nmode = \"fastest\"").is_err());
+        assert!(load("missing", "[[circuit]]\nrobots = 3\nversion = \"v1\"").is_err());
+        assert_eq!(load("empty", "").unwrap().circuits().len(), 0);
+    }
+
+    #[test]
+    fn circuits_are_registered_once() {
+        let circuit = "[[circuit]]\nrobots = 3\ntasks = 6\nversion = \"v1\"\n";
+        let registry = load("twice", &format!("{}{}", circuit, circuit)).unwrap();
+        assert!(matches!(registry.check(&[]), Err(SkateError::Artifact(e)) if e.contains("registered twice")));
+        // Another version or size is another circuit
+        let registry = load("versions", &format!("{}{}", circuit, circuit.replace("v1", "v2"))).unwrap();
+        assert!(registry.check(&[]).is_ok());
+        let registry = load("blank", "[[circuit]]\nrobots = 0\ntasks = 6\nversion = \"v1\"\n").unwrap();
+        assert!(registry.check(&[]).is_err());
+    }
+
+    #[test]
+    fn road_maps_go_with_the_graph_metric() {
+        let circuit = |fields: &str| format!("[[circuit]]\nrobots = 3\ntasks = 6\nversion = \"v1\"\n{}", fields);
+        for (fields, valid) in [
+            ("", true),
+            ("road_map = true\nmetric = \"graph\"", true),
+            ("road_map = true", false),
+            ("road_map = true\nmetric = \"manhattan\"", false),
+            ("metric = \"graph\"", false),
+            ("road_map = false\nmetric = \"graph\"", false),
+        ] {
+            let registry = load("road_map", &circuit(fields)).unwrap();
+            assert_eq!(registry.check(&[]).is_ok(), valid, "{}", fields);
+        }
+    }
+
+    #[test]
+    fn artifacts_agree_on_the_public_inputs() {
+        let registry = load(
+            "artifacts",
+            &format!(
+                "[[circuit]]\nrobots = 3\ntasks = 6\nversion = \"v1\"\nzkey = \"{}\"\nverifying_key = \"{}\"\n",
+                fixture("multiplier.zkey"),
+                fixture("multiplier_verification_key.json")
+            ),
+        )
+        .unwrap();
+        assert_eq!(zkey_header(&PathBuf::from(fixture("multiplier.zkey"))).unwrap(), 1);
+        assert!(registry.check(&[]).is_ok());
+        for artifact in [Artifact::Zkey, Artifact::VerifyingKey] {
+            let found = registry.check(&[artifact]);
+            let expected = format!("1 public inputs, circuit v1/6_3 has {}", PUBLIC_INPUTS);
+            assert!(matches!(found, Err(SkateError::Artifact(e)) if e.ends_with(&expected)), "{:?}", artifact);
+        }
+
+        // Not a zkey, and a missing file
+        assert!(zkey_header(&PathBuf::from(fixture("multiplier_verification_key.json"))).is_err());
+        let registry = load("absent", "[[circuit]]\nrobots = 3\ntasks = 6\nversion = \"v1\"\nzkey = \"none.zkey\"\n");
+        assert!(registry.unwrap().check(&[Artifact::Zkey]).is_err());
+    }
+}
@@ -0,0 +1,1624 @@
+use crate::error::{Result, SkateError};
+use crate::gadgets::{
+    enforce_equal, enforce_product, greater_eq, hash_2_to_1, hash_4_to_1, input, is_equal, is_zero,
+    less_eq, less_than, merkle_path_root, merkle_root, mul, num2bits, num2bits_strict, select, sum, witness,
+    Signal,
+};
+use crate::graph::{road_map_witness, DistanceTable, RoadMapWitness, TableEntry};
+use crate::hashes::{
+    distance_table_root, robot_root, round_seed, task_root, EMPTY_ROBOT_LEAF, EMPTY_TASK_LEAF, TASK_STATE_DEPTH,
+};
+use crate::inputs::RollupInputs;
+use crate::lifecycle::{busy_robots, is_busy, task_state_witness, TaskState, TaskStateWitness};
+use crate::position::Position;
+use crate::Skate::{
+    apply_assignments_with_busy, check_robot, check_task, create_robot, create_task, index_bits, prerequisite_values,
+    skate_assignments_with_busy, to_index, AssignmentMode, DistanceMetric, Robot, SkateParameters, Task,
+    CHARGE_TASK_ID, IDLE_TASK_ID, LOW_BATTERY_DIVISOR, MAX_PREREQUISITES, MAX_PRIORITY, MAX_WORKLOAD,
+    VERTICAL_WEIGHT,
+};
+use ark_bn254::Fr;
+use ark_ff::{Field, One, Zero};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, OptimizationGoal, SynthesisError, SynthesisMode,
+};
+use std::str::FromStr;
+
+/* Rollup circuit of a round, sized at runtime by the number of robots and tasks:
+    - the old robot root and the task root are recomputed from the committed robots and tasks
+    - deadlines and prerequisites are checked as in verify_inputs, the busy robots are found there
+    - the seed is the one of the old robot root and the round (see hashes::round_seed)
+    - the SKATE step (Skate and skate_core) gives the new robots, whose root is the first public input
+    - the tasks of the round are moved in the task-state tree (task_state_transition)
+   The public inputs are listed in public_inputs, the server checks a proof of a round against them with
+   the keys of the circuit of its size. The robot tree is padded as in hashes::robot_root, 2^(ilog2(n) + 1)
+   leaves. With a road map (the graph metric), the travel times are read from the committed distance table
+   (see table_entry), and its root is one more public input */
+
+type Synthesis<T> = std::result::Result<T, SynthesisError>;
+
+#[derive(Debug, Clone)]
+pub struct RollupCircuit {
+    pub robots: Vec<Robot>,
+    pub tasks: Vec<Task>,
+    pub parameters: SkateParameters,
+    pub round: Fr,
+    pub task_state: TaskStateWitness,
+    pub road_map: Option<RoadMapWitness>,
+    pub old_robots_root: Fr,
+    pub tasks_root: Fr,
+    pub new_robots_root: Fr,
+}
+
+impl RollupCircuit {
+    // `task_states` are the leaves of the task-state tree before the round (see TaskLedger::states)
+    pub fn new(
+        robots: &Vec<Robot>,
+        tasks: &Vec<Task>,
+        parameters: &SkateParameters,
+        round: Fr,
+        task_states: &Vec<(Fr, Fr)>,
+    ) -> Result<RollupCircuit> {
+        RollupCircuit::with_road_map(robots, tasks, parameters, round, task_states, None)
+    }
+
+    // The rollup of a round on a road map, whose metric must be the graph one
+    pub fn with_road_map(
+        robots: &Vec<Robot>,
+        tasks: &Vec<Task>,
+        parameters: &SkateParameters,
+        round: Fr,
+        task_states: &Vec<(Fr, Fr)>,
+        road_map: Option<&DistanceTable>,
+    ) -> Result<RollupCircuit> {
+        check_road_map(parameters, road_map.is_some())?;
+        // The seed of the parameters is replaced by the one the circuit enforces
+        let old_robots_root = robot_root(robots)?.0;
+        let parameters = SkateParameters {
+            seed: round_seed(old_robots_root, round),
+            ..*parameters
+        };
+        let busy = busy_robots(task_states, robots)?;
+        let assignments = skate_assignments_with_busy(robots, tasks, &parameters, road_map, &busy)?;
+        let new_robots =
+            apply_assignments_with_busy(robots, tasks, &assignments, parameters.metric, road_map, &busy)?;
+        let road_map = match road_map {
+            Some(table) => Some(road_map_witness(table, &distance_table_root(table)?, robots, tasks)?),
+            None => None,
+        };
+        Ok(RollupCircuit {
+            robots: robots.clone(),
+            tasks: tasks.clone(),
+            parameters,
+            round,
+            task_state: task_state_witness(task_states, robots, tasks, &assignments)?,
+            road_map,
+            old_robots_root,
+            tasks_root: task_root(tasks)?,
+            new_robots_root: robot_root(&new_robots)?.0,
+        })
+    }
+
+    // A circuit of n robots and m tasks with placeholder values, for the setup: the constraints do not
+    // depend on the values. With a road map, its distance table has 2^depth entries
+    pub fn blank(
+        n: usize,
+        m: usize,
+        parameters: &SkateParameters,
+        road_map_depth: Option<usize>,
+    ) -> Result<RollupCircuit> {
+        let mut robots = vec![];
+        for i in 0..n {
+            robots.push(create_robot(
+                Fr::from(i as u64),
+                [Fr::from(IDLE_TASK_ID); 2],
+                [Position::default(); 2],
+            )?);
+        }
+        let mut tasks = vec![];
+        let mut states = vec![];
+        for j in 0..m {
+            tasks.push(create_task(Fr::from(j as u64), Position::default())?);
+            states.push((Fr::from(j as u64), TaskState::Pending.value()));
+        }
+        // Every robot and task is at the origin, a single node is enough to run the round
+        let table = DistanceTable {
+            nodes: vec![Position::default()],
+            travel_times: vec![vec![Fr::zero()]],
+        };
+        let mut circuit = RollupCircuit::with_road_map(
+            &robots,
+            &tasks,
+            parameters,
+            Fr::zero(),
+            &states,
+            road_map_depth.map(|_| &table),
+        )?;
+        circuit.road_map = road_map_depth.map(|depth| RoadMapWitness::blank(n, m, depth));
+        Ok(circuit)
+    }
+
+    // The circuit of the inputs of a round of n robots and m tasks (with the road map, with a distance table
+    // of the given depth), set up with the mode and the metric of the parameters (see
+    // registry::CircuitArtifacts::parameters). The roots and the seed of the inputs must be those of their
+    // robots and tasks
+    pub fn from_inputs(
+        inputs: &RollupInputs,
+        n: usize,
+        m: usize,
+        parameters: &SkateParameters,
+        road_map_depth: Option<usize>,
+    ) -> Result<RollupCircuit> {
+        check_road_map(parameters, road_map_depth.is_some())?;
+        inputs.check(n, m, road_map_depth)?;
+        let signal = |name: &str| inputs.get(name).unwrap().as_slice();
+        let position = |coordinates: &[Fr]| {
+            Position::from_coordinates([coordinates[0], coordinates[1], coordinates[2]])
+                .ok_or_else(|| SkateError::Proof("position out of the grid".to_string()))
+        };
+        let depth = TASK_STATE_DEPTH;
+
+        let mut robots = vec![];
+        for i in 0..n {
+            let ids = &signal("old_robots_tasks_id")[2 * i..2 * i + 2];
+            let positions = &signal("old_robots_positions")[6 * i..6 * i + 6];
+            let robot = Robot {
+                robot_id: signal("robots_id")[i],
+                capabilities: signal("robots_capabilities")[i],
+                battery: signal("robots_battery")[i],
+                capacity: signal("robots_capacity")[i],
+                station: position(&signal("robots_station")[3 * i..3 * i + 3])?,
+                workload: signal("robots_workload")[i],
+                list_tasks: [
+                    create_task(ids[0], position(&positions[0..3])?)?,
+                    create_task(ids[1], position(&positions[3..6])?)?,
+                ],
+            };
+            check_robot(&robot)?;
+            robots.push(robot);
+        }
+        let mut tasks = vec![];
+        for j in 0..m {
+            let slots = &signal("tasks_prerequisites")[MAX_PREREQUISITES * j..MAX_PREREQUISITES * (j + 1)];
+            let task = Task {
+                task_id: signal("tasks_id")[j],
+                position: position(&signal("tasks_positions")[3 * j..3 * j + 3])?,
+                priority: signal("tasks_priority")[j],
+                deadline: signal("tasks_deadline")[j],
+                requirements: signal("tasks_requirements")[j],
+                team_size: signal("tasks_team")[j],
+                prerequisites: std::array::from_fn(|k| (!slots[k].is_zero()).then(|| slots[k] - Fr::one())),
+            };
+            check_task(&task)?;
+            tasks.push(task);
+        }
+
+        let paths = |name: &str, k: usize| signal(name)[depth * k..depth * (k + 1)].to_vec();
+        let task_state = TaskStateWitness {
+            root: signal("task_state_root")[0],
+            prerequisites: (0..m)
+                .map(|j| {
+                    std::array::from_fn(|k| {
+                        let slot = MAX_PREREQUISITES * j + k;
+                        (signal("prerequisites_state")[slot], paths("prerequisites_path", slot))
+                    })
+                })
+                .collect(),
+            robots: (0..n)
+                .map(|i| (signal("robots_task_leaf")[i], signal("robots_task_state")[i], paths("robots_task_path", i)))
+                .collect(),
+            tasks: (0..m).map(|j| paths("tasks_state_path", j)).collect(),
+            new_root: signal("new_task_state_root")[0],
+        };
+        let busy: Vec<bool> = robots
+            .iter()
+            .zip(&task_state.robots)
+            .map(|(robot, (leaf, state, _))| is_busy(robot.list_tasks[1].task_id, (*leaf, *state)))
+            .collect();
+
+        let round = signal("round")[0];
+        let old_robots_root = robot_root(&robots)?.0;
+        let tasks_root = task_root(&tasks)?;
+        let seed = round_seed(old_robots_root, round);
+        if old_robots_root != signal("old_robots_root")[0] || tasks_root != signal("tasks_root")[0] {
+            return Err(SkateError::Proof("the roots are not those of the robots and tasks".to_string()));
+        }
+        if Fr::from(seed) != signal("seed")[0] {
+            return Err(SkateError::Proof("the seed is not the one of the round".to_string()));
+        }
+        let bounded = |value: Fr, bits: u32| {
+            to_index(value, 1 << bits)
+                .map_err(|_| SkateError::Proof(format!("{} does not fit on {} bits", value, bits)))
+        };
+        let parameters = SkateParameters {
+            seed,
+            fairness_penalty: bounded(signal("fairness_penalty")[0], 16)? as u16,
+            workload_cap: bounded(signal("workload_cap")[0], 32)? as u32,
+            ..*parameters
+        };
+
+        let mut road_map = None;
+        if let Some(depth) = road_map_depth {
+            let entries = |names: [&str; 3], count: usize| {
+                (0..count)
+                    .map(|k| {
+                        Ok(TableEntry {
+                            travel_time: signal(names[0])[k],
+                            index: to_index(signal(names[2])[k], 1 << depth)?,
+                            path: signal(names[1])[depth * k..depth * (k + 1)].to_vec(),
+                        })
+                    })
+                    .collect::<Result<Vec<TableEntry>>>()
+            };
+            let rows = |entries: Vec<TableEntry>| entries.chunks(m).map(|row| row.to_vec()).collect();
+            road_map = Some(RoadMapWitness {
+                root: signal("road_map_root")[0],
+                depth,
+                travel: rows(entries(["travel_times", "travel_paths", "travel_indexes"], n * m)?),
+                returns: rows(entries(["return_times", "return_paths", "return_indexes"], n * m)?),
+                charges: entries(["charge_times", "charge_paths", "charge_indexes"], n)?,
+            });
+        }
+        // The robots only move between the positions of the entries
+        let table = road_map.as_ref().map(|road_map| road_map.table(&robots, &tasks)).transpose()?;
+        let assignments = skate_assignments_with_busy(&robots, &tasks, &parameters, table.as_ref(), &busy)?;
+        let new_robots =
+            apply_assignments_with_busy(&robots, &tasks, &assignments, parameters.metric, table.as_ref(), &busy)?;
+        Ok(RollupCircuit {
+            robots,
+            tasks,
+            parameters,
+            round,
+            task_state,
+            road_map,
+            old_robots_root,
+            tasks_root,
+            new_robots_root: robot_root(&new_robots)?.0,
+        })
+    }
+
+    // Full assignment of the circuit: the constant 1, the public inputs, then the private variables, in
+    // the order of the matrices of setup::outlined_matrices
+    pub fn assignment(self) -> Result<Vec<Fr>> {
+        let cs = ConstraintSystem::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::Weight);
+        // The outlined variables are only assigned when the matrices are built
+        cs.set_mode(SynthesisMode::Prove { construct_matrices: true });
+        self.generate_constraints(cs.clone()).map_err(|e| SkateError::Proof(e.to_string()))?;
+        cs.finalize();
+        if !cs.is_satisfied().map_err(|e| SkateError::Proof(e.to_string()))? {
+            return Err(SkateError::Proof("the round does not satisfy the circuit".to_string()));
+        }
+        let cs = cs.borrow().unwrap();
+        Ok([cs.instance_assignment.clone(), cs.witness_assignment.clone()].concat())
+    }
+
+    // Public inputs of the proofs, the root of the distance table last with a road map
+    pub fn public_inputs(&self) -> Vec<Fr> {
+        let mut inputs = vec![
+            self.new_robots_root,
+            self.old_robots_root,
+            self.tasks_root,
+            self.round,
+            Fr::from(self.parameters.seed),
+            self.task_state.root,
+            Fr::from(self.parameters.fairness_penalty),
+            Fr::from(self.parameters.workload_cap),
+            self.task_state.new_root,
+        ];
+        inputs.extend(self.road_map.as_ref().map(|road_map| road_map.root));
+        inputs
+    }
+}
+
+// A road map goes with the graph metric, and the graph metric needs a road map
+fn check_road_map(parameters: &SkateParameters, road_map: bool) -> Result<()> {
+    match (parameters.metric == DistanceMetric::Graph, road_map) {
+        (true, false) => Err(SkateError::MissingRoadMap),
+        (false, true) => Err(SkateError::Proof("a road map needs the graph metric".to_string())),
+        _ => Ok(()),
+    }
+}
+
+fn constant(value: u64) -> Signal {
+    Signal::constant(Fr::from(value))
+}
+
+fn power_of_two(bits: u32) -> Fr {
+    Fr::from(2u64).pow([bits as u64])
+}
+
+#[derive(Debug, Clone)]
+struct RobotSignals {
+    id: Signal,
+    capabilities: Signal,
+    battery: Signal,
+    capacity: Signal,
+    station: [Signal; 3],
+    workload: Signal,
+    tasks_id: [Signal; 2],
+    positions: [[Signal; 3]; 2],
+}
+
+#[derive(Debug, Clone)]
+struct TaskSignals {
+    id: Signal,
+    position: [Signal; 3],
+    priority: Signal,
+    deadline: Signal,
+    requirements: Signal,
+    team: Signal,
+    prerequisites: [Signal; MAX_PREREQUISITES],
+}
+
+fn position_signals(cs: &ConstraintSystemRef<Fr>, position: Position) -> Synthesis<[Signal; 3]> {
+    Ok([
+        witness(cs, position.x())?,
+        witness(cs, position.y())?,
+        witness(cs, position.z())?,
+    ])
+}
+
+impl RobotSignals {
+    fn new(cs: &ConstraintSystemRef<Fr>, robot: &Robot) -> Synthesis<RobotSignals> {
+        Ok(RobotSignals {
+            id: witness(cs, robot.robot_id)?,
+            capabilities: witness(cs, robot.capabilities)?,
+            battery: witness(cs, robot.battery)?,
+            capacity: witness(cs, robot.capacity)?,
+            station: position_signals(cs, robot.station)?,
+            workload: witness(cs, robot.workload)?,
+            tasks_id: [
+                witness(cs, robot.list_tasks[0].task_id)?,
+                witness(cs, robot.list_tasks[1].task_id)?,
+            ],
+            positions: [
+                position_signals(cs, robot.list_tasks[0].position)?,
+                position_signals(cs, robot.list_tasks[1].position)?,
+            ],
+        })
+    }
+}
+
+impl TaskSignals {
+    fn new(cs: &ConstraintSystemRef<Fr>, task: &Task) -> Synthesis<TaskSignals> {
+        let prerequisites = prerequisite_values(task);
+        Ok(TaskSignals {
+            id: witness(cs, task.task_id)?,
+            position: position_signals(cs, task.position)?,
+            priority: witness(cs, task.priority)?,
+            deadline: witness(cs, task.deadline)?,
+            requirements: witness(cs, task.requirements)?,
+            team: witness(cs, task.team_size)?,
+            prerequisites: [witness(cs, prerequisites[0])?, witness(cs, prerequisites[1])?],
+        })
+    }
+}
+
+// The id is on 64 bits and the positions below 10 000 (hash_task)
+fn check_waypoint(cs: &ConstraintSystemRef<Fr>, id: &Signal, position: &[Signal; 3]) -> Synthesis<()> {
+    num2bits(cs, id, 64)?;
+    let mut below = vec![];
+    for coordinate in position {
+        below.push(less_than(cs, coordinate, &constant(10000), 14)?);
+    }
+    enforce_equal(cs, &sum(&below), &constant(3))
+}
+
+fn hash_task(cs: &ConstraintSystemRef<Fr>, task: &TaskSignals) -> Synthesis<Signal> {
+    check_waypoint(cs, &task.id, &task.position)?;
+    num2bits(cs, &task.priority, 8)?;
+    num2bits(cs, &task.deadline, 32)?;
+    num2bits(cs, &task.requirements, 16)?;
+    num2bits(cs, &task.team, 4)?;
+    for prerequisite in &task.prerequisites {
+        num2bits(cs, prerequisite, 65)?;
+    }
+    let packed = sum(&[
+        &task.position[1] * power_of_two(14),
+        task.position[2].clone(),
+        &task.priority * power_of_two(28),
+        &task.deadline * power_of_two(36),
+        &task.requirements * power_of_two(68),
+        &task.team * power_of_two(84),
+        &task.prerequisites[0] * power_of_two(88),
+        &task.prerequisites[1] * power_of_two(153),
+    ]);
+    hash_2_to_1(cs, &(&(&task.id * power_of_two(14)) + &task.position[0]), &packed)
+}
+
+// The tasks of a robot only commit their id and position
+fn hash_waypoint(cs: &ConstraintSystemRef<Fr>, id: &Signal, position: &[Signal; 3]) -> Synthesis<Signal> {
+    check_waypoint(cs, id, position)?;
+    hash_2_to_1(
+        cs,
+        &(&(id * power_of_two(14)) + &position[0]),
+        &(&(&position[1] * power_of_two(14)) + &position[2]),
+    )
+}
+
+fn hash_robot(cs: &ConstraintSystemRef<Fr>, robot: &RobotSignals) -> Synthesis<Signal> {
+    let first = hash_waypoint(cs, &robot.tasks_id[0], &robot.positions[0])?;
+    let second = hash_waypoint(cs, &robot.tasks_id[1], &robot.positions[1])?;
+    num2bits(cs, &robot.capabilities, 16)?;
+    num2bits(cs, &robot.battery, 32)?;
+    num2bits(cs, &robot.capacity, 32)?;
+    num2bits(cs, &robot.workload, 32)?;
+    for coordinate in &robot.station {
+        num2bits(cs, coordinate, 14)?;
+    }
+    let station = sum(&[
+        &robot.station[0] * power_of_two(28),
+        &robot.station[1] * power_of_two(14),
+        robot.station[2].clone(),
+    ]);
+    let packed = sum(&[
+        robot.capabilities.clone(),
+        &robot.battery * power_of_two(16),
+        &robot.capacity * power_of_two(48),
+        &station * power_of_two(80),
+        &robot.workload * power_of_two(122),
+    ]);
+    hash_4_to_1(cs, [&robot.id, &first, &second, &packed])
+}
+
+// Leaves padded up to 2^(ilog2(length) + 1), as in hashes::robot_root and hashes::task_root
+fn padded_root(cs: &ConstraintSystemRef<Fr>, mut leaves: Vec<Signal>, padding: &str) -> Synthesis<Signal> {
+    let length = 2usize.pow(leaves.len().ilog2() + 1);
+    while leaves.len() < length {
+        leaves.push(Signal::constant(Fr::from_str(padding).unwrap()));
+    }
+    merkle_root(cs, leaves)
+}
+
+fn robots_root(cs: &ConstraintSystemRef<Fr>, robots: &[RobotSignals]) -> Synthesis<Signal> {
+    let mut leaves = vec![];
+    for robot in robots {
+        leaves.push(hash_robot(cs, robot)?);
+    }
+    padded_root(cs, leaves, EMPTY_ROBOT_LEAF)
+}
+
+// Strictly increasing ids, each id sits on a single leaf
+fn tasks_root(cs: &ConstraintSystemRef<Fr>, tasks: &[TaskSignals]) -> Synthesis<Signal> {
+    let mut leaves = vec![];
+    for task in tasks {
+        leaves.push(hash_task(cs, task)?);
+    }
+    for pair in tasks.windows(2) {
+        let increasing = less_than(cs, &pair[0].id, &pair[1].id, 64)?;
+        enforce_equal(cs, &increasing, &Signal::one())?;
+    }
+    padded_root(cs, leaves, EMPTY_TASK_LEAF)
+}
+
+// Leaf index of a task id on 64 bits, its low TASK_STATE_DEPTH bits (hashes::task_state_slot)
+fn task_state_slot(cs: &ConstraintSystemRef<Fr>, task_id: &Signal) -> Synthesis<Vec<Signal>> {
+    let mut bits = num2bits(cs, task_id, 64)?;
+    bits.truncate(TASK_STATE_DEPTH);
+    Ok(bits)
+}
+
+// Root of the task-state tree from a leaf (task id, state) at a slot and its path (task_state_path)
+fn task_state_path(
+    cs: &ConstraintSystemRef<Fr>,
+    slot: &[Signal],
+    task_id: &Signal,
+    state: &Signal,
+    path: &[Signal],
+) -> Synthesis<Signal> {
+    let leaf = hash_2_to_1(cs, task_id, state)?;
+    merkle_path_root(cs, &leaf, slot, path)
+}
+
+fn path_signals(cs: &ConstraintSystemRef<Fr>, path: &[Fr]) -> Synthesis<Vec<Signal>> {
+    let mut siblings = vec![];
+    for sibling in path {
+        siblings.push(witness(cs, *sibling)?);
+    }
+    Ok(siblings)
+}
+
+// verify_inputs: committed robots and tasks, deadlines and prerequisites. Returns the busy robots
+fn verify_inputs(
+    cs: &ConstraintSystemRef<Fr>,
+    robots: &[RobotSignals],
+    tasks: &[TaskSignals],
+    task_state: &TaskStateWitness,
+    old_robots_root: &Signal,
+    committed_tasks_root: &Signal,
+    round: &Signal,
+    task_state_root: &Signal,
+) -> Synthesis<Vec<Signal>> {
+    enforce_equal(cs, &robots_root(cs, robots)?, old_robots_root)?;
+    enforce_equal(cs, &tasks_root(cs, tasks)?, committed_tasks_root)?;
+
+    // No task has expired (deadline is 0 or deadline >= round)
+    for task in tasks {
+        let no_deadline = is_zero(cs, &task.deadline)?;
+        let not_expired = greater_eq(cs, &task.deadline, round, 32)?;
+        enforce_product(
+            cs,
+            &(&Signal::one() - &no_deadline),
+            &(&Signal::one() - &not_expired),
+            &Signal::zero(),
+        )?;
+    }
+
+    // The prerequisites of every task are done (state 3) in the task-state root (task_state_leaf)
+    for (task, proofs) in tasks.iter().zip(&task_state.prerequisites) {
+        for (prerequisite, (state, path)) in task.prerequisites.iter().zip(proofs) {
+            let enabled = &Signal::one() - &is_zero(cs, prerequisite)?;
+            let task_id = mul(cs, &enabled, &(prerequisite + -Fr::one()))?;
+            let state = witness(cs, *state)?;
+            let slot = task_state_slot(cs, &task_id)?;
+            let root = task_state_path(cs, &slot, &task_id, &state, &path_signals(cs, path)?)?;
+            enforce_product(cs, &(&root - task_state_root), &enabled, &Signal::zero())?;
+            let done = is_equal(cs, &state, &Signal::constant(TaskState::Done.value()))?;
+            enforce_product(cs, &enabled, &(&Signal::one() - &done), &Signal::zero())?;
+        }
+    }
+
+    // robot_busy: the leaf at the slot of the current task of a robot is this task, assigned or in
+    // progress. A retired task or a waypoint has the leaf of another task or the empty one
+    let mut busy = vec![];
+    for (robot, (leaf, state, path)) in robots.iter().zip(&task_state.robots) {
+        let slot = task_state_slot(cs, &robot.tasks_id[1])?;
+        let leaf = witness(cs, *leaf)?;
+        let state = witness(cs, *state)?;
+        let root = task_state_path(cs, &slot, &leaf, &state, &path_signals(cs, path)?)?;
+        enforce_equal(cs, &root, task_state_root)?;
+        let own = is_equal(cs, &leaf, &robot.tasks_id[1])?;
+        let assigned = is_equal(cs, &state, &Signal::constant(TaskState::Assigned.value()))?;
+        let started = is_equal(cs, &state, &Signal::constant(TaskState::InProgress.value()))?;
+        busy.push(mul(cs, &own, &(&assigned + &started))?);
+    }
+    Ok(busy)
+}
+
+// task_state_transition: each task of the round goes from pending to assigned if it got its robots, one
+// leaf after the other. Returns the task-state root after the round
+fn task_state_transition(
+    cs: &ConstraintSystemRef<Fr>,
+    tasks: &[TaskSignals],
+    assignment: &[Signal],
+    paths: &[Vec<Fr>],
+    task_state_root: &Signal,
+) -> Synthesis<Signal> {
+    let mut root = task_state_root.clone();
+    for (k, (task, path)) in tasks.iter().zip(paths).enumerate() {
+        let mut count = vec![];
+        for robot in assignment {
+            count.push(is_equal(cs, robot, &constant(k as u64))?);
+        }
+        let assigned = &Signal::one() - &is_zero(cs, &sum(&count))?;
+        let path = path_signals(cs, path)?;
+        let slot = task_state_slot(cs, &task.id)?;
+        let before = task_state_path(cs, &slot, &task.id, &Signal::constant(TaskState::Pending.value()), &path)?;
+        enforce_equal(cs, &before, &root)?;
+        root = task_state_path(cs, &slot, &task.id, &assigned, &path)?;
+    }
+    Ok(root)
+}
+
+// |a - b| for a and b on 14 bits
+fn abs_diff(cs: &ConstraintSystemRef<Fr>, a: &Signal, b: &Signal) -> Synthesis<Signal> {
+    let less = less_than(cs, a, b, 14)?;
+    mul(cs, &(a - b), &(&Signal::one() - &(&less * Fr::from(2u64))))
+}
+
+// max(a, b) for a and b on 14 bits
+fn max_14(cs: &ConstraintSystemRef<Fr>, a: &Signal, b: &Signal) -> Synthesis<Signal> {
+    let less = less_than(cs, a, b, 14)?;
+    Ok(a + &mul(cs, &less, &(b - a))?)
+}
+
+fn distance(
+    cs: &ConstraintSystemRef<Fr>,
+    metric: DistanceMetric,
+    from: &[Signal; 3],
+    to: &[Signal; 3],
+) -> Synthesis<Signal> {
+    match metric {
+        DistanceMetric::Manhattan | DistanceMetric::Chebyshev => {
+            let mut delta = vec![];
+            for k in 0..3 {
+                delta.push(abs_diff(cs, &from[k], &to[k])?);
+            }
+            if metric == DistanceMetric::Manhattan {
+                Ok(sum(&delta))
+            } else {
+                let max_xy = max_14(cs, &delta[0], &delta[1])?;
+                max_14(cs, &max_xy, &delta[2])
+            }
+        }
+        _ => {
+            let mut square = vec![];
+            for k in 0..3 {
+                let delta = &from[k] - &to[k];
+                square.push(mul(cs, &delta, &delta)?);
+            }
+            Ok(match metric {
+                DistanceMetric::Weighted => {
+                    &(&square[0] + &square[1]) + &(&square[2] * Fr::from(VERTICAL_WEIGHT))
+                }
+                DistanceMetric::Planar => &square[0] + &square[1],
+                _ => sum(&square),
+            })
+        }
+    }
+}
+
+// table_entry: the travel time between two positions is a leaf of the distance table, checked if enabled
+fn table_entry(
+    cs: &ConstraintSystemRef<Fr>,
+    from: &[Signal; 3],
+    to: &[Signal; 3],
+    entry: &TableEntry,
+    root: &Signal,
+    enabled: &Signal,
+) -> Synthesis<Signal> {
+    let index = witness(cs, Fr::from(entry.index as u64))?;
+    let bits = num2bits(cs, &index, entry.path.len())?;
+    let travel_time = witness(cs, entry.travel_time)?;
+    num2bits(cs, &travel_time, DistanceMetric::Graph.bits() as usize)?;
+    let pack = |position: &[Signal; 3]| {
+        sum(&[
+            &position[0] * power_of_two(28),
+            &position[1] * power_of_two(14),
+            position[2].clone(),
+        ])
+    };
+    let leaf = hash_4_to_1(cs, [&pack(from), &pack(to), &travel_time, &Signal::zero()])?;
+    let node = merkle_path_root(cs, &leaf, &bits, &path_signals(cs, &entry.path)?)?;
+    enforce_product(cs, &(&node - root), enabled, &Signal::zero())?;
+    Ok(travel_time)
+}
+
+// Travel times of a road map, those to the stations are only checked for the robots with a battery
+struct TravelTimes {
+    travel: Vec<Vec<Signal>>,
+    returns: Vec<Vec<Signal>>,
+    charges: Vec<Signal>,
+}
+
+// graph_cost_matrix and graph_station_times: the entries of the round in the committed distance table
+fn travel_times(
+    cs: &ConstraintSystemRef<Fr>,
+    robots: &[RobotSignals],
+    tasks: &[TaskSignals],
+    road_map: &RoadMapWitness,
+    root: &Signal,
+) -> Synthesis<TravelTimes> {
+    let mut times = TravelTimes {
+        travel: vec![],
+        returns: vec![],
+        charges: vec![],
+    };
+    for (i, robot) in robots.iter().enumerate() {
+        let limited = &Signal::one() - &is_zero(cs, &robot.capacity)?;
+        let mut travel = vec![];
+        let mut returns = vec![];
+        for (j, task) in tasks.iter().enumerate() {
+            let entry = &road_map.travel[i][j];
+            travel.push(table_entry(cs, &robot.positions[1], &task.position, entry, root, &Signal::one())?);
+            let entry = &road_map.returns[i][j];
+            returns.push(table_entry(cs, &task.position, &robot.station, entry, root, &limited)?);
+        }
+        let entry = &road_map.charges[i];
+        times.charges.push(table_entry(cs, &robot.positions[1], &robot.station, entry, root, &limited)?);
+        times.travel.push(travel);
+        times.returns.push(returns);
+    }
+    Ok(times)
+}
+
+// charge_level: battery once charged, limited battery and return to the station
+struct ChargeLevel {
+    level: Signal,
+    limited: Signal,
+    low: Signal,
+}
+
+fn charge_level(cs: &ConstraintSystemRef<Fr>, robot: &RobotSignals) -> Synthesis<ChargeLevel> {
+    let mut at = vec![];
+    for k in 0..3 {
+        at.push(is_equal(cs, &robot.positions[1][k], &robot.station[k])?);
+    }
+    let at_xy = mul(cs, &at[0], &at[1])?;
+    let at_station = mul(cs, &at_xy, &at[2])?;
+    let limited = &Signal::one() - &is_zero(cs, &robot.capacity)?;
+    let refill = mul(cs, &at_station, &limited)?;
+    let level = &robot.battery + &mul(cs, &refill, &(&robot.capacity - &robot.battery))?;
+    let below = less_than(cs, &(&level * Fr::from(LOW_BATTERY_DIVISOR)), &robot.capacity, 34)?;
+    let low = mul(cs, &limited, &below)?;
+    Ok(ChargeLevel {
+        level,
+        limited,
+        low,
+    })
+}
+
+// workload_cap: 1 if the robot can take a task, never at MAX_WORKLOAD
+fn workload_allowed(
+    cs: &ConstraintSystemRef<Fr>,
+    mode: AssignmentMode,
+    robots: &[RobotSignals],
+    penalty: &Signal,
+    cap: &Signal,
+) -> Synthesis<Vec<Signal>> {
+    num2bits(cs, penalty, 16)?;
+    num2bits(cs, cap, 32)?;
+    let no_cap = is_zero(cs, cap)?;
+    let mut allowed = vec![];
+    for robot in robots {
+        let fair = if mode == AssignmentMode::Fair {
+            let below = less_than(cs, &robot.workload, cap, 32)?;
+            &no_cap + &mul(cs, &(&Signal::one() - &no_cap), &below)?
+        } else {
+            Signal::one()
+        };
+        let full = is_equal(cs, &robot.workload, &constant(MAX_WORKLOAD))?;
+        allowed.push(mul(cs, &fair, &(&Signal::one() - &full))?);
+    }
+    Ok(allowed)
+}
+
+// out = 1 if the capabilities cover the requirements, both given by their 16 bits
+fn compatible(cs: &ConstraintSystemRef<Fr>, capabilities: &[Signal], requirements: &[Signal]) -> Synthesis<Signal> {
+    let mut ok = Signal::one();
+    for (capability, requirement) in capabilities.iter().zip(requirements) {
+        let missing = mul(cs, requirement, &(&Signal::one() - capability))?;
+        ok = mul(cs, &ok, &(&Signal::one() - &missing))?;
+    }
+    Ok(ok)
+}
+
+// Sorted indexes of the keys of a column, checked by verify_sorting and verify_permutation
+fn rank_column(cs: &ConstraintSystemRef<Fr>, keys: &[Signal], bits: usize) -> Synthesis<Vec<Signal>> {
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_by_key(|i| keys[*i].value);
+    let mut rank = vec![];
+    for i in order {
+        rank.push(witness(cs, Fr::from(i as u64))?);
+    }
+
+    let mut sorted = vec![];
+    for index in &rank {
+        sorted.push(select(cs, keys, index)?);
+    }
+    for pair in sorted.windows(2) {
+        let less = less_eq(cs, &pair[0], &pair[1], bits)?;
+        enforce_equal(cs, &less, &Signal::one())?;
+    }
+
+    for value in 0..rank.len() {
+        let mut count = vec![];
+        for index in &rank {
+            count.push(is_equal(cs, &constant(value as u64), index)?);
+        }
+        enforce_equal(cs, &sum(&count), &Signal::one())?;
+    }
+    Ok(rank)
+}
+
+// task_choice: the available task ranked for the robot with the minimal key, m if none
+fn task_choice(
+    cs: &ConstraintSystemRef<Fr>,
+    ranks: &[Signal],
+    available: &[Signal],
+    robot: usize,
+    costs: &[Signal],
+    parameters: &SkateParameters,
+) -> Synthesis<Signal> {
+    let m = costs.len();
+    let log_m = index_bits(m);
+    let no_task_key = parameters.no_task_cost() * power_of_two(log_m);
+    let key: Vec<Signal> = costs
+        .iter()
+        .enumerate()
+        .map(|(i, cost)| &(cost * power_of_two(log_m)) + Fr::from(i as u64))
+        .collect();
+
+    let mut take = vec![];
+    for i in 0..m {
+        let eq = is_equal(cs, &ranks[i], &constant(robot as u64))?;
+        take.push(mul(cs, &eq, &available[i])?);
+    }
+
+    let min = (0..m)
+        .map(|i| if take[i].value.is_one() { key[i].value } else { no_task_key })
+        .min()
+        .unwrap();
+    let chosen = key.iter().position(|key| key.value == min).unwrap_or(m);
+    let mini = witness(cs, min)?;
+    let task_id = witness(cs, Fr::from(chosen as u64))?;
+
+    // The minimal key is below every key matching the robot
+    let bits = (parameters.cost_bits() + log_m) as usize;
+    for i in 0..m {
+        let bound = &mul(cs, &take[i], &(&key[i] + -no_task_key))? + no_task_key;
+        let less = less_eq(cs, &mini, &bound, bits)?;
+        enforce_equal(cs, &less, &Signal::one())?;
+    }
+
+    // The task id matches the minimal key
+    let none = is_equal(cs, &constant(m as u64), &task_id)?;
+    let index = mul(cs, &(&Signal::one() - &none), &task_id)?;
+    let selected = select(cs, &key, &index)?;
+    let expected = &mul(cs, &(&Signal::one() - &none), &mini)? + &mul(cs, &none, &key[0])?;
+    enforce_equal(cs, &selected, &expected)?;
+
+    // The chosen task matches the robot, and no task is chosen only if none matches
+    let selected_take = select(cs, &take, &index)?;
+    enforce_product(
+        cs,
+        &(&Signal::one() - &none),
+        &(&Signal::one() - &selected_take),
+        &Signal::zero(),
+    )?;
+    enforce_product(cs, &none, &sum(&take), &Signal::zero())?;
+    Ok(task_id)
+}
+
+// assign: m if the robot has no task, a task stays available until it has its team
+fn assign(
+    cs: &ConstraintSystemRef<Fr>,
+    ranks: &[Vec<Signal>],
+    costs: &[Vec<Signal>],
+    compatible: &[Vec<Signal>],
+    team: &[Signal],
+    parameters: &SkateParameters,
+) -> Synthesis<Vec<Signal>> {
+    let n = costs.len();
+    let m = team.len();
+    let mut robot_available = vec![Signal::one(); n];
+    let mut task_available = vec![Signal::one(); m];
+    let mut remaining = team.to_vec();
+    let mut assigned = vec![vec![]; n];
+    let mut chosen = vec![vec![]; n];
+
+    for i in 0..n {
+        let mut ok = vec![];
+        let mut next_available = vec![];
+        for j in 0..n {
+            let task_id = task_choice(cs, &ranks[i], &task_available, j, &costs[i], parameters)?;
+            let mut ok_j = vec![];
+            for k in 0..m {
+                let eq = is_equal(cs, &constant(k as u64), &task_id)?;
+                ok_j.push(mul(cs, &eq, &compatible[j][k])?);
+            }
+            let accept = sum(&ok_j);
+            let taken = mul(cs, &accept, &robot_available[j])?;
+            chosen[j].push(mul(cs, &taken, &task_id)?);
+            assigned[j].push(taken);
+            next_available.push(mul(cs, &robot_available[j], &(&Signal::one() - &accept))?);
+            ok.push(ok_j);
+        }
+        if i != n - 1 {
+            for k in 0..m {
+                let mut takers = vec![];
+                for j in 0..n {
+                    takers.push(mul(cs, &ok[j][k], &robot_available[j])?);
+                }
+                remaining[k] = &remaining[k] - &sum(&takers);
+                task_available[k] = &Signal::one() - &is_zero(cs, &remaining[k])?;
+            }
+        }
+        robot_available = next_available;
+    }
+
+    Ok((0..n)
+        .map(|j| &sum(&chosen[j]) + &(&(&Signal::one() - &sum(&assigned[j])) * Fr::from(m as u64)))
+        .collect())
+}
+
+// coalitions: the robots of a partial coalition get no task, a task ends with 0 or team robots
+fn coalitions(cs: &ConstraintSystemRef<Fr>, assign: &[Signal], team: &[Signal]) -> Synthesis<Vec<Signal>> {
+    let m = team.len();
+    let mut complete = vec![];
+    for k in 0..m {
+        let mut count = vec![];
+        for assignment in assign {
+            count.push(is_equal(cs, assignment, &constant(k as u64))?);
+        }
+        complete.push(is_equal(cs, &sum(&count), &team[k])?);
+    }
+    complete.push(Signal::one());
+
+    let mut assignment = vec![];
+    for robot in assign {
+        let formed = select(cs, &complete, robot)?;
+        let no_task = constant(m as u64);
+        assignment.push(&no_task + &mul(cs, &formed, &(robot - &no_task))?);
+    }
+
+    // Every task gets exactly the robots it needs, or none
+    for k in 0..m {
+        let mut count = vec![];
+        for robot in &assignment {
+            count.push(is_equal(cs, robot, &constant(k as u64))?);
+        }
+        let count = sum(&count);
+        enforce_product(cs, &count, &(&count - &team[k]), &Signal::zero())?;
+    }
+    Ok(assignment)
+}
+
+// Skate and skate_core: the robots once they moved to their task, or to their station, with the
+// assignment (m for no task). A busy robot keeps its task. The travel times come from the metric, or
+// from the road map with the graph metric
+fn skate(
+    cs: &ConstraintSystemRef<Fr>,
+    robots: &[RobotSignals],
+    busy: &[Signal],
+    tasks: &[TaskSignals],
+    times: Option<&TravelTimes>,
+    parameters: &SkateParameters,
+    seed: &Signal,
+    penalty: &Signal,
+    cap: &Signal,
+) -> Synthesis<(Vec<RobotSignals>, Vec<Signal>)> {
+    let (n, m) = (robots.len(), tasks.len());
+    let metric = parameters.metric;
+    let infeasible = parameters.infeasible_cost();
+
+    // Robots with a battery need an additive metric (DistanceMetric::is_additive)
+    if !metric.is_additive() {
+        for robot in robots {
+            enforce_equal(cs, &robot.capacity, &Signal::zero())?;
+        }
+    }
+
+    let mut distances = vec![];
+    let mut levels = vec![];
+    let mut to_station = vec![];
+    let mut charge = vec![];
+    let mut feasible = vec![];
+    for (i, (robot, busy)) in robots.iter().zip(busy).enumerate() {
+        let level = charge_level(cs, robot)?;
+        to_station.push(match times {
+            Some(times) => times.charges[i].clone(),
+            None => distance(cs, metric, &robot.positions[1], &robot.station)?,
+        });
+        // charge_move: a low robot that cannot reach its station stays where it is, as a busy one
+        let reachable = less_eq(cs, to_station.last().unwrap(), &level.level, 32)?;
+        let low = mul(cs, &level.low, &(&Signal::one() - busy))?;
+        charge.push(mul(cs, &low, &reachable)?);
+        let mut travel = vec![];
+        let mut feasible_i = vec![];
+        for (j, task) in tasks.iter().enumerate() {
+            let (there, back) = match times {
+                Some(times) => (times.travel[i][j].clone(), times.returns[i][j].clone()),
+                None => (
+                    distance(cs, metric, &robot.positions[1], &task.position)?,
+                    distance(cs, metric, &task.position, &robot.station)?,
+                ),
+            };
+            let enough = less_eq(cs, &(&there + &back), &level.level, 32)?;
+            let reachable = &mul(cs, &level.limited, &(&enough + -Fr::one()))? + Fr::one();
+            feasible_i.push(mul(cs, &(&Signal::one() - &level.low), &reachable)?);
+            travel.push(there);
+        }
+        distances.push(travel);
+        feasible.push(feasible_i);
+        levels.push(level);
+    }
+
+    // cost_matrix, the bits of the capabilities and requirements are shared by the pairs
+    let mut allowed = vec![];
+    for (fair, busy) in workload_allowed(cs, parameters.mode, robots, penalty, cap)?.iter().zip(busy) {
+        allowed.push(mul(cs, fair, &(&Signal::one() - busy))?);
+    }
+    let mut requirements = vec![];
+    for task in tasks {
+        requirements.push(num2bits(cs, &task.requirements, 16)?);
+    }
+    let mut cost = vec![];
+    let mut compatibility = vec![];
+    for i in 0..n {
+        let capabilities = num2bits(cs, &robots[i].capabilities, 16)?;
+        let extra = mul(cs, penalty, &robots[i].workload)?;
+        let mut cost_i = vec![];
+        let mut compatible_i = vec![];
+        for j in 0..m {
+            let allowed_ij = mul(cs, &feasible[i][j], &allowed[i])?;
+            let capable = compatible(cs, &capabilities, &requirements[j])?;
+            let compatible_ij = mul(cs, &capable, &allowed_ij)?;
+            let raw = match parameters.mode {
+                AssignmentMode::Distance => distances[i][j].clone(),
+                AssignmentMode::Priority => {
+                    let urgency = &(&constant(MAX_PRIORITY as u64) - &tasks[j].priority)
+                        * power_of_two(metric.bits());
+                    &distances[i][j] + &urgency
+                }
+                AssignmentMode::Fair => &distances[i][j] + &extra,
+            };
+            cost_i.push(&mul(cs, &compatible_ij, &(&raw + -infeasible))? + infeasible);
+            compatible_i.push(compatible_ij);
+        }
+        cost.push(cost_i);
+        compatibility.push(compatible_i);
+    }
+
+    // Rank keys: cost * 2^log_n + (robot index + seed) mod 2^log_n
+    num2bits(cs, seed, 32)?;
+    let log_n = index_bits(n);
+    let mut keys = vec![];
+    for i in 0..n {
+        let rotation = num2bits(cs, &(seed + Fr::from(i as u64)), 33)?;
+        let mut rotated = Signal::zero();
+        for b in 0..log_n as usize {
+            rotated = &rotated + &(&rotation[b] * power_of_two(b as u32));
+        }
+        keys.push(
+            cost[i]
+                .iter()
+                .map(|cost| &(cost * power_of_two(log_n)) + &rotated)
+                .collect::<Vec<Signal>>(),
+        );
+    }
+    let mut ranks = vec![vec![Signal::zero(); m]; n];
+    for j in 0..m {
+        let column: Vec<Signal> = keys.iter().map(|row| row[j].clone()).collect();
+        let rank = rank_column(cs, &column, (parameters.cost_bits() + log_n) as usize)?;
+        for i in 0..n {
+            ranks[i][j] = rank[i].clone();
+        }
+    }
+
+    let mut team = vec![];
+    for task in tasks {
+        team.push(&task.team + &is_zero(cs, &task.team)?);
+    }
+    let assignment = coalitions(
+        cs,
+        &assign(cs, &ranks, &cost, &compatibility, &team, parameters)?,
+        &team,
+    )?;
+
+    // The index m stands for "no task": the robot stays on its current task, or goes back to its
+    // station (CHARGE_TASK_ID) if it is low on battery and can reach it
+    let mut new_robots = vec![];
+    for i in 0..n {
+        let robot = &robots[i];
+        let level = &levels[i];
+        let charge = &charge[i];
+        let mut ids: Vec<Signal> = tasks.iter().map(|task| task.id.clone()).collect();
+        let charge_id = &constant(CHARGE_TASK_ID) - &robot.tasks_id[1];
+        ids.push(&robot.tasks_id[1] + &mul(cs, charge, &charge_id)?);
+        let new_id = select(cs, &ids, &assignment[i])?;
+
+        let mut new_position = vec![];
+        for k in 0..3 {
+            let mut coordinates: Vec<Signal> = tasks.iter().map(|task| task.position[k].clone()).collect();
+            let to_station = &robot.station[k] - &robot.positions[1][k];
+            coordinates.push(&robot.positions[1][k] + &mul(cs, charge, &to_station)?);
+            new_position.push(select(cs, &coordinates, &assignment[i])?);
+        }
+
+        // Every assigned pair must be compatible
+        let mut compatible_i = compatibility[i].clone();
+        compatible_i.push(Signal::one());
+        let compatible = select(cs, &compatible_i, &assignment[i])?;
+        enforce_equal(cs, &compatible, &Signal::one())?;
+
+        // battery_update and workload_update
+        let mut travel = distances[i].clone();
+        travel.push(mul(cs, charge, &to_station[i])?);
+        let used = select(cs, &travel, &assignment[i])?;
+        let battery = &level.level - &mul(cs, &level.limited, &used)?;
+        num2bits(cs, &battery, 32)?;
+        let idle = is_equal(cs, &assignment[i], &constant(m as u64))?;
+        let workload = &(&robot.workload + Fr::one()) - &idle;
+
+        new_robots.push(RobotSignals {
+            battery,
+            workload,
+            tasks_id: [robot.tasks_id[1].clone(), new_id],
+            positions: [
+                robot.positions[1].clone(),
+                [new_position[0].clone(), new_position[1].clone(), new_position[2].clone()],
+            ],
+            ..robot.clone()
+        });
+    }
+    Ok((new_robots, assignment))
+}
+
+impl ConstraintSynthesizer<Fr> for RollupCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Synthesis<()> {
+        let cs = &cs;
+        let new_robots_root = input(cs, self.new_robots_root)?;
+        let old_robots_root = input(cs, self.old_robots_root)?;
+        let committed_tasks_root = input(cs, self.tasks_root)?;
+        let round = input(cs, self.round)?;
+        let seed = input(cs, Fr::from(self.parameters.seed))?;
+        let task_state_root = input(cs, self.task_state.root)?;
+        let fairness_penalty = input(cs, Fr::from(self.parameters.fairness_penalty))?;
+        let workload_cap = input(cs, Fr::from(self.parameters.workload_cap))?;
+        let new_task_state_root = input(cs, self.task_state.new_root)?;
+        let road_map_root = match &self.road_map {
+            Some(road_map) => Some(input(cs, road_map.root)?),
+            None => None,
+        };
+
+        let mut robots = vec![];
+        for robot in &self.robots {
+            robots.push(RobotSignals::new(cs, robot)?);
+        }
+        let mut tasks = vec![];
+        for task in &self.tasks {
+            tasks.push(TaskSignals::new(cs, task)?);
+        }
+
+        let busy = verify_inputs(
+            cs,
+            &robots,
+            &tasks,
+            &self.task_state,
+            &old_robots_root,
+            &committed_tasks_root,
+            &round,
+            &task_state_root,
+        )?;
+        // round_seed: the low 32 bits of hash(old robot root, round)
+        let bits = num2bits_strict(cs, &hash_2_to_1(cs, &old_robots_root, &round)?)?;
+        let mut derived = Signal::zero();
+        for (b, bit) in bits.iter().take(32).enumerate() {
+            derived = &derived + &(bit * power_of_two(b as u32));
+        }
+        enforce_equal(cs, &derived, &seed)?;
+
+        let times = match (&self.road_map, &road_map_root) {
+            (Some(road_map), Some(root)) => Some(travel_times(cs, &robots, &tasks, road_map, root)?),
+            _ => None,
+        };
+        let (new_robots, assignment) = skate(
+            cs,
+            &robots,
+            &busy,
+            &tasks,
+            times.as_ref(),
+            &self.parameters,
+            &seed,
+            &fairness_penalty,
+            &workload_cap,
+        )?;
+        enforce_equal(cs, &robots_root(cs, &new_robots)?, &new_robots_root)?;
+        let root = task_state_transition(cs, &tasks, &assignment, &self.task_state.tasks, &task_state_root)?;
+        enforce_equal(cs, &root, &new_task_state_root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashes::task_state_root;
+    use crate::Skate::{create_robot_with_battery, skate_assignments};
+    use ark_std::rand::rngs::StdRng;
+    use ark_std::rand::{Rng, SeedableRng};
+
+    // A ledger of m pending tasks, with ids 0 to m - 1
+    fn pending(m: usize) -> Vec<(Fr, Fr)> {
+        (0..m).map(|id| (Fr::from(id as u64), TaskState::Pending.value())).collect()
+    }
+
+    fn is_satisfied(circuit: RollupCircuit) -> bool {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    fn robot(i: u64, position: Position) -> Robot {
+        create_robot(Fr::from(i), [Fr::from(IDLE_TASK_ID); 2], [Position::default(), position]).unwrap()
+    }
+
+    // Robots and tasks on a 2x2 square, most costs are tied
+    fn tied_round(rng: &mut StdRng, n: usize, m: usize) -> (Vec<Robot>, Vec<Task>) {
+        let corner = |rng: &mut StdRng| Position::new(rng.gen_range(0..2), rng.gen_range(0..2), 0).unwrap();
+        let robots = (0..n).map(|i| robot(i as u64, corner(rng))).collect();
+        let tasks = (0..m).map(|j| create_task(Fr::from(j as u64), corner(rng)).unwrap()).collect();
+        (robots, tasks)
+    }
+
+    #[test]
+    fn any_size_is_satisfied() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let spread = |rng: &mut StdRng| Position::new(rng.gen_range(0..10000), rng.gen_range(0..10000), 0).unwrap();
+        for (n, m) in [(1, 1), (2, 3), (5, 2), (3, 6)] {
+            let robots = (0..n).map(|i| robot(i as u64, spread(&mut rng))).collect();
+            let tasks = (0..m).map(|j| create_task(Fr::from(j as u64), spread(&mut rng)).unwrap()).collect();
+            let circuit = RollupCircuit::new(&robots, &tasks, &SkateParameters::default(), Fr::one(), &pending(m))
+                .unwrap();
+            let cs = ConstraintSystem::new_ref();
+            circuit.clone().generate_constraints(cs.clone()).unwrap();
+            assert!(cs.is_satisfied().unwrap(), "{} robots, {} tasks", n, m);
+            assert_eq!(cs.num_instance_variables(), 1 + circuit.public_inputs().len());
+
+            // The new root must be the one of the robots after the round
+            let mut wrong = circuit;
+            wrong.new_robots_root += Fr::one();
+            assert!(!is_satisfied(wrong), "{} robots, {} tasks", n, m);
+        }
+    }
+
+    // The new robot root of the circuit is the one of Skate::skate_assignments, so a satisfied circuit breaks
+    // the ties as the library does
+    #[test]
+    fn ties_are_broken_as_the_circuit_does() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let modes = [AssignmentMode::Distance, AssignmentMode::Priority, AssignmentMode::Fair];
+        let metrics = [
+            DistanceMetric::SquaredEuclidean,
+            DistanceMetric::Manhattan,
+            DistanceMetric::Chebyshev,
+            DistanceMetric::Weighted,
+            DistanceMetric::Planar,
+        ];
+        for _ in 0..24 {
+            let (n, m) = (rng.gen_range(1..5), rng.gen_range(1..4));
+            let (robots, tasks) = tied_round(&mut rng, n, m);
+            let parameters = SkateParameters {
+                mode: modes[rng.gen_range(0..modes.len())],
+                metric: metrics[rng.gen_range(0..metrics.len())],
+                ..SkateParameters::default()
+            };
+            let round = Fr::from(rng.gen_range(0..1000u64));
+            let circuit = RollupCircuit::new(&robots, &tasks, &parameters, round, &pending(m))
+                .unwrap();
+            assert!(is_satisfied(circuit), "{} robots, {} tasks, {:?}", n, m, parameters);
+        }
+    }
+
+    #[test]
+    fn rounds_rotate_the_tie_breaker() {
+        let position = Position::new(1, 1, 0).unwrap();
+        let robots = vec![robot(0, position), robot(1, position)];
+        let tasks = vec![create_task(Fr::from(0), Position::default()).unwrap()];
+        let mut winners = vec![];
+        for round in 0..16u64 {
+            let circuit = RollupCircuit::new(
+                &robots,
+                &tasks,
+                &SkateParameters::default(),
+                Fr::from(round),
+                &pending(2),
+            )
+            .unwrap();
+            let assignments = skate_assignments(&robots, &tasks, &circuit.parameters, None).unwrap();
+            winners.push(assignments.iter().position(|a| a.is_some()).unwrap());
+            assert!(is_satisfied(circuit));
+        }
+        assert!(winners.contains(&0) && winners.contains(&1), "{:?}", winners);
+    }
+
+    #[test]
+    fn fair_costs_match_the_circuit() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..16 {
+            let (n, m) = (rng.gen_range(1..5), rng.gen_range(1..4));
+            let grid = |rng: &mut StdRng| Position::new(rng.gen_range(0..20), rng.gen_range(0..20), 0).unwrap();
+            let robots: Vec<Robot> = (0..n)
+                .map(|i| Robot { workload: Fr::from(rng.gen_range(0..6u64)), ..robot(i as u64, grid(&mut rng)) })
+                .collect();
+            let tasks: Vec<Task> = (0..m).map(|j| create_task(Fr::from(j as u64), grid(&mut rng)).unwrap()).collect();
+            let parameters = SkateParameters {
+                mode: AssignmentMode::Fair,
+                metric: DistanceMetric::Manhattan,
+                fairness_penalty: rng.gen_range(0..40),
+                workload_cap: rng.gen_range(0..6),
+                ..SkateParameters::default()
+            };
+            let circuit = RollupCircuit::new(&robots, &tasks, &parameters, Fr::one(), &pending(m))
+                .unwrap();
+            assert!(is_satisfied(circuit), "{:?}", parameters);
+        }
+    }
+
+    #[test]
+    fn saturated_workloads_in_the_circuit() {
+        let full = Robot { workload: Fr::from(MAX_WORKLOAD), ..robot(0, Position::default()) };
+        let robots = vec![full, robot(1, Position::new(50, 0, 0).unwrap())];
+        let tasks = vec![create_task(Fr::from(1), Position::new(1, 0, 0).unwrap()).unwrap()];
+        for mode in [AssignmentMode::Distance, AssignmentMode::Fair] {
+            let parameters = SkateParameters { mode, ..SkateParameters::default() };
+            let circuit = RollupCircuit::new(&robots, &tasks, &parameters, Fr::one(), &pending(2))
+                .unwrap();
+            assert!(is_satisfied(circuit), "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn stranded_robots_stay_in_the_circuit() {
+        let station = Position::default();
+        let position = Position::new(100, 0, 0).unwrap();
+        let tasks = vec![create_task(Fr::from(1), Position::new(101, 0, 0).unwrap()).unwrap()];
+        let parameters = SkateParameters { metric: DistanceMetric::Manhattan, ..SkateParameters::default() };
+        // Able to reach the station (150 >= 100), then stranded (50 < 100)
+        for battery in [150u64, 50] {
+            let robot = create_robot_with_battery(
+                Fr::zero(),
+                Fr::zero(),
+                [Fr::zero(); 2],
+                [position; 2],
+                Fr::from(800),
+                station,
+            )
+            .unwrap();
+            let robots = vec![Robot { battery: Fr::from(battery), ..robot }];
+            let circuit =
+                RollupCircuit::new(&robots, &tasks, &parameters, Fr::one(), &pending(2)).unwrap();
+            assert!(is_satisfied(circuit), "battery {}", battery);
+        }
+    }
+
+    #[test]
+    fn seed_is_the_one_of_the_round() {
+        let robots = vec![robot(0, Position::new(1, 1, 0).unwrap())];
+        let tasks = vec![create_task(Fr::from(0), Position::default()).unwrap()];
+        let mut circuit =
+            RollupCircuit::new(&robots, &tasks, &SkateParameters::default(), Fr::from(3), &pending(2))
+                .unwrap();
+        assert_eq!(circuit.parameters.seed, round_seed(circuit.old_robots_root, Fr::from(3)));
+        assert!(is_satisfied(circuit.clone()));
+        // A single robot and task: any seed gives the same assignment, only the derivation rejects it
+        circuit.parameters.seed ^= 1;
+        assert!(!is_satisfied(circuit));
+    }
+
+    #[test]
+    fn busy_robots_keep_their_task() {
+        // Robot 0 holds task 0, next to task 1, robot 1 is idle
+        let position = Position::new(5, 5, 0).unwrap();
+        let busy = create_robot(Fr::zero(), [Fr::from(IDLE_TASK_ID), Fr::zero()], [position; 2]).unwrap();
+        let robots = vec![busy, robot(1, Position::new(40, 40, 0).unwrap())];
+        let tasks = vec![create_task(Fr::one(), Position::new(6, 5, 0).unwrap()).unwrap()];
+        let mut states = pending(2);
+        states[0].1 = TaskState::InProgress.value();
+
+        let circuit = RollupCircuit::new(&robots, &tasks, &SkateParameters::default(), Fr::one(), &states).unwrap();
+        let mut expected = states.clone();
+        expected[1].1 = TaskState::Assigned.value();
+        assert_eq!(circuit.task_state.new_root, task_state_root(&expected).unwrap().0);
+        assert_eq!(circuit.public_inputs()[8], circuit.task_state.new_root);
+        assert!(is_satisfied(circuit.clone()));
+
+        // The busy robot cannot be shown idle, nor the task left pending
+        let mut idle = circuit.clone();
+        idle.task_state.robots[0].1 = TaskState::Pending.value();
+        assert!(!is_satisfied(idle));
+        let mut unchanged = circuit;
+        unchanged.task_state.new_root = unchanged.task_state.root;
+        assert!(!is_satisfied(unchanged));
+    }
+
+    #[test]
+    fn task_ids_are_bound_to_their_slot() {
+        // Task 5 is retired, task 1029 took its slot (5 = 1029 mod 2^10) and is held by robot 0, robot 1 still
+        // points to task 5
+        let slot = 1u64 << TASK_STATE_DEPTH;
+        let position = Position::new(5, 5, 0).unwrap();
+        let holder = create_robot(Fr::zero(), [Fr::from(IDLE_TASK_ID), Fr::from(slot + 5)], [position; 2]).unwrap();
+        let former = create_robot(Fr::one(), [Fr::from(IDLE_TASK_ID), Fr::from(5)], [position; 2]).unwrap();
+        let robots = vec![holder, former];
+        let tasks = vec![create_task(Fr::from(2 * slot + 6), Position::new(6, 5, 0).unwrap()).unwrap()];
+        let states = vec![
+            (Fr::from(slot + 5), TaskState::InProgress.value()),
+            (Fr::from(2 * slot + 6), TaskState::Pending.value()),
+        ];
+        assert_eq!(busy_robots(&states, &robots).unwrap(), vec![true, false]);
+        let circuit = RollupCircuit::new(&robots, &tasks, &SkateParameters::default(), Fr::one(), &states).unwrap();
+        assert_eq!(circuit.task_state.robots[1].0, Fr::from(slot + 5));
+        assert!(is_satisfied(circuit.clone()));
+
+        // The leaf of the slot is the one of its task only: the holder cannot claim the retired task
+        let mut retired = circuit.clone();
+        retired.task_state.robots[0].0 = Fr::from(5);
+        assert!(!is_satisfied(retired));
+        // Two tasks of the same slot are not a tree
+        let states = vec![(Fr::from(6), TaskState::Done.value()), (Fr::from(2 * slot + 6), TaskState::Pending.value())];
+        assert_eq!(
+            RollupCircuit::new(&robots, &tasks, &SkateParameters::default(), Fr::one(), &states).unwrap_err(),
+            SkateError::SlotTaken(Fr::from(2 * slot + 6))
+        );
+    }
+
+    #[test]
+    fn round_tasks_must_be_pending() {
+        let robots = vec![robot(0, Position::default())];
+        let tasks = vec![create_task(Fr::zero(), Position::default()).unwrap()];
+        let mut states = pending(1);
+        states[0].1 = TaskState::Done.value();
+        let error = RollupCircuit::new(&robots, &tasks, &SkateParameters::default(), Fr::one(), &states).unwrap_err();
+        assert_eq!(error, SkateError::NotPending(Fr::zero()));
+    }
+
+    // A square of 4 nodes, a robot without battery limit at node 0 and one with a battery at node 2
+    fn road_map_round() -> (DistanceTable, Vec<Robot>, Vec<Task>) {
+        let node = |x, y| Position::new(x, y, 0).unwrap();
+        let nodes = vec![node(0, 0), node(10, 0), node(10, 10), node(0, 10)];
+        let table = crate::graph::RoadMap {
+            nodes: nodes.clone(),
+            edges: vec![(0, 1, 3), (1, 2, 4), (2, 3, 5), (3, 0, 6)],
+        }
+        .distance_table()
+        .unwrap();
+        let battery = create_robot_with_battery(
+            Fr::one(),
+            Fr::zero(),
+            [Fr::from(IDLE_TASK_ID); 2],
+            [nodes[2]; 2],
+            Fr::from(100),
+            nodes[3],
+        )
+        .unwrap();
+        let robots = vec![robot(0, nodes[0]), Robot { battery: Fr::from(40), ..battery }];
+        let tasks = vec![
+            create_task(Fr::zero(), nodes[1]).unwrap(),
+            create_task(Fr::one(), nodes[3]).unwrap(),
+        ];
+        (table, robots, tasks)
+    }
+
+    #[test]
+    fn road_map_rounds_read_the_distance_table() {
+        let (table, robots, tasks) = road_map_round();
+        for mode in [AssignmentMode::Distance, AssignmentMode::Priority, AssignmentMode::Fair] {
+            let parameters = SkateParameters { mode, metric: DistanceMetric::Graph, ..SkateParameters::default() };
+            let circuit =
+                RollupCircuit::with_road_map(&robots, &tasks, &parameters, Fr::one(), &pending(2), Some(&table))
+                    .unwrap();
+            let road_map = circuit.road_map.clone().unwrap();
+            assert_eq!(road_map.depth, 4);
+            assert_eq!(circuit.public_inputs().len(), 10);
+            assert_eq!(circuit.public_inputs()[9], distance_table_root(&table).unwrap().0);
+            assert!(is_satisfied(circuit.clone()), "{:?}", mode);
+
+            // Every travel time is checked against the root, those to the station only with a battery
+            let mut wrong = circuit.clone();
+            wrong.road_map.as_mut().unwrap().travel[0][1].travel_time += Fr::one();
+            assert!(!is_satisfied(wrong));
+            let mut wrong = circuit.clone();
+            wrong.road_map.as_mut().unwrap().returns[1][0].travel_time += Fr::one();
+            assert!(!is_satisfied(wrong));
+            let mut wrong = circuit.clone();
+            wrong.road_map.as_mut().unwrap().charges[1].path[0] += Fr::one();
+            assert!(!is_satisfied(wrong));
+            let mut unchecked = circuit.clone();
+            unchecked.road_map.as_mut().unwrap().returns[0][0].travel_time += Fr::one();
+            assert!(is_satisfied(unchecked));
+            let mut wrong = circuit;
+            wrong.road_map.as_mut().unwrap().root += Fr::one();
+            assert!(!is_satisfied(wrong));
+        }
+
+        // The graph metric and the road map go together
+        let graph = SkateParameters { metric: DistanceMetric::Graph, ..SkateParameters::default() };
+        let error = RollupCircuit::new(&robots, &tasks, &graph, Fr::one(), &pending(2)).unwrap_err();
+        assert_eq!(error, SkateError::MissingRoadMap);
+        let default = SkateParameters::default();
+        assert!(RollupCircuit::with_road_map(&robots, &tasks, &default, Fr::one(), &pending(2), Some(&table)).is_err());
+    }
+
+    #[test]
+    fn road_map_ties_rotate_with_the_rounds() {
+        let (table, _, tasks) = road_map_round();
+        // Both robots at the first node, the same travel time to the task
+        let robots: Vec<Robot> = (0..2).map(|i| robot(i, table.nodes[0])).collect();
+        let tasks = vec![tasks[0]];
+        let parameters = SkateParameters { metric: DistanceMetric::Graph, ..SkateParameters::default() };
+        let mut winners = vec![];
+        for round in 0..8u64 {
+            let circuit =
+                RollupCircuit::with_road_map(&robots, &tasks, &parameters, Fr::from(round), &pending(1), Some(&table))
+                    .unwrap();
+            let assignments = skate_assignments(&robots, &tasks, &circuit.parameters, Some(&table)).unwrap();
+            winners.push(assignments.iter().position(|a| a.is_some()).unwrap());
+            assert!(is_satisfied(circuit));
+        }
+        assert!(winners.contains(&0) && winners.contains(&1), "{:?}", winners);
+    }
+
+    #[test]
+    fn road_map_rounds_are_proven_from_their_inputs() {
+        use crate::graph::road_map_witness;
+        use crate::inputs::build_rollup_inputs;
+        use crate::setup::{matrix_parameters, outlined_matrices};
+        use ark_bn254::Bn254;
+        use ark_circom::CircomReduction;
+        use ark_groth16::{prepare_verifying_key, Groth16};
+        use ark_std::UniformRand;
+
+        let (table, robots, tasks) = road_map_round();
+        let parameters = SkateParameters { metric: DistanceMetric::Graph, ..SkateParameters::default() };
+        let circuit =
+            RollupCircuit::with_road_map(&robots, &tasks, &parameters, Fr::from(2), &pending(2), Some(&table))
+                .unwrap();
+        let road_map = road_map_witness(&table, &distance_table_root(&table).unwrap(), &robots, &tasks).unwrap();
+        let inputs = build_rollup_inputs(&robots, &tasks, circuit.old_robots_root, circuit.tasks_root)
+            .with_round(&tasks, circuit.round, &circuit.parameters, &circuit.task_state)
+            .unwrap()
+            .with_road_map(&road_map);
+        let native = RollupCircuit::from_inputs(&inputs, 2, 2, &parameters, Some(4)).unwrap();
+        assert_eq!(native.public_inputs(), circuit.public_inputs());
+        assert!(RollupCircuit::from_inputs(&inputs, 2, 2, &parameters, Some(3)).is_err());
+
+        let mut rng = StdRng::seed_from_u64(6);
+        let matrices = outlined_matrices(RollupCircuit::blank(2, 2, &parameters, Some(4)).unwrap()).unwrap();
+        let pk = matrix_parameters(&matrices, &mut rng).unwrap();
+        let witness = native.assignment().unwrap();
+        let proof = Groth16::<Bn254, CircomReduction>::create_proof_with_reduction_and_matrices(
+            &pk,
+            Fr::rand(&mut rng),
+            Fr::rand(&mut rng),
+            &matrices,
+            matrices.num_instance_variables,
+            matrices.num_constraints,
+            &witness,
+        )
+        .unwrap();
+        let vk = prepare_verifying_key(&pk.vk);
+        let mut public_inputs = circuit.public_inputs();
+        assert!(Groth16::<Bn254, CircomReduction>::verify_proof(&vk, &proof, &public_inputs).unwrap());
+        public_inputs[9] += Fr::one();
+        assert!(!Groth16::<Bn254, CircomReduction>::verify_proof(&vk, &proof, &public_inputs).unwrap());
+    }
+
+    #[test]
+    fn native_rounds_are_proven_from_their_inputs() {
+        use crate::inputs::build_rollup_inputs;
+        use crate::setup::{matrix_parameters, outlined_matrices};
+        use ark_bn254::Bn254;
+        use ark_circom::CircomReduction;
+        use ark_groth16::{prepare_verifying_key, Groth16};
+        use ark_std::UniformRand;
+
+        // Robot 0 is busy on task 0, as in busy_robots_keep_their_task
+        let position = Position::new(5, 5, 0).unwrap();
+        let busy = create_robot(Fr::zero(), [Fr::from(IDLE_TASK_ID), Fr::zero()], [position; 2]).unwrap();
+        let robots = vec![busy, robot(1, Position::new(40, 40, 0).unwrap())];
+        let tasks = vec![
+            create_task(Fr::one(), Position::new(6, 5, 0).unwrap()).unwrap(),
+            create_task(Fr::from(2), Position::new(30, 40, 0).unwrap()).unwrap(),
+        ];
+        let mut states = pending(3);
+        states[0].1 = TaskState::InProgress.value();
+        let circuit = RollupCircuit::new(&robots, &tasks, &SkateParameters::default(), Fr::from(7), &states).unwrap();
+
+        let inputs = build_rollup_inputs(&robots, &tasks, circuit.old_robots_root, circuit.tasks_root)
+            .with_round(&tasks, circuit.round, &circuit.parameters, &circuit.task_state)
+            .unwrap();
+        let native = RollupCircuit::from_inputs(&inputs, 2, 2, &SkateParameters::default(), None).unwrap();
+        assert_eq!(native.public_inputs(), circuit.public_inputs());
+
+        let mut rng = StdRng::seed_from_u64(5);
+        let blank = RollupCircuit::blank(2, 2, &SkateParameters::default(), None).unwrap();
+        let matrices = outlined_matrices(blank).unwrap();
+        let pk = matrix_parameters(&matrices, &mut rng).unwrap();
+        let witness = native.assignment().unwrap();
+        assert_eq!(witness.len(), matrices.num_instance_variables + matrices.num_witness_variables);
+        let proof = Groth16::<Bn254, CircomReduction>::create_proof_with_reduction_and_matrices(
+            &pk,
+            Fr::rand(&mut rng),
+            Fr::rand(&mut rng),
+            &matrices,
+            matrices.num_instance_variables,
+            matrices.num_constraints,
+            &witness,
+        )
+        .unwrap();
+        let vk = prepare_verifying_key(&pk.vk);
+        let public_inputs = circuit.public_inputs();
+        assert!(Groth16::<Bn254, CircomReduction>::verify_proof(&vk, &proof, &public_inputs).unwrap());
+
+        // A wrong public input fails the verification, inputs with a wrong root are rejected before proving
+        let mut wrong = public_inputs;
+        wrong[0] += Fr::one();
+        assert!(!Groth16::<Bn254, CircomReduction>::verify_proof(&vk, &proof, &wrong).unwrap());
+        let inputs = build_rollup_inputs(&robots, &tasks, circuit.old_robots_root + Fr::one(), circuit.tasks_root)
+            .with_round(&tasks, circuit.round, &circuit.parameters, &circuit.task_state)
+            .unwrap();
+        assert!(RollupCircuit::from_inputs(&inputs, 2, 2, &SkateParameters::default(), None).is_err());
+    }
+}
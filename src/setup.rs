@@ -0,0 +1,255 @@
+use crate::error::{Result, SkateError};
+use crate::verifying_key::{from_g1, from_g2, to_g1, to_g2};
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine};
+use ark_circom::circom::R1CS;
+use ark_circom::{CircomCircuit, CircomReduction};
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::Field;
+use ark_groth16::{Groth16, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::{
+    ConstraintMatrices, ConstraintSynthesizer, ConstraintSystem, OptimizationGoal, SynthesisMode,
+};
+use ark_std::rand::{CryptoRng, RngCore};
+use ark_std::UniformRand;
+use serde::{Deserialize, Serialize};
+
+/* Local Groth16 setup of the circuits, with keys proven with CircomReduction as the snarkjs ones (its
+   H query is in the Lagrange basis snarkjs uses, see ark_circom::CircomReduction), so they can be
+   written as a zkey (see zkey::write_zkey) and used by prover::CircuitProver.
+   The setup samples every secret on this machine. A ceremony then lets other parties re-randomize delta
+   in turn, as the phase 2 of snarkjs: each contribution multiplies delta by a secret s and divides the
+   L and H queries by it, and publishes [s]_1 and [s]_2 so that anyone can check the chain of deltas
+   (see verify_contributions). This does not make the keys sound once one contributor dropped its s:
+   there is no multi-party powers of tau here, and whoever kept tau (which the H query is built from),
+   alpha or beta of the first step can forge proofs whatever the contributions. The keys are only as
+   trustworthy as the machine that ran the first step */
+
+type GrothBn = Groth16<Bn254, CircomReduction>;
+
+// Matrices of the circuit as the prover uses them, see zkey::write_zkey
+pub fn constraint_matrices<C: ConstraintSynthesizer<Fr>>(circuit: C) -> Result<ConstraintMatrices<Fr>> {
+    matrices(circuit, OptimizationGoal::Constraints)
+}
+
+// Matrices of a native circuit (see rollup::RollupCircuit::assignment). Its linear combinations are
+// outlined into variables: inlined as the Constraints goal does, their rows get millions of terms
+pub fn outlined_matrices<C: ConstraintSynthesizer<Fr>>(circuit: C) -> Result<ConstraintMatrices<Fr>> {
+    matrices(circuit, OptimizationGoal::Weight)
+}
+
+fn matrices<C: ConstraintSynthesizer<Fr>>(circuit: C, goal: OptimizationGoal) -> Result<ConstraintMatrices<Fr>> {
+    let cs = ConstraintSystem::new_ref();
+    cs.set_optimization_goal(goal);
+    cs.set_mode(SynthesisMode::Setup);
+    circuit
+        .generate_constraints(cs.clone())
+        .map_err(|e| SkateError::Proof(e.to_string()))?;
+    cs.finalize();
+    cs.to_matrices()
+        .ok_or_else(|| SkateError::Proof("the constraint system has no matrices".to_string()))
+}
+
+pub fn generate_parameters<C, R>(circuit: C, rng: &mut R) -> Result<(ProvingKey<Bn254>, ConstraintMatrices<Fr>)>
+where
+    C: ConstraintSynthesizer<Fr> + Clone,
+    R: RngCore + CryptoRng,
+{
+    let pk = GrothBn::generate_random_parameters_with_reduction(circuit.clone(), rng)
+        .map_err(|e| SkateError::Proof(e.to_string()))?;
+    Ok((pk, constraint_matrices(circuit)?))
+}
+
+// Keys of given matrices, replayed as the constraints of an r1cs so that the setup keeps their variables
+pub fn matrix_parameters<R>(matrices: &ConstraintMatrices<Fr>, rng: &mut R) -> Result<ProvingKey<Bn254>>
+where
+    R: RngCore + CryptoRng,
+{
+    let row = |row: &Vec<(Fr, usize)>| row.iter().map(|(coefficient, index)| (*index, *coefficient)).collect();
+    let constraints = (0..matrices.num_constraints)
+        .map(|i| (row(&matrices.a[i]), row(&matrices.b[i]), row(&matrices.c[i])))
+        .collect();
+    let r1cs = R1CS::<Bn254> {
+        num_inputs: matrices.num_instance_variables,
+        num_aux: matrices.num_witness_variables,
+        num_variables: matrices.num_instance_variables + matrices.num_witness_variables,
+        constraints,
+        wire_mapping: None,
+    };
+    GrothBn::generate_random_parameters_with_reduction(CircomCircuit { r1cs, witness: None }, rng)
+        .map_err(|e| SkateError::Proof(e.to_string()))
+}
+
+// One step of the ceremony, points as in the snarkjs JSON files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Contribution {
+    pub name: String,
+    pub delta_1: Vec<String>, // delta once contributed
+    pub delta_2: Vec<Vec<String>>,
+    pub s_1: Vec<String>, // the new delta is s times the previous one
+    pub s_2: Vec<Vec<String>>,
+}
+
+fn scale_g1(points: &[G1Affine], scalar: Fr) -> Vec<G1Affine> {
+    let scaled: Vec<G1Projective> = points.iter().map(|point| *point * scalar).collect();
+    G1Projective::normalize_batch(&scaled)
+}
+
+pub fn contribute<R: RngCore + CryptoRng>(pk: &mut ProvingKey<Bn254>, name: &str, rng: &mut R) -> Contribution {
+    let s = Fr::rand(rng);
+    let s_inverse = s.inverse().unwrap();
+    pk.delta_g1 = (pk.delta_g1 * s).into_affine();
+    pk.vk.delta_g2 = (pk.vk.delta_g2 * s).into_affine();
+    pk.l_query = scale_g1(&pk.l_query, s_inverse);
+    pk.h_query = scale_g1(&pk.h_query, s_inverse);
+    Contribution {
+        name: name.to_string(),
+        delta_1: from_g1(&pk.delta_g1),
+        delta_2: from_g2(&pk.vk.delta_g2),
+        s_1: from_g1(&(G1Affine::generator() * s).into_affine()),
+        s_2: from_g2(&(G2Affine::generator() * s).into_affine()),
+    }
+}
+
+fn invalid(message: &str) -> SkateError {
+    SkateError::Artifact(message.to_string())
+}
+
+// The final key follows from the initial one through the contributions: each one multiplies delta by
+// its s, the L and H queries are divided by the product (checked on a random combination), nothing
+// else changed
+pub fn verify_contributions<R: RngCore + CryptoRng>(
+    initial: &ProvingKey<Bn254>,
+    last: &ProvingKey<Bn254>,
+    contributions: &[Contribution],
+    rng: &mut R,
+) -> Result<()> {
+    let g1 = G1Affine::generator();
+    let g2 = G2Affine::generator();
+    let (mut delta_1, mut delta_2) = (initial.delta_g1, initial.vk.delta_g2);
+    for contribution in contributions {
+        let error = |message: &str| SkateError::Artifact(format!("contribution of {}: {}", contribution.name, message));
+        let point_1 = |point: &Vec<String>| to_g1(point).ok_or_else(|| error("invalid point"));
+        let point_2 = |point: &Vec<Vec<String>>| to_g2(point).ok_or_else(|| error("invalid point"));
+        let (s_1, s_2) = (point_1(&contribution.s_1)?, point_2(&contribution.s_2)?);
+        let (next_1, next_2) = (point_1(&contribution.delta_1)?, point_2(&contribution.delta_2)?);
+        if s_1.is_zero() || Bn254::pairing(s_1, g2) != Bn254::pairing(g1, s_2) {
+            return Err(error("[s]_1 and [s]_2 differ"));
+        }
+        if Bn254::pairing(delta_1, s_2) != Bn254::pairing(next_1, g2)
+            || Bn254::pairing(s_1, delta_2) != Bn254::pairing(g1, next_2)
+        {
+            return Err(error("delta is not multiplied by s"));
+        }
+        (delta_1, delta_2) = (next_1, next_2);
+    }
+    if (last.delta_g1, last.vk.delta_g2) != (delta_1, delta_2) {
+        return Err(invalid("the delta of the key is not the last contributed one"));
+    }
+    let unchanged = initial.vk.alpha_g1 == last.vk.alpha_g1
+        && initial.vk.beta_g2 == last.vk.beta_g2
+        && initial.vk.gamma_g2 == last.vk.gamma_g2
+        && initial.vk.gamma_abc_g1 == last.vk.gamma_abc_g1
+        && initial.beta_g1 == last.beta_g1
+        && initial.a_query == last.a_query
+        && initial.b_g1_query == last.b_g1_query
+        && initial.b_g2_query == last.b_g2_query;
+    if !unchanged {
+        return Err(invalid("the contributions changed more than delta"));
+    }
+    for (before, after, name) in [
+        (&initial.l_query, &last.l_query, "L"),
+        (&initial.h_query, &last.h_query, "H"),
+    ] {
+        if before.len() != after.len() {
+            return Err(SkateError::Artifact(format!("the {} query changed size", name)));
+        }
+        let scalars: Vec<Fr> = (0..before.len()).map(|_| Fr::rand(rng)).collect();
+        let before = G1Projective::msm(before, &scalars).unwrap();
+        let after = G1Projective::msm(after, &scalars).unwrap();
+        if Bn254::pairing(before, initial.vk.delta_g2) != Bn254::pairing(after, last.vk.delta_g2) {
+            return Err(SkateError::Artifact(format!("the {} query is not divided by the contributions", name)));
+        }
+    }
+    Ok(())
+}
+
+// The verifying key is the one of the proving key, and the points given in both groups agree
+pub fn check_keys(pk: &ProvingKey<Bn254>, vk: &VerifyingKey<Bn254>) -> Result<()> {
+    if pk.vk.gamma_abc_g1.len() != vk.gamma_abc_g1.len() {
+        return Err(SkateError::Artifact(format!(
+            "{} public inputs in the verifying key, {} in the proving key",
+            vk.gamma_abc_g1.len() - 1,
+            pk.vk.gamma_abc_g1.len() - 1
+        )));
+    }
+    for (name, same) in [
+        ("alpha", pk.vk.alpha_g1 == vk.alpha_g1),
+        ("beta", pk.vk.beta_g2 == vk.beta_g2),
+        ("gamma", pk.vk.gamma_g2 == vk.gamma_g2),
+        ("delta", pk.vk.delta_g2 == vk.delta_g2),
+        ("IC", pk.vk.gamma_abc_g1 == vk.gamma_abc_g1),
+    ] {
+        if !same {
+            return Err(SkateError::Artifact(format!("{} differs between the keys", name)));
+        }
+    }
+    // The generators of the setup are random, beta and delta are checked against each other
+    if Bn254::pairing(pk.beta_g1, pk.vk.delta_g2) != Bn254::pairing(pk.delta_g1, pk.vk.beta_g2) {
+        return Err(invalid("the proving key is inconsistent between G1 and G2"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rollup::RollupCircuit;
+    use crate::Skate::SkateParameters;
+    use ark_std::rand::rngs::StdRng;
+    use ark_std::rand::SeedableRng;
+
+    fn keys(seed: u64) -> ProvingKey<Bn254> {
+        let circuit = RollupCircuit::blank(1, 1, &SkateParameters::default(), None).unwrap();
+        generate_parameters(circuit, &mut StdRng::seed_from_u64(seed)).unwrap().0
+    }
+
+    #[test]
+    fn mismatched_keys_are_rejected() {
+        let pk = keys(0);
+        assert!(check_keys(&pk, &pk.vk).is_ok());
+
+        // The verifying key of another setup of the same circuit, or with a public input less
+        assert!(check_keys(&pk, &keys(1).vk).is_err());
+        let mut fewer = pk.vk.clone();
+        fewer.gamma_abc_g1.pop();
+        assert!(check_keys(&pk, &fewer).is_err());
+        let mut delta = pk.vk.clone();
+        delta.delta_g2 = (delta.delta_g2 * Fr::from(2)).into_affine();
+        assert!(check_keys(&pk, &delta).is_err());
+
+        // A proving key whose delta is not the same in G1 and G2
+        let mut inconsistent = pk.clone();
+        inconsistent.delta_g1 = (inconsistent.delta_g1 * Fr::from(2)).into_affine();
+        assert!(check_keys(&inconsistent, &inconsistent.vk).is_err());
+    }
+
+    #[test]
+    fn contributions_are_checked() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let initial = keys(0);
+        let mut last = initial.clone();
+        let contributions = vec![contribute(&mut last, "first", &mut rng), contribute(&mut last, "second", &mut rng)];
+        assert!(verify_contributions(&initial, &last, &contributions, &mut rng).is_ok());
+        assert!(check_keys(&last, &last.vk).is_ok());
+
+        // A missing contribution, a query left undivided, or a changed query
+        assert!(verify_contributions(&initial, &last, &contributions[..1], &mut rng).is_err());
+        let mut undivided = last.clone();
+        undivided.h_query = initial.h_query.clone();
+        assert!(verify_contributions(&initial, &undivided, &contributions, &mut rng).is_err());
+        let mut changed = last.clone();
+        changed.a_query[0] = (changed.a_query[0] * Fr::from(2)).into_affine();
+        assert!(verify_contributions(&initial, &changed, &contributions, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,614 @@
+use crate::error::{Result, SkateError};
+use crate::graph::DistanceTable;
+use crate::hashes::{robot_root, round_seed};
+use crate::lifecycle::{task_state_witness, TaskState, TaskStateWitness};
+use crate::metrics::{evaluate, RoundMetrics, Summary};
+use crate::position::{Position, GRID_SIZE};
+use crate::Skate::{
+    apply_assignments_with_busy, create_robot_with_capabilities, is_expired, skate_assignments_with_busy, travel,
+    DistanceMetric, Robot, SkateParameters, Task, IDLE_TASK_ID, MAX_CAPABILITIES, MAX_DEADLINE,
+    MAX_PREREQUISITES, MAX_PRIORITY,
+};
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use ark_std::rand::rngs::StdRng;
+use ark_std::rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/* Discrete-event simulation of a fleet:
+    - tasks arrive following an arrival process
+    - every `round_period` seconds the whole fleet and the pending tasks are given to SKATE, the robots
+      still moving to their task being busy (see lifecycle::busy_robots)
+    - an assigned robot moves to its task at its speed (grid units per second), the length of the move
+      following the metric of the rounds, and becomes idle again once it reaches it
+   Times are in seconds, rounds are numbered from 1 (round 0 is the genesis state). The robots of the
+   fleet are the leaves of the robot tree: their ids are their indexes */
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct SimulationConfig {
+    pub rounds: u64,
+    pub round_period: f64,
+    pub max_tasks_per_round: usize, // `m` of the round, the oldest pending tasks are assigned first
+    pub seed: u64,                  // seed of the arrival processes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Hotspot {
+    pub center: Position,
+    pub radius: u64, // grid units, positions are drawn uniformly in the square around the center
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct TraceEntry {
+    pub time: f64,
+    pub position: Position,
+    #[serde(default)]
+    pub priority: u32,
+    #[serde(default)]
+    pub deadline: u64,
+    #[serde(default)]
+    pub requirements: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "process", rename_all = "snake_case")]
+pub enum ArrivalProcess {
+    Poisson { rate: f64 },                          // tasks per second, uniform on the grid
+    Hotspots { rate: f64, hotspots: Vec<Hotspot> }, // tasks per second, around weighted hotspots
+    Trace { entries: Vec<TraceEntry> },             // replayed arrivals
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct FleetRobot {
+    pub robot_id: u64,
+    pub capabilities: u32,
+    pub position: Position,
+    pub speed: f64, // grid units per second
+}
+
+// Proof mode: each round transition is proven and verified by the prover
+pub trait RoundProver {
+    // Returns whether the proof of the transition from `robots` to `new_robots` verified
+    fn prove_round(
+        &mut self,
+        round: u64,
+        robots: &Vec<Robot>,
+        tasks: &Vec<Task>,
+        new_robots: &Vec<Robot>,
+        parameters: &SkateParameters,
+        task_state: &TaskStateWitness,
+        road_map: Option<&DistanceTable>,
+    ) -> Result<bool>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TaskRecord {
+    pub task: Task, // task_id is the arrival index of the task
+    pub arrival: f64,
+    pub assigned: Option<f64>,
+    pub completed: Option<f64>,
+    pub robot_id: Option<u64>,
+    pub expired: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    pub tasks: Vec<TaskRecord>,
+    pub rounds: Vec<RoundMetrics>,
+    pub proofs: Vec<bool>, // empty without a prover
+}
+
+impl SimulationReport {
+    pub fn completed(&self) -> usize {
+        self.tasks.iter().filter(|t| t.completed.is_some()).count()
+    }
+
+    pub fn expired(&self) -> usize {
+        self.tasks.iter().filter(|t| t.expired).count()
+    }
+
+    // Mean time between the arrival and the completion of the completed tasks
+    pub fn mean_completion_time(&self) -> Option<f64> {
+        let times: Vec<f64> = self
+            .tasks
+            .iter()
+            .filter_map(|t| t.completed.map(|c| c - t.arrival))
+            .collect();
+        if times.is_empty() {
+            None
+        } else {
+            Some(times.iter().sum::<f64>() / times.len() as f64)
+        }
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary::new(&self.rounds)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum EventKind {
+    // At the same time, arrivals and robots reaching their task happen before the round
+    Arrival(usize),
+    Reached(usize, usize),
+    Round(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Event {
+    time: f64,
+    kind: EventKind,
+}
+
+impl Eq for Event {}
+
+impl Ord for Event {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time
+            .total_cmp(&other.time)
+            .then(self.kind.cmp(&other.kind))
+    }
+}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct Simulator<'a> {
+    config: SimulationConfig,
+    parameters: SkateParameters,
+    fleet: Vec<FleetRobot>,
+    arrivals: ArrivalProcess,
+    road_map: Option<&'a DistanceTable>,
+    prover: Option<&'a mut dyn RoundProver>,
+}
+
+impl<'a> Simulator<'a> {
+    pub fn new(
+        config: SimulationConfig,
+        parameters: SkateParameters,
+        fleet: Vec<FleetRobot>,
+        arrivals: ArrivalProcess,
+    ) -> Simulator<'a> {
+        Simulator {
+            config,
+            parameters,
+            fleet,
+            arrivals,
+            road_map: None,
+            prover: None,
+        }
+    }
+
+    // Tasks are snapped to the nearest node, the robots must start on a node. The travel times of the
+    // road map are lengths in grid units, covered at the speed of the robot
+    pub fn with_road_map(mut self, road_map: &'a DistanceTable) -> Simulator<'a> {
+        self.road_map = Some(road_map);
+        self
+    }
+
+    pub fn with_prover(mut self, prover: &'a mut dyn RoundProver) -> Simulator<'a> {
+        self.prover = Some(prover);
+        self
+    }
+
+    pub fn run(&mut self) -> Result<SimulationReport> {
+        for (r, robot) in self.fleet.iter().enumerate() {
+            if robot.robot_id != r as u64 {
+                return Err(SkateError::InvalidIndex(Fr::from(robot.robot_id), self.fleet.len()));
+            }
+        }
+        let horizon = self.config.rounds as f64 * self.config.round_period;
+        let mut rng = StdRng::seed_from_u64(self.config.seed);
+        let mut tasks = self.generate_tasks(horizon, &mut rng);
+        if let Some(table) = self.road_map {
+            for record in tasks.iter_mut() {
+                record.task.position = table.nearest_node(record.task.position);
+            }
+        }
+
+        let mut events = BinaryHeap::new();
+        for (i, record) in tasks.iter().enumerate() {
+            events.push(Reverse(Event {
+                time: record.arrival,
+                kind: EventKind::Arrival(i),
+            }));
+        }
+        for round in 1..=self.config.rounds {
+            events.push(Reverse(Event {
+                time: round as f64 * self.config.round_period,
+                kind: EventKind::Round(round),
+            }));
+        }
+
+        let mut fleet = self.fleet.clone();
+        let mut previous = vec![Position::default(); fleet.len()];
+        let mut busy = vec![false; fleet.len()];
+        let mut current = vec![IDLE_TASK_ID; fleet.len()]; // task a busy robot is moving to
+        let mut workload = vec![0u64; fleet.len()]; // tasks assigned to each robot, for the fair mode
+        let mut pending: Vec<usize> = vec![]; // arrived and not assigned, oldest first
+        let mut report = SimulationReport {
+            tasks: vec![],
+            rounds: vec![],
+            proofs: vec![],
+        };
+
+        while let Some(Reverse(event)) = events.pop() {
+            match event.kind {
+                EventKind::Arrival(i) => pending.push(i),
+                EventKind::Reached(r, i) => {
+                    previous[r] = fleet[r].position;
+                    fleet[r].position = tasks[i].task.position;
+                    busy[r] = false;
+                    current[r] = IDLE_TASK_ID;
+                    tasks[i].completed = Some(event.time);
+                }
+                EventKind::Round(round) => {
+                    let mut alive = vec![];
+                    for i in pending {
+                        if is_expired(&tasks[i].task, Fr::from(round))? {
+                            tasks[i].expired = true;
+                        } else {
+                            alive.push(i);
+                        }
+                    }
+                    pending = alive;
+
+                    let idle: Vec<usize> = (0..fleet.len()).filter(|r| !busy[*r]).collect();
+                    let batch: Vec<usize> = pending
+                        .iter()
+                        .copied()
+                        .take(self.config.max_tasks_per_round)
+                        .collect();
+                    if idle.is_empty() || batch.is_empty() {
+                        continue;
+                    }
+
+                    let mut robots = vec![];
+                    for r in 0..fleet.len() {
+                        robots.push(Robot {
+                            workload: Fr::from(workload[r]),
+                            ..create_robot_with_capabilities(
+                                Fr::from(fleet[r].robot_id),
+                                Fr::from(fleet[r].capabilities),
+                                [Fr::from(IDLE_TASK_ID), Fr::from(current[r])],
+                                [previous[r], fleet[r].position],
+                            )?
+                        });
+                    }
+                    // Tasks keep their arrival index as id, the batch is in arrival order
+                    let round_tasks: Vec<Task> = batch.iter().map(|i| tasks[*i].task).collect();
+
+                    // The seed the proofs enforce
+                    let parameters = SkateParameters {
+                        seed: round_seed(robot_root(&robots)?.0, Fr::from(round)),
+                        ..self.parameters
+                    };
+                    let assignments =
+                        skate_assignments_with_busy(&robots, &round_tasks, &parameters, self.road_map, &busy)?;
+                    // The quality of the round is the one of the assignment of the idle robots
+                    report.rounds.push(evaluate(
+                        round,
+                        &idle.iter().map(|r| robots[*r]).collect(),
+                        &round_tasks,
+                        &idle.iter().map(|r| assignments[*r]).collect(),
+                        &parameters,
+                        self.road_map,
+                    )?);
+                    if let Some(prover) = self.prover.as_mut() {
+                        // The task-state tree of the unfinished tasks, their ids are their arrival index. The
+                        // tasks over are retired (see lifecycle::TaskLedger::add), two unfinished tasks cannot
+                        // be 2^TASK_STATE_DEPTH arrivals apart
+                        let states = tasks
+                            .iter()
+                            .filter(|record| record.completed.is_none() && !record.expired)
+                            .map(|record| (record.task.task_id, state(record)))
+                            .collect();
+                        let task_state = task_state_witness(&states, &robots, &round_tasks, &assignments)?;
+                        let new_robots = apply_assignments_with_busy(
+                            &robots,
+                            &round_tasks,
+                            &assignments,
+                            parameters.metric,
+                            self.road_map,
+                            &busy,
+                        )?;
+                        report.proofs.push(prover.prove_round(
+                            round,
+                            &robots,
+                            &round_tasks,
+                            &new_robots,
+                            &parameters,
+                            &task_state,
+                            self.road_map,
+                        )?);
+                    }
+
+                    for (k, assignment) in assignments.iter().enumerate() {
+                        let Some(j) = assignment else {
+                            continue;
+                        };
+                        let (r, i) = (k, batch[*j]);
+                        busy[r] = true;
+                        current[r] = i as u64;
+                        workload[r] += 1;
+                        tasks[i].assigned = Some(event.time);
+                        tasks[i].robot_id = Some(fleet[r].robot_id);
+                        pending.retain(|p| *p != i);
+                        let length = self.move_length(fleet[r].position, tasks[i].task.position)?;
+                        events.push(Reverse(Event {
+                            time: event.time + length / fleet[r].speed,
+                            kind: EventKind::Reached(r, i),
+                        }));
+                    }
+                }
+            }
+        }
+
+        report.tasks = tasks;
+        Ok(report)
+    }
+
+    // Length of the move of a robot in grid units: the square root of the squared metrics, the travel time
+    // of the road map taken as a length
+    fn move_length(&self, from: Position, to: Position) -> Result<f64> {
+        let cost = travel(from, to, self.parameters.metric, self.road_map)?.into_bigint().0[0] as f64;
+        Ok(match self.parameters.metric {
+            DistanceMetric::SquaredEuclidean | DistanceMetric::Weighted | DistanceMetric::Planar => cost.sqrt(),
+            DistanceMetric::Manhattan | DistanceMetric::Chebyshev | DistanceMetric::Graph => cost,
+        })
+    }
+
+    fn generate_tasks(&self, horizon: f64, rng: &mut StdRng) -> Vec<TaskRecord> {
+        let mut arrivals: Vec<(f64, Task)> = vec![];
+        match &self.arrivals {
+            ArrivalProcess::Poisson { rate } => {
+                for time in poisson_times(*rate, horizon, rng) {
+                    let position = Position::new(
+                        rng.gen_range(0..GRID_SIZE),
+                        rng.gen_range(0..GRID_SIZE),
+                        rng.gen_range(0..GRID_SIZE),
+                    )
+                    .unwrap();
+                    arrivals.push((time, task_at(position, 0, 0, 0)));
+                }
+            }
+            ArrivalProcess::Hotspots { rate, hotspots } if !hotspots.is_empty() => {
+                let total: f64 = hotspots.iter().map(|h| h.weight).sum();
+                for time in poisson_times(*rate, horizon, rng) {
+                    let mut draw = rng.gen::<f64>() * total;
+                    let hotspot = hotspots
+                        .iter()
+                        .find(|h| {
+                            draw -= h.weight;
+                            draw < 0f64
+                        })
+                        .unwrap_or(&hotspots[hotspots.len() - 1]); // rounding of the last weight
+                    let center = hotspot.center.grid();
+                    let mut grid = [0u64; 3];
+                    for i in 0..3 {
+                        let low = center[i].saturating_sub(hotspot.radius);
+                        let high = (center[i] + hotspot.radius).min(GRID_SIZE - 1);
+                        grid[i] = rng.gen_range(low..=high);
+                    }
+                    let position = Position::new(grid[0], grid[1], grid[2]).unwrap();
+                    arrivals.push((time, task_at(position, 0, 0, 0)));
+                }
+            }
+            ArrivalProcess::Hotspots { .. } => {}
+            ArrivalProcess::Trace { entries } => {
+                for entry in entries.iter().filter(|e| e.time < horizon) {
+                    arrivals.push((
+                        entry.time,
+                        task_at(
+                            entry.position,
+                            entry.priority.min(MAX_PRIORITY),
+                            entry.deadline.min(MAX_DEADLINE),
+                            entry.requirements.min(MAX_CAPABILITIES),
+                        ),
+                    ));
+                }
+            }
+        }
+        arrivals.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        arrivals
+            .into_iter()
+            .enumerate()
+            .map(|(i, (arrival, task))| TaskRecord {
+                task: Task {
+                    task_id: Fr::from(i as u64),
+                    ..task
+                },
+                arrival,
+                assigned: None,
+                completed: None,
+                robot_id: None,
+                expired: false,
+            })
+            .collect()
+    }
+}
+
+fn state(record: &TaskRecord) -> Fr {
+    let state = if record.completed.is_some() {
+        TaskState::Done
+    } else if record.expired {
+        TaskState::Failed
+    } else if record.assigned.is_some() {
+        TaskState::Assigned
+    } else {
+        TaskState::Pending
+    };
+    state.value()
+}
+
+fn task_at(position: Position, priority: u32, deadline: u64, requirements: u32) -> Task {
+    Task {
+        task_id: Fr::from(0),
+        position,
+        priority: Fr::from(priority),
+        deadline: Fr::from(deadline),
+        requirements: Fr::from(requirements),
+        team_size: Fr::from(0),
+        prerequisites: [None; MAX_PREREQUISITES],
+    }
+}
+
+// Exponential inter-arrival times
+fn poisson_times(rate: f64, horizon: f64, rng: &mut StdRng) -> Vec<f64> {
+    let mut times = vec![];
+    if rate <= 0f64 {
+        return times;
+    }
+    let mut time = 0f64;
+    loop {
+        time += -(1f64 - rng.gen::<f64>()).ln() / rate;
+        if time >= horizon {
+            return times;
+        }
+        times.push(time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::is_busy;
+    use crate::Skate::AssignmentMode;
+
+    // Checks what the circuit of the round checks of the robots instead of proving it
+    struct CheckingProver {
+        fleet: usize,
+    }
+
+    impl RoundProver for CheckingProver {
+        fn prove_round(
+            &mut self,
+            _round: u64,
+            robots: &Vec<Robot>,
+            tasks: &Vec<Task>,
+            new_robots: &Vec<Robot>,
+            _parameters: &SkateParameters,
+            task_state: &TaskStateWitness,
+            _road_map: Option<&DistanceTable>,
+        ) -> Result<bool> {
+            // The whole robot tree, each robot at its leaf, and the busy robots keep their task
+            let leaves = robots.iter().enumerate().all(|(r, robot)| robot.robot_id == Fr::from(r as u64));
+            let kept = robots.iter().zip(new_robots).zip(&task_state.robots).all(|((robot, new), (leaf, state, _))| {
+                let task_id = robot.list_tasks[1].task_id;
+                !is_busy(task_id, (*leaf, *state)) || new.list_tasks[1].task_id == task_id
+            });
+            Ok(robots.len() == self.fleet && leaves && kept && task_state.tasks.len() == tasks.len())
+        }
+    }
+
+    fn fleet() -> Vec<FleetRobot> {
+        (0..3)
+            .map(|r| FleetRobot {
+                robot_id: r,
+                capabilities: 0,
+                position: Position::new(r * 4000, 0, 0).unwrap(),
+                speed: 1000f64,
+            })
+            .collect()
+    }
+
+    fn trace(times: &[f64]) -> ArrivalProcess {
+        let entries = times
+            .iter()
+            .enumerate()
+            .map(|(i, time)| TraceEntry {
+                time: *time,
+                position: Position::new(i as u64 * 1000, 5000, 0).unwrap(),
+                priority: 0,
+                deadline: 0,
+                requirements: 0,
+            })
+            .collect();
+        ArrivalProcess::Trace { entries }
+    }
+
+    fn config(rounds: u64) -> SimulationConfig {
+        SimulationConfig {
+            rounds,
+            round_period: 1f64,
+            max_tasks_per_round: 3,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn short_simulation_proves_every_round() {
+        // More tasks than robots: some robots are still moving when the next rounds come
+        let arrivals = trace(&[0.1, 0.2, 0.3, 0.4, 1.5, 2.5, 3.5]);
+        let mut prover = CheckingProver { fleet: 3 };
+        let report = Simulator::new(config(20), SkateParameters::default(), fleet(), arrivals)
+            .with_prover(&mut prover)
+            .run()
+            .unwrap();
+        assert!(!report.proofs.is_empty());
+        assert!(report.proofs.iter().all(|verified| *verified));
+        assert_eq!(report.completed(), 7);
+
+        // A robot has at most one task at a time
+        for robot in 0..3 {
+            let mut moves: Vec<(f64, f64)> = report
+                .tasks
+                .iter()
+                .filter(|t| t.robot_id == Some(robot))
+                .map(|t| (t.assigned.unwrap(), t.completed.unwrap()))
+                .collect();
+            moves.sort_by(|a, b| a.0.total_cmp(&b.0));
+            assert!(moves.windows(2).all(|pair| pair[0].1 <= pair[1].0));
+        }
+    }
+
+    #[test]
+    fn moves_follow_the_metric() {
+        // From (0, 0, 0) to (3000, 5000, 0): 8 seconds in Manhattan, 5 in Chebyshev, about 5.83 in euclidean
+        let fleet = vec![FleetRobot {
+            robot_id: 0,
+            capabilities: 0,
+            position: Position::default(),
+            speed: 1000f64,
+        }];
+        let arrivals = ArrivalProcess::Trace {
+            entries: vec![TraceEntry {
+                time: 0.5,
+                position: Position::new(3000, 5000, 0).unwrap(),
+                priority: 0,
+                deadline: 0,
+                requirements: 0,
+            }],
+        };
+        let durations: Vec<f64> = [DistanceMetric::Manhattan, DistanceMetric::Chebyshev, DistanceMetric::SquaredEuclidean]
+            .iter()
+            .map(|metric| {
+                let parameters = SkateParameters {
+                    mode: AssignmentMode::Distance,
+                    metric: *metric,
+                    ..SkateParameters::default()
+                };
+                let report = Simulator::new(config(1), parameters, fleet.clone(), arrivals.clone()).run().unwrap();
+                report.tasks[0].completed.unwrap() - report.tasks[0].assigned.unwrap()
+            })
+            .collect();
+        assert_eq!(durations[0], 8f64);
+        assert_eq!(durations[1], 5f64);
+        assert!((durations[2] - 34f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn robot_ids_are_their_leaves() {
+        let mut fleet = fleet();
+        fleet.swap(0, 1);
+        let result = Simulator::new(config(1), SkateParameters::default(), fleet, trace(&[0.5])).run();
+        assert!(matches!(result, Err(SkateError::InvalidIndex(..))));
+    }
+}
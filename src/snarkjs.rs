@@ -0,0 +1,189 @@
+use crate::error::{Result, SkateError};
+use crate::proof_system::{Backend, Groth16Backend, ProofSystem};
+use crate::verifying_key::{from_g1, from_g2};
+use ark_bn254::{Bn254, Fr};
+use ark_ff::PrimeField;
+use ark_groth16::Proof;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/* Groth16 proofs and public inputs in the files of snarkjs (proof.json and public.json), so that a proof
+   of the crate is checked by `snarkjs groth16 verify` with the verification_key.json of the circuit (see
+   verifying_key), and a proof of snarkjs by the agents. The points are in projective coordinates as
+   snarkjs writes them, B with its Fq2 coordinates as [c0, c1]. The updates carry the Groth16 proofs in
+   this format (see UpdateProof) */
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofFile {
+    pub pi_a: Vec<String>,
+    pub pi_b: Vec<Vec<String>>,
+    pub pi_c: Vec<String>,
+    pub protocol: String,
+    pub curve: String,
+}
+
+pub fn to_proof_file(proof: &Proof<Bn254>) -> ProofFile {
+    ProofFile {
+        pi_a: from_g1(&proof.a),
+        pi_b: from_g2(&proof.b),
+        pi_c: from_g1(&proof.c),
+        protocol: "groth16".to_string(),
+        curve: "bn128".to_string(),
+    }
+}
+
+// Checked as the proofs of the updates, the points on the curve and B in its subgroup
+pub fn from_proof_file(file: &ProofFile) -> Result<Proof<Bn254>> {
+    if file.protocol != "groth16" || file.curve != "bn128" {
+        return Err(SkateError::Proof(format!("{} proof on {}, expected groth16 on bn128", file.protocol, file.curve)));
+    }
+    let invalid = || SkateError::Proof("invalid point".to_string());
+    let mut strings = vec![];
+    strings.extend(file.pi_a.iter().take(2).cloned());
+    for coordinate in file.pi_b.iter().take(2) {
+        strings.extend(coordinate.iter().take(2).cloned());
+    }
+    strings.extend(file.pi_c.iter().take(2).cloned());
+    // The affine coordinates of snarkjs, the point at infinity has none
+    let affine = |z: Option<&String>| z.is_some_and(|z| z == "1");
+    let b_affine = file.pi_b.get(2).is_some_and(|z| z.len() == 2 && z[0] == "1" && z[1] == "0");
+    if !affine(file.pi_a.get(2)) || !b_affine || !affine(file.pi_c.get(2)) {
+        return Err(invalid());
+    }
+    Groth16Backend.decode(&strings)
+}
+
+pub fn read_proof(path: &Path) -> Result<Proof<Bn254>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))?;
+    let file = serde_json::from_str(&contents)
+        .map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))?;
+    from_proof_file(&file).map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))
+}
+
+pub fn write_proof(path: &Path, proof: &Proof<Bn254>) -> Result<()> {
+    let contents = serde_json::to_string_pretty(&to_proof_file(proof)).unwrap();
+    std::fs::write(path, contents).map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))
+}
+
+// Public inputs as decimal strings, in the order of the circuit
+pub fn read_public_inputs(path: &Path) -> Result<Vec<Fr>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))?;
+    let inputs: Vec<String> = serde_json::from_str(&contents)
+        .map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))?;
+    inputs
+        .iter()
+        .map(|input| {
+            input
+                .parse::<Fr>()
+                .map_err(|_| SkateError::Artifact(format!("{}: invalid input {}", path.display(), input)))
+        })
+        .collect()
+}
+
+pub fn write_public_inputs(path: &Path, inputs: &[Fr]) -> Result<()> {
+    let inputs: Vec<String> = inputs.iter().map(|input| input.into_bigint().to_string()).collect();
+    let contents = serde_json::to_string_pretty(&inputs).unwrap();
+    std::fs::write(path, contents).map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))
+}
+
+// Proof of an update: the proof.json of snarkjs for Groth16, the strings of the backend for PLONK (see
+// ProofSystem::encode). The eight strings of the coordinators predating the format are still accepted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum UpdateProof {
+    Snarkjs(ProofFile),
+    Strings(Vec<String>),
+}
+
+impl UpdateProof {
+    pub fn new(backend: Backend, proof: &[String]) -> Result<UpdateProof> {
+        match backend {
+            Backend::Groth16 => Ok(UpdateProof::Snarkjs(to_proof_file(&Groth16Backend.decode(proof)?))),
+            Backend::Plonk => Ok(UpdateProof::Strings(proof.to_vec())),
+        }
+    }
+
+    // The proof as the strings of its backend
+    pub fn strings(&self) -> Result<Vec<String>> {
+        match self {
+            UpdateProof::Snarkjs(file) => Ok(Groth16Backend.encode(&from_proof_file(file)?)),
+            UpdateProof::Strings(strings) => Ok(strings.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verifying_key::read_verifying_key;
+    use ark_circom::{read_zkey, CircomReduction};
+    use ark_groth16::{prepare_verifying_key, Groth16};
+    use ark_std::rand::rngs::StdRng;
+    use ark_std::rand::SeedableRng;
+    use ark_std::UniformRand;
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::path::PathBuf;
+
+    /* The fixtures are test vectors of ark-circom made by snarkjs: multiplier.zkey and its exported
+       verification key, of c = a * b with c public, and the proof.json and public.json of a
+       `snarkjs groth16 prove` on another circuit, whose key is not shipped */
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/snarkjs").join(name)
+    }
+
+    #[test]
+    fn proofs_are_verified_from_their_files() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let zkey = fixture("multiplier.zkey");
+        let (pk, matrices) = read_zkey(&mut BufReader::new(File::open(zkey).unwrap())).unwrap();
+        let vk = read_verifying_key(&fixture("multiplier_verification_key.json")).unwrap();
+        assert_eq!(vk, pk.vk);
+
+        // The witness of snarkjs for a = 3 and b = 11: 1, c, a, b
+        let witness = [1, 33, 3, 11].map(Fr::from);
+        let proof = Groth16::<Bn254, CircomReduction>::create_proof_with_reduction_and_matrices(
+            &pk,
+            Fr::rand(&mut rng),
+            Fr::rand(&mut rng),
+            &matrices,
+            matrices.num_instance_variables,
+            matrices.num_constraints,
+            &witness,
+        )
+        .unwrap();
+
+        let directory = std::env::temp_dir().join(format!("skate_snarkjs_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let (proof_path, public_path) = (directory.join("proof.json"), directory.join("public.json"));
+        write_proof(&proof_path, &proof).unwrap();
+        write_public_inputs(&public_path, &[Fr::from(33)]).unwrap();
+        let read = (read_proof(&proof_path), read_public_inputs(&public_path));
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let (read_proof, inputs) = (read.0.unwrap(), read.1.unwrap());
+        assert_eq!(read_proof, proof);
+        assert_eq!(inputs, vec![Fr::from(33)]);
+        let vk = prepare_verifying_key(&vk);
+        assert!(Groth16::<Bn254>::verify_proof(&vk, &read_proof, &inputs).unwrap());
+        assert!(!Groth16::<Bn254>::verify_proof(&vk, &read_proof, &[Fr::from(34)]).unwrap());
+    }
+
+    #[test]
+    fn snarkjs_proofs_are_read() {
+        // B with its coordinates as [c0, c1]: swapped, the point is off the curve
+        let proof = read_proof(&fixture("proof.json")).unwrap();
+        let contents = std::fs::read_to_string(fixture("proof.json")).unwrap();
+        let file: ProofFile = serde_json::from_str(&contents).unwrap();
+        assert_eq!(to_proof_file(&proof), file);
+        let mut swapped = file.clone();
+        swapped.pi_b.iter_mut().take(2).for_each(|coordinate| coordinate.swap(0, 1));
+        assert!(from_proof_file(&swapped).is_err());
+
+        let inputs = read_public_inputs(&fixture("public.json")).unwrap();
+        let expected = "20227169454906525228014700210166866282343639252280745415680311389428188660505";
+        assert_eq!(inputs, vec![expected.parse::<Fr>().unwrap()]);
+    }
+}
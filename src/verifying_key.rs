@@ -0,0 +1,134 @@
+use crate::error::{Result, SkateError};
+use ark_bn254::{Bn254, Fq, Fq2, G1Affine, G2Affine};
+use ark_ec::pairing::Pairing;
+use ark_ff::{Field, PrimeField};
+use ark_groth16::VerifyingKey;
+use ark_std::Zero;
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Verifying key exported by snarkjs (verification_key.json)
+#[allow(non_snake_case)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifierFile {
+    pub protocol: String,
+    pub curve: String,
+    pub nPublic: u32,
+    pub vk_alpha_1: Vec<String>,
+    pub vk_beta_2: Vec<Vec<String>>,
+    pub vk_gamma_2: Vec<Vec<String>>,
+    pub vk_delta_2: Vec<Vec<String>>,
+    pub vk_alphabeta_12: Vec<Vec<Vec<String>>>,
+    pub IC: Vec<Vec<String>>,
+}
+
+pub fn read_verifier_file(path: &Path) -> Result<VerifierFile> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))?;
+    serde_json::from_str(&contents)
+        .map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))
+}
+
+pub fn read_verifying_key(path: &Path) -> Result<VerifyingKey<Bn254>> {
+    let json = read_verifier_file(path)?;
+    let invalid = || SkateError::Artifact(format!("{}: invalid point", path.display()));
+    if json.IC.len() != json.nPublic as usize + 1 {
+        return Err(SkateError::Artifact(format!(
+            "{}: {} IC points for {} public inputs",
+            path.display(),
+            json.IC.len(),
+            json.nPublic
+        )));
+    }
+    let mut key = VerifyingKey::default();
+    key.alpha_g1 = to_g1(&json.vk_alpha_1).ok_or_else(invalid)?;
+    key.beta_g2 = to_g2(&json.vk_beta_2).ok_or_else(invalid)?;
+    key.gamma_g2 = to_g2(&json.vk_gamma_2).ok_or_else(invalid)?;
+    key.delta_g2 = to_g2(&json.vk_delta_2).ok_or_else(invalid)?;
+    key.gamma_abc_g1 = json
+        .IC
+        .iter()
+        .map(|point| to_g1(point))
+        .collect::<Option<Vec<G1Affine>>>()
+        .ok_or_else(invalid)?;
+    Ok(key)
+}
+
+pub fn write_verifying_key(path: &Path, vk: &VerifyingKey<Bn254>) -> Result<()> {
+    let alpha_beta = Bn254::pairing(vk.alpha_g1, vk.beta_g2).0;
+    let json = VerifierFile {
+        protocol: "groth16".to_string(),
+        curve: "bn128".to_string(),
+        nPublic: vk.gamma_abc_g1.len() as u32 - 1,
+        vk_alpha_1: from_g1(&vk.alpha_g1),
+        vk_beta_2: from_g2(&vk.beta_g2),
+        vk_gamma_2: from_g2(&vk.gamma_g2),
+        vk_delta_2: from_g2(&vk.delta_g2),
+        vk_alphabeta_12: [alpha_beta.c0, alpha_beta.c1]
+            .iter()
+            .map(|half| [half.c0, half.c1, half.c2].iter().map(from_fq2).collect())
+            .collect(),
+        IC: vk.gamma_abc_g1.iter().map(from_g1).collect(),
+    };
+    let contents = serde_json::to_string_pretty(&json).unwrap();
+    std::fs::write(path, contents).map_err(|e| SkateError::Artifact(format!("{}: {}", path.display(), e)))
+}
+
+fn from_fq(value: &Fq) -> String {
+    BigUint::from(value.into_bigint()).to_string()
+}
+
+fn from_fq2(value: &Fq2) -> Vec<String> {
+    vec![from_fq(&value.c0), from_fq(&value.c1)]
+}
+
+// Projective coordinates as snarkjs writes them, (0, 1, 0) for the point at infinity
+pub fn from_g1(point: &G1Affine) -> Vec<String> {
+    if point.infinity {
+        vec!["0".to_string(), "1".to_string(), "0".to_string()]
+    } else {
+        vec![from_fq(&point.x), from_fq(&point.y), "1".to_string()]
+    }
+}
+
+pub fn from_g2(point: &G2Affine) -> Vec<Vec<String>> {
+    if point.infinity {
+        vec![from_fq2(&Fq2::zero()), from_fq2(&Fq2::ONE), from_fq2(&Fq2::zero())]
+    } else {
+        vec![from_fq2(&point.x), from_fq2(&point.y), from_fq2(&Fq2::ONE)]
+    }
+}
+
+// Affine point from its decimal coordinates, (0, 0) or a zero projective coordinate being the point at
+// infinity
+pub fn to_g1(coordinates: &[String]) -> Option<G1Affine> {
+    let x = coordinates.first()?.parse::<Fq>().ok()?;
+    let y = coordinates.get(1)?.parse::<Fq>().ok()?;
+    if x.is_zero() && y.is_zero() || coordinates.get(2).is_some_and(|z| z == "0") {
+        Some(G1Affine::identity())
+    } else {
+        Some(G1Affine::new_unchecked(x, y))
+    }
+}
+
+pub fn to_g2(coordinates: &[Vec<String>]) -> Option<G2Affine> {
+    let mut elements = vec![];
+    for pair in coordinates.iter().take(2) {
+        elements.push(Fq2::from_base_prime_field_elems(&[
+            pair.first()?.parse::<Fq>().ok()?,
+            pair.get(1)?.parse::<Fq>().ok()?,
+        ])?);
+    }
+    if elements.len() != 2 {
+        return None;
+    }
+    let at_infinity = coordinates
+        .get(2)
+        .is_some_and(|z| z.iter().all(|coordinate| coordinate == "0"));
+    if elements[0].is_zero() && elements[1].is_zero() || at_infinity {
+        Some(G2Affine::identity())
+    } else {
+        Some(G2Affine::new_unchecked(elements[0], elements[1]))
+    }
+}
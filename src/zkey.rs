@@ -0,0 +1,171 @@
+use crate::error::{Result, SkateError};
+use ark_bn254::{Bn254, Fq, Fr, G1Affine, G2Affine};
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::ProvingKey;
+use ark_relations::r1cs::ConstraintMatrices;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/* Writer of Groth16 zkey files in the snarkjs format, read back by ark_circom::read_zkey:
+    1 header (protocol 1 for Groth16)
+    2 Groth16 header: field sizes and primes, numbers of variables and public inputs, domain size,
+      alpha_1, beta_1, beta_2, gamma_2, delta_1, delta_2
+    3 IC, 4 coefficients of the A and B matrices, 5 A, 6 B_1, 7 B_2, 8 C (l_query), 9 H
+    10 contributions, left empty: the ceremony transcripts are kept apart (see setup::Contribution)
+   Coordinates are written in Montgomery form and the coefficients multiplied by R^2, as snarkjs does.
+   The H points must be those of the snarkjs witness map (see setup::generate_parameters), the zkey is
+   proven with CircomReduction */
+
+fn write_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_fq(out: &mut Vec<u8>, value: &Fq) {
+    out.extend_from_slice(&value.0.to_bytes_le());
+}
+
+fn write_g1(out: &mut Vec<u8>, point: &G1Affine) {
+    if point.infinity {
+        out.extend_from_slice(&[0u8; 64]);
+    } else {
+        write_fq(out, &point.x);
+        write_fq(out, &point.y);
+    }
+}
+
+fn write_g2(out: &mut Vec<u8>, point: &G2Affine) {
+    if point.infinity {
+        out.extend_from_slice(&[0u8; 128]);
+    } else {
+        for coordinate in [point.x.c0, point.x.c1, point.y.c0, point.y.c1] {
+            write_fq(out, &coordinate);
+        }
+    }
+}
+
+// Coefficient as snarkjs stores it, the Montgomery form of its Montgomery form
+fn write_coefficient(out: &mut Vec<u8>, value: &Fr) {
+    out.extend_from_slice(&Fr::from_bigint(value.0).unwrap().0.to_bytes_le());
+}
+
+fn zkey_sections(pk: &ProvingKey<Bn254>, matrices: &ConstraintMatrices<Fr>) -> Vec<(u32, Vec<u8>)> {
+    let n_vars = pk.a_query.len();
+    let n_public = pk.vk.gamma_abc_g1.len() - 1;
+
+    let mut header = vec![];
+    write_u32(&mut header, 1);
+
+    let mut groth = vec![];
+    write_u32(&mut groth, 32);
+    groth.extend_from_slice(&Fq::MODULUS.to_bytes_le());
+    write_u32(&mut groth, 32);
+    groth.extend_from_slice(&Fr::MODULUS.to_bytes_le());
+    write_u32(&mut groth, n_vars as u32);
+    write_u32(&mut groth, n_public as u32);
+    write_u32(&mut groth, pk.h_query.len() as u32);
+    write_g1(&mut groth, &pk.vk.alpha_g1);
+    write_g1(&mut groth, &pk.beta_g1);
+    write_g2(&mut groth, &pk.vk.beta_g2);
+    write_g2(&mut groth, &pk.vk.gamma_g2);
+    write_g1(&mut groth, &pk.delta_g1);
+    write_g2(&mut groth, &pk.vk.delta_g2);
+
+    let g1_section = |points: &Vec<G1Affine>| {
+        let mut out = vec![];
+        points.iter().for_each(|point| write_g1(&mut out, point));
+        out
+    };
+
+    // The public inputs are bound by one more A row each, after the constraints
+    let mut coefficients = vec![];
+    let mut count = 0;
+    for (matrix, rows) in [(0, &matrices.a), (1, &matrices.b)] {
+        for (constraint, row) in rows.iter().enumerate().take(matrices.num_constraints) {
+            for (value, signal) in row {
+                write_u32(&mut coefficients, matrix);
+                write_u32(&mut coefficients, constraint as u32);
+                write_u32(&mut coefficients, *signal as u32);
+                write_coefficient(&mut coefficients, value);
+                count += 1;
+            }
+        }
+    }
+    for signal in 0..=n_public {
+        write_u32(&mut coefficients, 0);
+        write_u32(&mut coefficients, (matrices.num_constraints + signal) as u32);
+        write_u32(&mut coefficients, signal as u32);
+        write_coefficient(&mut coefficients, &Fr::from(1u64));
+        count += 1;
+    }
+    let mut section_4 = vec![];
+    write_u32(&mut section_4, count);
+    section_4.extend(coefficients);
+
+    let mut b_g2 = vec![];
+    pk.b_g2_query.iter().for_each(|point| write_g2(&mut b_g2, point));
+
+    // Hash of the circuit and number of contributions
+    let mut contributions = vec![0u8; 64];
+    write_u32(&mut contributions, 0);
+
+    vec![
+        (1, header),
+        (2, groth),
+        (3, g1_section(&pk.vk.gamma_abc_g1)),
+        (4, section_4),
+        (5, g1_section(&pk.a_query)),
+        (6, g1_section(&pk.b_g1_query)),
+        (7, b_g2),
+        (8, g1_section(&pk.l_query)),
+        (9, g1_section(&pk.h_query)),
+        (10, contributions),
+    ]
+}
+
+pub fn write_zkey(path: &Path, pk: &ProvingKey<Bn254>, matrices: &ConstraintMatrices<Fr>) -> Result<()> {
+    let error = |e: std::io::Error| SkateError::Artifact(format!("{}: {}", path.display(), e));
+    let sections = zkey_sections(pk, matrices);
+    let mut writer = BufWriter::new(File::create(path).map_err(error)?);
+    let mut header = b"zkey".to_vec();
+    write_u32(&mut header, 1);
+    write_u32(&mut header, sections.len() as u32);
+    writer.write_all(&header).map_err(error)?;
+    for (id, content) in sections {
+        writer.write_all(&id.to_le_bytes()).map_err(error)?;
+        writer.write_all(&(content.len() as u64).to_le_bytes()).map_err(error)?;
+        writer.write_all(&content).map_err(error)?;
+    }
+    writer.flush().map_err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rollup::RollupCircuit;
+    use crate::setup::generate_parameters;
+    use crate::Skate::SkateParameters;
+    use ark_circom::read_zkey;
+    use ark_std::rand::rngs::StdRng;
+    use ark_std::rand::SeedableRng;
+    use std::io::BufReader;
+
+    #[test]
+    fn zkeys_are_read_back() {
+        let circuit = RollupCircuit::blank(1, 1, &SkateParameters::default(), None).unwrap();
+        let (pk, matrices) = generate_parameters(circuit, &mut StdRng::seed_from_u64(0)).unwrap();
+        let path = std::env::temp_dir().join(format!("skate_zkey_{}.zkey", std::process::id()));
+        write_zkey(&path, &pk, &matrices).unwrap();
+        let read = read_zkey(&mut BufReader::new(File::open(&path).unwrap()));
+        std::fs::remove_file(&path).unwrap();
+
+        // The C matrix is not in the zkey, the prover of CircomReduction does not need it
+        let (read_pk, read_matrices) = read.unwrap();
+        assert_eq!(read_pk, pk);
+        assert_eq!(read_matrices.num_instance_variables, matrices.num_instance_variables);
+        // read_zkey counts the wires of snarkjs but the public inputs, so the constant 1 as a witness
+        assert_eq!(read_matrices.num_witness_variables, matrices.num_witness_variables + 1);
+        assert_eq!(read_matrices.num_constraints, matrices.num_constraints);
+        assert_eq!((read_matrices.a, read_matrices.b), (matrices.a, matrices.b));
+    }
+}